@@ -0,0 +1,131 @@
+//! Files attached to notes. They are copied into an `attachments` directory
+//! next to the notebooks, and notes refer to them by file name. They are
+//! kept as they are, so none can be added to encrypted notes.
+
+use std::{
+    ffi::OsStr,
+    fs, io,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use chrono::Local;
+
+use crate::{
+    crypt::Cipher,
+    model::{Feed, Note, NoteId},
+    store,
+};
+
+/// The directory holding the attachments of the notebooks next to
+/// `data_file`.
+pub fn dir(data_file: &Path) -> PathBuf {
+    store::notebook_dir(data_file).join("attachments")
+}
+
+const ENCRYPTED: &str =
+    "attachments are not encrypted, so the notes here cannot have any";
+
+/// Copies `source` into the attachments directory, under a name starting
+/// with the id of the note it is for, and returns the new name. Fails if the
+/// notes are encrypted with a `cipher`.
+pub fn add(
+    data_file: &Path,
+    note: NoteId,
+    source: &Path,
+    cipher: Option<&Cipher>,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    if cipher.is_some() {
+        return Err(ENCRYPTED.into());
+    }
+    let file_name = source
+        .file_name()
+        .ok_or_else(|| format!("{} is not a file", source.display()))?
+        .to_string_lossy();
+    let dir = dir(data_file);
+    fs::create_dir_all(&dir)?;
+
+    let mut name = PathBuf::from(format!("{}-{}", note, file_name));
+    let mut n = 1;
+    while dir.join(&name).exists() {
+        n += 1;
+        name = PathBuf::from(format!("{}-{}-{}", note, n, file_name));
+    }
+    fs::copy(source, dir.join(&name))?;
+    Ok(name)
+}
+
+/// Writes an image pasted into a note being written, which has no id yet,
+/// into the attachments directory, and returns its name. Fails like [`add`].
+pub fn add_pasted(
+    data_file: &Path,
+    png: &[u8],
+    cipher: Option<&Cipher>,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    if cipher.is_some() {
+        return Err(ENCRYPTED.into());
+    }
+    let dir = dir(data_file);
+    fs::create_dir_all(&dir)?;
+
+    let stamp = Local::now().format("%Y%m%d-%H%M%S");
+    let mut name = PathBuf::from(format!("pasted-{}.png", stamp));
+    let mut n = 1;
+    while dir.join(&name).exists() {
+        n += 1;
+        name = PathBuf::from(format!("pasted-{}-{}.png", stamp, n));
+    }
+    fs::write(dir.join(&name), png)?;
+    Ok(name)
+}
+
+/// How a pasted image is referred to in the text of its note.
+pub fn image_reference(name: &Path) -> String {
+    format!("![image]({})", name.display())
+}
+
+/// Whether the attachment `name` is an image, going by its extension.
+pub fn is_image(name: &Path) -> bool {
+    name.extension().and_then(OsStr::to_str).is_some_and(|e| {
+        ["png", "jpg", "jpeg", "gif", "webp"]
+            .contains(&e.to_lowercase().as_str())
+    })
+}
+
+/// Opens an attachment with the desktop's default application.
+pub fn open(data_file: &Path, name: &Path) -> io::Result<()> {
+    launch(dir(data_file).join(name))
+}
+
+/// Opens a file, or a web address in the browser, with the desktop's
+/// default application.
+pub fn launch(target: impl AsRef<OsStr>) -> io::Result<()> {
+    let opener = if cfg!(target_os = "macos") { "open" } else { "xdg-open" };
+    Command::new(opener)
+        .arg(target)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    Ok(())
+}
+
+/// Deletes the files attached to `removed`, notes that are gone for good,
+/// unless a note still in `feed` refers to them too.
+pub fn remove_orphans(
+    data_file: &Path,
+    feed: &Feed,
+    removed: &[Note],
+) -> io::Result<()> {
+    let dir = dir(data_file);
+    for name in removed.iter().flat_map(|n| n.attachments.iter()) {
+        if feed.notes.iter().any(|n| n.attachments.contains(name)) {
+            continue;
+        }
+        match fs::remove_file(dir.join(name)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+    }
+    Ok(())
+}
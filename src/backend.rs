@@ -0,0 +1,504 @@
+//! The ways the notes of a notebook can be kept on disk. [`store`] locks a
+//! notebook, merges in what other processes saved to it and recovers from
+//! crashes around a [`NoteStore`], which only reads and writes notes, so
+//! keeping them some other way takes one more implementation of it.
+//!
+//! Which one a notebook uses follows from the extension of its file:
+//!
+//! - `.json`, and any extension no other uses: [`JsonFile`].
+//! - `.jsonl`: [`JsonlJournal`].
+//! - `.db`: [`Sqlite`].
+//!
+//! New notebooks get the extension of the `storage` in the config.
+//!
+//! [`store`]: crate::store
+
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    crypt::{self, Cipher},
+    model::{Feed, Note, NoteId},
+    store,
+};
+
+/// Reading and writing the notes of one notebook. Everything is encrypted
+/// with the cipher passed in if there is one.
+pub trait NoteStore {
+    /// Reads all the notes, an empty feed if nothing was saved yet. The feed
+    /// comes back with [`Feed::rewrite`] set when it should be written in
+    /// full, like when it is not encrypted yet but there is a cipher.
+    fn load(
+        &self,
+        cipher: Option<&Cipher>,
+    ) -> Result<Feed, Box<dyn std::error::Error>>;
+
+    /// Writes the whole of `feed`, replacing what was saved before.
+    fn save(
+        &self,
+        feed: &Feed,
+        cipher: Option<&Cipher>,
+    ) -> Result<(), Box<dyn std::error::Error>>;
+
+    /// Writes the notes of `feed` that changed since it was last saved, its
+    /// [`Feed::changed`], or all of it when that is due.
+    fn append(
+        &self,
+        feed: &Feed,
+        cipher: Option<&Cipher>,
+    ) -> Result<(), Box<dyn std::error::Error>>;
+
+    /// The files that change whenever notes are saved, watched to notice
+    /// another process saving and committed when the notebooks are kept in
+    /// git.
+    fn watch(&self) -> Vec<PathBuf>;
+
+    /// Whether the notes saved are encrypted, so a passphrase is needed to
+    /// read them.
+    fn encrypted(&self) -> Result<bool, Box<dyn std::error::Error>>;
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    #[default]
+    Json,
+    Jsonl,
+    Sqlite,
+}
+
+impl Backend {
+    pub const ALL: [Backend; 3] =
+        [Backend::Json, Backend::Jsonl, Backend::Sqlite];
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Backend::Json => "json",
+            Backend::Jsonl => "jsonl",
+            Backend::Sqlite => "db",
+        }
+    }
+
+    /// The backend of the notebook at `path`, by its extension.
+    pub fn of(path: &Path) -> Backend {
+        let extension = path.extension().and_then(|e| e.to_str());
+        Backend::ALL
+            .into_iter()
+            .find(|b| Some(b.extension()) == extension)
+            .unwrap_or(Backend::Json)
+    }
+}
+
+/// The store of the notebook at `path`.
+pub fn open(path: &Path) -> Box<dyn NoteStore> {
+    let path = path.to_path_buf();
+    match Backend::of(&path) {
+        Backend::Json => Box::new(JsonFile { path }),
+        Backend::Jsonl => Box::new(JsonlJournal { path }),
+        Backend::Sqlite => Box::new(Sqlite { path }),
+    }
+}
+
+/// A change to the feed, one JSON line each in a journal.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+enum Entry {
+    /// A note was added or changed; it replaces any note with its id.
+    Put { note: Box<Note> },
+    /// A note was deleted for good.
+    Remove { id: NoteId },
+    /// The highest id given out, written first when a journal is compacted,
+    /// so the ids of notes deleted before are not given out again.
+    Ids { last: NoteId },
+}
+
+/// `value` as a line of JSON, or encrypted and in base64 with `cipher`.
+fn encode(
+    value: &impl Serialize,
+    cipher: Option<&Cipher>,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let json = serde_json::to_string(value)?;
+    match cipher {
+        Some(cipher) => Ok(STANDARD.encode(cipher.encrypt(json.as_bytes())?)),
+        None => Ok(json),
+    }
+}
+
+/// Reads a line written by [`encode`], with or without encryption.
+fn decode<T: DeserializeOwned>(
+    line: &str,
+    cipher: Option<&Cipher>,
+) -> Result<T, Box<dyn std::error::Error>> {
+    if is_plaintext(line) {
+        return Ok(serde_json::from_str(line)?);
+    }
+    let cipher = cipher.ok_or("the notes are encrypted")?;
+    Ok(serde_json::from_slice(&cipher.decrypt(&STANDARD.decode(line)?)?)?)
+}
+
+fn is_plaintext(line: &str) -> bool {
+    line.starts_with('{')
+}
+
+/// The entries of the journal at `path`, none if there is none. A partial
+/// last line, left by a crash in the middle of an append, is skipped.
+fn read_journal(
+    path: &Path,
+    cipher: Option<&Cipher>,
+) -> Result<(Vec<Entry>, bool), Box<dyn std::error::Error>> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return Ok((Vec::new(), false))
+        }
+        Err(e) => return Err(e.into()),
+    };
+    let mut entries = Vec::new();
+    let mut plaintext = false;
+    let count = text.lines().count();
+    for (n, line) in text.lines().enumerate() {
+        plaintext |= is_plaintext(line);
+        match decode(line, cipher) {
+            Ok(entry) => entries.push(entry),
+            Err(_) if n + 1 == count && !text.ends_with('\n') => break,
+            Err(e) => {
+                return Err(
+                    format!("{} line {}: {}", path.display(), n + 1, e).into()
+                )
+            }
+        }
+    }
+    Ok((entries, plaintext))
+}
+
+/// Appends the notes of `feed` that changed to the journal at `path`.
+fn append_changes(
+    path: &Path,
+    feed: &Feed,
+    cipher: Option<&Cipher>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut lines = String::new();
+    for &id in &feed.changed {
+        let entry = match feed.notes.iter().find(|n| n.id == id) {
+            Some(note) => Entry::Put { note: Box::new(note.clone()) },
+            None => Entry::Remove { id },
+        };
+        lines += &encode(&entry, cipher)?;
+        lines.push('\n');
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    file.write_all(lines.as_bytes())?;
+    file.sync_all()?;
+    Ok(())
+}
+
+/// Applies `entries` to `feed`. Only the last entry for each note counts.
+fn replay(feed: &mut Feed, entries: Vec<Entry>) {
+    let mut latest: HashMap<NoteId, Option<Note>> = HashMap::new();
+    for entry in entries {
+        match entry {
+            Entry::Put { note } => {
+                latest.insert(note.id, Some(*note));
+            }
+            Entry::Remove { id } => {
+                feed.last_id = feed.last_id.max(id);
+                latest.insert(id, None);
+            }
+            Entry::Ids { last } => feed.last_id = feed.last_id.max(last),
+        }
+    }
+
+    feed.notes.retain_mut(|note| match latest.remove(&note.id) {
+        Some(Some(changed)) => {
+            *note = changed;
+            true
+        }
+        Some(None) => false,
+        None => true,
+    });
+    let mut added: Vec<Note> = latest.into_values().flatten().collect();
+    added.sort_by_key(|n| n.date);
+    for note in added {
+        feed.put(note);
+    }
+}
+
+/// The whole feed as one JSON file, with a journal next to it,
+/// `<file>.log`, that saves append the changed notes to, so saving stays
+/// quick however many notes there are. The journal is folded back into the
+/// file once it grows bigger than it.
+pub struct JsonFile {
+    path: PathBuf,
+}
+
+/// The journal is folded into the notes file once it is bigger than the
+/// notes file and than this many bytes, which keeps replaying it on load
+/// cheap while a small feed is not rewritten on every other save.
+const COMPACT_MIN: u64 = 64 * 1024;
+
+impl JsonFile {
+    fn journal(&self) -> PathBuf {
+        let mut journal = self.path.as_os_str().to_owned();
+        journal.push(".log");
+        PathBuf::from(journal)
+    }
+
+    fn journal_too_big(&self) -> bool {
+        let size = |p: &Path| fs::metadata(p).map_or(0, |m| m.len());
+        size(&self.journal()) > size(&self.path).max(COMPACT_MIN)
+    }
+}
+
+impl NoteStore for JsonFile {
+    fn load(
+        &self,
+        cipher: Option<&Cipher>,
+    ) -> Result<Feed, Box<dyn std::error::Error>> {
+        let mut encrypted = false;
+        let mut feed: Feed = match fs::read(&self.path) {
+            Ok(data) if crypt::is_encrypted(&data) => {
+                encrypted = true;
+                let cipher = cipher.ok_or_else(|| {
+                    format!("{} is encrypted", self.path.display())
+                })?;
+                serde_json::from_slice(&cipher.decrypt(&data)?)?
+            }
+            Ok(data) => serde_json::from_slice(&data)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Feed::new(),
+            Err(e) => return Err(e.into()),
+        };
+        let (entries, _) = read_journal(&self.journal(), cipher)?;
+        replay(&mut feed, entries);
+        // a plaintext file is encrypted on the first save once there is a
+        // passphrase
+        if cipher.is_some() && !encrypted && self.path.exists() {
+            feed.rewrite = true;
+        }
+        if self.journal_too_big() {
+            feed.rewrite = true;
+        }
+        Ok(feed)
+    }
+
+    fn save(
+        &self,
+        feed: &Feed,
+        cipher: Option<&Cipher>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        store::write(&self.path, feed, cipher)?;
+        match fs::remove_file(self.journal()) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn append(
+        &self,
+        feed: &Feed,
+        cipher: Option<&Cipher>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.path.exists() {
+            return self.save(feed, cipher);
+        }
+        append_changes(&self.journal(), feed, cipher)?;
+        if self.journal_too_big() {
+            return self.save(feed, cipher);
+        }
+        Ok(())
+    }
+
+    fn watch(&self) -> Vec<PathBuf> {
+        vec![self.path.clone(), self.journal()]
+    }
+
+    fn encrypted(&self) -> Result<bool, Box<dyn std::error::Error>> {
+        Ok(crypt::file_is_encrypted(&self.path)?)
+    }
+}
+
+/// Nothing but a journal: one line for each note added, changed or
+/// deleted, in the order it happened, which suits sync tools that merge
+/// files line by line. Once the journal holds more than twice as many lines
+/// as there are notes, it is written again with a line per note.
+pub struct JsonlJournal {
+    path: PathBuf,
+}
+
+impl NoteStore for JsonlJournal {
+    fn load(
+        &self,
+        cipher: Option<&Cipher>,
+    ) -> Result<Feed, Box<dyn std::error::Error>> {
+        let (entries, plaintext) = read_journal(&self.path, cipher)?;
+        let lines = entries.len();
+        let mut feed = Feed::new();
+        replay(&mut feed, entries);
+        feed.rewrite =
+            (cipher.is_some() && plaintext) || lines > 2 * feed.notes.len() + 1;
+        Ok(feed)
+    }
+
+    fn save(
+        &self,
+        feed: &Feed,
+        cipher: Option<&Cipher>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut lines = encode(&Entry::Ids { last: feed.last_id }, cipher)?;
+        lines.push('\n');
+        // oldest first, as if each was just added
+        for note in feed.notes.iter().rev() {
+            lines +=
+                &encode(&Entry::Put { note: Box::new(note.clone()) }, cipher)?;
+            lines.push('\n');
+        }
+        store::write_data(&self.path, lines.as_bytes(), None)
+    }
+
+    fn append(
+        &self,
+        feed: &Feed,
+        cipher: Option<&Cipher>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        append_changes(&self.path, feed, cipher)
+    }
+
+    fn watch(&self) -> Vec<PathBuf> {
+        vec![self.path.clone()]
+    }
+
+    fn encrypted(&self) -> Result<bool, Box<dyn std::error::Error>> {
+        match fs::read_to_string(&self.path) {
+            Ok(text) => {
+                Ok(text.lines().next().is_some_and(|l| !is_plaintext(l)))
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// A row for each note in an SQLite database, holding the note as JSON, or
+/// encrypted like a journal line.
+pub struct Sqlite {
+    path: PathBuf,
+}
+
+impl Sqlite {
+    fn connect(&self) -> rusqlite::Result<Connection> {
+        let db = Connection::open(&self.path)?;
+        db.execute_batch(
+            "CREATE TABLE IF NOT EXISTS notes (
+                id INTEGER PRIMARY KEY,
+                note TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS meta (
+                key TEXT PRIMARY KEY,
+                value INTEGER NOT NULL
+            );",
+        )?;
+        Ok(db)
+    }
+}
+
+impl NoteStore for Sqlite {
+    fn load(
+        &self,
+        cipher: Option<&Cipher>,
+    ) -> Result<Feed, Box<dyn std::error::Error>> {
+        let mut feed = Feed::new();
+        if !self.path.exists() {
+            return Ok(feed);
+        }
+        let db = self.connect()?;
+        let mut rows = db.prepare("SELECT note FROM notes")?;
+        let mut plaintext = false;
+        for row in rows.query_map([], |row| row.get::<_, String>(0))? {
+            let row = row?;
+            plaintext |= is_plaintext(&row);
+            feed.notes.push_back(decode(&row, cipher)?);
+        }
+        feed.notes.make_contiguous().sort_by_key(|n| Reverse(n.date));
+        let last_id: Option<NoteId> = db
+            .query_row(
+                "SELECT value FROM meta WHERE key = 'last_id'",
+                [],
+                |row| row.get(0),
+            )
+            .optional()?;
+        feed.last_id = last_id.unwrap_or(0);
+        feed.rewrite = cipher.is_some() && plaintext;
+        Ok(feed)
+    }
+
+    fn save(
+        &self,
+        feed: &Feed,
+        cipher: Option<&Cipher>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut db = self.connect()?;
+        let tx = db.transaction()?;
+        tx.execute("DELETE FROM notes", [])?;
+        for note in &feed.notes {
+            tx.execute(
+                "INSERT INTO notes (id, note) VALUES (?1, ?2)",
+                params![note.id, encode(note, cipher)?],
+            )?;
+        }
+        set_last_id(&tx, feed.last_id)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn append(
+        &self,
+        feed: &Feed,
+        cipher: Option<&Cipher>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut db = self.connect()?;
+        let tx = db.transaction()?;
+        for &id in &feed.changed {
+            match feed.notes.iter().find(|n| n.id == id) {
+                Some(note) => tx.execute(
+                    "INSERT OR REPLACE INTO notes (id, note) VALUES (?1, ?2)",
+                    params![id, encode(note, cipher)?],
+                )?,
+                None => tx.execute("DELETE FROM notes WHERE id = ?1", [id])?,
+            };
+        }
+        set_last_id(&tx, feed.last_id)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn watch(&self) -> Vec<PathBuf> {
+        vec![self.path.clone()]
+    }
+
+    fn encrypted(&self) -> Result<bool, Box<dyn std::error::Error>> {
+        if !self.path.exists() {
+            return Ok(false);
+        }
+        let note: Option<String> = self
+            .connect()?
+            .query_row("SELECT note FROM notes LIMIT 1", [], |row| row.get(0))
+            .optional()?;
+        Ok(note.is_some_and(|n| !is_plaintext(&n)))
+    }
+}
+
+fn set_last_id(db: &Connection, last_id: NoteId) -> rusqlite::Result<()> {
+    db.execute(
+        "INSERT OR REPLACE INTO meta (key, value) VALUES ('last_id', ?1)",
+        [last_id],
+    )?;
+    Ok(())
+}
@@ -0,0 +1,301 @@
+//! Commands typed as several keys in a row, like `dd` and `gg`, with a count
+//! before them as in `3j`. Keys are taken one at a time as they come, so a
+//! command typed halfway holds up neither drawing nor saving, and it is
+//! dropped if the rest of it does not follow within the timeout.
+//!
+//! Mappings, from `[keys.map]` in the config, make keys stand for others:
+//! typing the keys of one types the keys it maps to instead.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// Keys that make up a command.
+pub struct Chord {
+    /// The count typed before the keys.
+    pub count: Option<usize>,
+    /// The first key, with its modifiers.
+    pub key: KeyEvent,
+    /// The keys typed after the first.
+    pub rest: String,
+}
+
+impl Chord {
+    /// Whether the keys are `keys`.
+    pub fn is(&self, keys: &str) -> bool {
+        match self.key.code {
+            KeyCode::Char(c) => keys.strip_prefix(c) == Some(&self.rest),
+            _ => false,
+        }
+    }
+
+    /// The keys one by one.
+    pub fn keys(&self) -> Vec<KeyEvent> {
+        let rest = self.rest.chars().map(|c| KeyEvent::from(KeyCode::Char(c)));
+        std::iter::once(self.key).chain(rest).collect()
+    }
+}
+
+impl From<KeyEvent> for Chord {
+    fn from(key: KeyEvent) -> Chord {
+        Chord { count: None, key, rest: String::new() }
+    }
+}
+
+/// What a key typed did.
+pub enum Step {
+    /// The keys so far start a command, which waits for the rest of them.
+    Pending,
+    /// The keys are complete, whether or not they make up a command.
+    Run(Chord),
+    /// The keys were dropped, or stood for others, which follow through
+    /// [`Chords::replay`].
+    Ignored,
+}
+
+pub struct Chords {
+    /// The commands of more than one key.
+    sequences: Vec<String>,
+    /// Keys typed and the keys they stand for.
+    mappings: HashMap<String, String>,
+    /// Take digits before a command as its count.
+    counts: bool,
+    timeout: Duration,
+    count: Option<usize>,
+    first: Option<KeyEvent>,
+    pending: String,
+    /// Whether the keys typed so far came from a mapping, so are not mapped
+    /// again.
+    mapped: bool,
+    since: Instant,
+    replay: VecDeque<KeyEvent>,
+}
+
+impl Chords {
+    pub fn new(sequences: Vec<String>, timeout: Duration) -> Chords {
+        Chords {
+            sequences,
+            mappings: HashMap::new(),
+            counts: false,
+            timeout,
+            count: None,
+            first: None,
+            pending: String::new(),
+            mapped: false,
+            since: Instant::now(),
+            replay: VecDeque::new(),
+        }
+    }
+
+    pub fn with_counts(mut self) -> Chords {
+        self.counts = true;
+        self
+    }
+
+    pub fn with_mappings(
+        mut self,
+        mappings: &HashMap<String, String>,
+    ) -> Chords {
+        self.mappings = mappings
+            .iter()
+            .filter(|(keys, _)| !keys.is_empty())
+            .map(|(keys, to)| (keys.clone(), to.clone()))
+            .collect();
+        self
+    }
+
+    /// Takes the next key typed, `mapped` if a mapping typed it.
+    pub fn key(&mut self, key: KeyEvent, mapped: bool) -> Step {
+        self.expire();
+        let plain = key.modifiers.difference(KeyModifiers::SHIFT).is_empty();
+        let c = match key.code {
+            KeyCode::Char(c) if plain => c,
+            // other keys are commands on their own, and cancel one being
+            // typed
+            _ if self.is_empty() => return Step::Run(Chord::from(key)),
+            _ => {
+                self.clear();
+                return Step::Ignored;
+            }
+        };
+
+        if self.counts && self.pending.is_empty() {
+            if let Some(d) =
+                c.to_digit(10).filter(|&d| d > 0 || self.count.is_some())
+            {
+                let count = self.count.unwrap_or(0);
+                self.count =
+                    Some(count.saturating_mul(10).saturating_add(d as usize));
+                self.since = Instant::now();
+                return Step::Pending;
+            }
+        }
+        if self.pending.is_empty() {
+            self.first = Some(key);
+            self.mapped = mapped;
+            self.since = Instant::now();
+        }
+        self.pending.push(c);
+
+        let starts = |keys: &String| {
+            keys.len() > self.pending.len() && keys.starts_with(&self.pending)
+        };
+        let longer = self.sequences.iter().any(starts)
+            || (!self.mapped && self.mappings.keys().any(starts));
+        if longer {
+            return Step::Pending;
+        }
+        if self.map() {
+            return Step::Ignored;
+        }
+        let chord = Chord {
+            count: self.count,
+            key: self.first.unwrap_or(key),
+            rest: self.pending.chars().skip(1).collect(),
+        };
+        self.clear();
+        Step::Run(chord)
+    }
+
+    /// Queues the keys the keys typed so far map to, if there is a mapping
+    /// for them.
+    fn map(&mut self) -> bool {
+        if self.mapped {
+            return false;
+        }
+        let Some(to) = self.mappings.get(&self.pending) else {
+            return false;
+        };
+        let count = self.count.map(|n| n.to_string()).unwrap_or_default();
+        self.replay.extend(count.chars().chain(to.chars()).map(|c| {
+            KeyEvent::from(match c {
+                '\n' => KeyCode::Enter,
+                '\t' => KeyCode::Tab,
+                c => KeyCode::Char(c),
+            })
+        }));
+        self.clear();
+        true
+    }
+
+    /// The next key a mapping typed.
+    pub fn replay(&mut self) -> Option<KeyEvent> {
+        self.replay.pop_front()
+    }
+
+    /// The keys typed so far, for the status bar.
+    pub fn typed(&self) -> String {
+        let count = self.count.map(|n| n.to_string()).unwrap_or_default();
+        count + &self.pending
+    }
+
+    /// How long the keys typed so far wait for the rest.
+    pub fn deadline(&self) -> Option<Duration> {
+        if self.is_empty() {
+            return None;
+        }
+        Some(self.timeout.saturating_sub(self.since.elapsed()))
+    }
+
+    /// Drops the keys typed so far once they have waited too long, or types
+    /// what they map to if they are the keys of a mapping that starts
+    /// another.
+    pub fn expire(&mut self) {
+        if self.deadline() == Some(Duration::ZERO) && !self.map() {
+            self.clear();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.count = None;
+        self.first = None;
+        self.pending.clear();
+    }
+
+    fn is_empty(&self) -> bool {
+        self.count.is_none() && self.pending.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chords(timeout: Duration) -> Chords {
+        Chords::new(vec!["dd".into(), "gg".into()], timeout).with_counts()
+    }
+
+    fn key(c: char) -> KeyEvent {
+        KeyEvent::from(KeyCode::Char(c))
+    }
+
+    /// Types `keys`, expecting all but the last to wait for the rest.
+    fn run(chords: &mut Chords, typed: &str) -> Chord {
+        let keys: Vec<char> = typed.chars().collect();
+        let (last, first) = keys.split_last().unwrap();
+        for &c in first {
+            assert!(matches!(chords.key(key(c), false), Step::Pending));
+        }
+        match chords.key(key(*last), false) {
+            Step::Run(chord) => chord,
+            _ => panic!("{} is not a command", typed),
+        }
+    }
+
+    #[test]
+    fn count_before_keys() {
+        let chord = run(&mut chords(Duration::from_secs(1)), "3dd");
+        assert_eq!(chord.count, Some(3));
+        assert!(chord.is("dd"));
+    }
+
+    #[test]
+    fn zero_is_not_a_count() {
+        let mut chords = chords(Duration::from_secs(1));
+        let chord = run(&mut chords, "0");
+        assert_eq!(chord.count, None);
+        assert_eq!(chord.key, key('0'));
+        assert_eq!(run(&mut chords, "10j").count, Some(10));
+    }
+
+    #[test]
+    fn keys_are_dropped_after_the_timeout() {
+        let mut chords = chords(Duration::ZERO);
+        assert!(matches!(chords.key(key('g'), false), Step::Pending));
+        // the first g is gone, and the second starts over
+        assert!(matches!(chords.key(key('g'), false), Step::Pending));
+        assert_eq!(chords.typed(), "g");
+        chords.expire();
+        assert_eq!(chords.typed(), "");
+    }
+
+    #[test]
+    fn escape_cancels_keys_typed() {
+        let mut chords = chords(Duration::from_secs(1));
+        assert!(matches!(chords.key(key('2'), false), Step::Pending));
+        assert!(matches!(chords.key(key('d'), false), Step::Pending));
+        let esc = KeyEvent::from(KeyCode::Esc);
+        assert!(matches!(chords.key(esc, false), Step::Ignored));
+        assert_eq!(chords.typed(), "");
+        assert_eq!(run(&mut chords, "dd").count, None);
+    }
+
+    #[test]
+    fn mapped_keys_are_not_mapped_again() {
+        let mappings =
+            HashMap::from([("a".into(), "b".into()), ("b".into(), "a".into())]);
+        let mut chords =
+            chords(Duration::from_secs(1)).with_mappings(&mappings);
+        assert!(matches!(chords.key(key('a'), false), Step::Ignored));
+        let b = chords.replay().unwrap();
+        assert_eq!(b, key('b'));
+        match chords.key(b, true) {
+            Step::Run(chord) => assert!(chord.is("b")),
+            _ => panic!("b is not a command"),
+        }
+        assert!(chords.replay().is_none());
+    }
+}
@@ -0,0 +1,571 @@
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, HashSet},
+    fs,
+    io::{self, IsTerminal, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use chrono::{Datelike, Local, Months, Weekday};
+
+use crate::{
+    clipboard, config,
+    crypt::Cipher,
+    dump, export,
+    filter::{FeedView, Query, Sort},
+    import::{self, Format},
+    model::{Feed, Note, NoteId},
+    stats::Stats,
+    store, sync, webdav, webhooks,
+};
+
+pub const USAGE: &str = "\
+usage: feednotes [--data-file <path>] [--readonly] [command]
+
+commands:
+    (none)          open the notes in the terminal UI, without ever changing
+                    them with --readonly
+    add <text>...   add a note and exit, `add -` reads the note from stdin
+    capture         write a note in a composer filling the terminal and exit,
+                    for binding to a global hotkey
+    clip            add the text on the clipboard as a note tagged #clipped,
+                    with the address of the page it was copied from
+    import --format <md|txt|json|org> <path>
+                    add the notes in a directory of Markdown or text files,
+                    in a JSON notes file or under the headings of Org files,
+                    skipping ones already there
+    search [--json] <query>...
+                    print the notes matching a filter query, one per line,
+                    or all of them as JSON with --json
+    export [--format <atom|rss|org|ics>] [--public]
+                    print the notes as an Atom or RSS feed, an Org file or
+                    an iCalendar file of the ones with due: or remind:
+                    dates, only the ones tagged #public with --public
+    archive <months>
+                    move the notes older than that many months into a file
+                    per month under archive/, searched with in:archive
+    stats           print how many notes were written when, and streaks
+    sync            sync the notes through the WebDAV server of [webdav] in
+                    the config, or else pull and push the git repository
+                    they are in
+    dump            print all the notes as JSON, trash included
+    load            replace all the notes with JSON read from stdin, in the
+                    form dump prints them
+    serve           answer HTTP requests listing, adding, changing and
+                    deleting notes, set up under [server] in the config
+";
+
+/// The parsed command line.
+pub struct Args {
+    pub data_file: Option<PathBuf>,
+    /// Browse the notes without writing anything.
+    pub readonly: bool,
+    pub command: Command,
+}
+
+pub enum Command {
+    Tui,
+    Help,
+    Add(Vec<String>),
+    Capture,
+    Clip,
+    Import {
+        format: Format,
+        path: PathBuf,
+    },
+    Search {
+        query: String,
+        json: bool,
+    },
+    /// The format defaults to the one in the config.
+    Export {
+        format: Option<export::Format>,
+        public: bool,
+    },
+    /// Notes older than this many months are archived.
+    Archive(u32),
+    Stats,
+    Sync,
+    Dump,
+    Load,
+    Serve,
+}
+
+impl Args {
+    /// Parses the arguments after the program name. `--data-file` is accepted
+    /// anywhere, everything after the command name belongs to the command.
+    pub fn parse(
+        args: impl IntoIterator<Item = String>,
+    ) -> Result<Args, Box<dyn std::error::Error>> {
+        let mut data_file = None;
+        let mut readonly = false;
+        let mut positional: Vec<String> = Vec::new();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if arg == "--data-file" {
+                let path = args.next().ok_or("--data-file requires a path")?;
+                data_file = Some(PathBuf::from(path));
+            } else if let Some(path) = arg.strip_prefix("--data-file=") {
+                data_file = Some(PathBuf::from(path));
+            } else if arg == "--readonly" {
+                readonly = true;
+            } else if arg == "--" {
+                positional.extend(args.by_ref());
+            } else if arg == "-h" || arg == "--help" {
+                return Ok(Args {
+                    data_file,
+                    readonly,
+                    command: Command::Help,
+                });
+            } else if positional.is_empty() && arg.starts_with('-') {
+                return Err(
+                    format!("unknown option {}, see --help", arg).into()
+                );
+            } else {
+                positional.push(arg);
+            }
+        }
+
+        let command = match positional.first().map(String::as_str) {
+            None => Command::Tui,
+            Some("add") => Command::Add(positional.split_off(1)),
+            Some("capture") => Command::Capture,
+            Some("clip") => Command::Clip,
+            Some("import") => parse_import(&positional[1..])?,
+            Some("search") => {
+                let json = positional.iter().any(|a| a == "--json");
+                let words: Vec<&str> = positional[1..]
+                    .iter()
+                    .map(String::as_str)
+                    .filter(|a| *a != "--json")
+                    .collect();
+                Command::Search { query: words.join(" "), json }
+            }
+            Some("export") => parse_export(&positional[1..])?,
+            Some("archive") => {
+                let months = match &positional[1..] {
+                    [months] => months.parse().ok(),
+                    _ => None,
+                };
+                Command::Archive(
+                    months.ok_or("archive requires a number of months")?,
+                )
+            }
+            Some("stats") => Command::Stats,
+            Some("sync") => Command::Sync,
+            Some("dump") => Command::Dump,
+            Some("load") => Command::Load,
+            Some("serve") => Command::Serve,
+            Some(other) => {
+                return Err(
+                    format!("unknown command {}, see --help", other).into()
+                )
+            }
+        };
+        if readonly && !matches!(command, Command::Tui) {
+            return Err("--readonly only applies to the terminal UI".into());
+        }
+        Ok(Args { data_file, readonly, command })
+    }
+}
+
+fn parse_import(
+    args: &[String],
+) -> Result<Command, Box<dyn std::error::Error>> {
+    let mut format = None;
+    let mut path = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let value = if arg == "--format" {
+            Some(args.next().ok_or("--format requires a value")?.as_str())
+        } else {
+            arg.strip_prefix("--format=")
+        };
+        if let Some(value) = value {
+            format = Some(Format::parse(value).ok_or_else(|| {
+                format!(
+                    "unknown format {}, expected md, txt, json or org",
+                    value
+                )
+            })?);
+        } else if path.is_none() {
+            path = Some(PathBuf::from(arg));
+        } else {
+            return Err(format!("unexpected argument {}", arg).into());
+        }
+    }
+    Ok(Command::Import {
+        format: format
+            .ok_or("import requires --format md, txt, json or org")?,
+        path: path.ok_or("import requires a path")?,
+    })
+}
+
+fn parse_export(
+    args: &[String],
+) -> Result<Command, Box<dyn std::error::Error>> {
+    let mut format = None;
+    let mut public = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let value = if arg == "--format" {
+            Some(args.next().ok_or("--format requires a value")?.as_str())
+        } else {
+            arg.strip_prefix("--format=")
+        };
+        if let Some(value) = value {
+            format = Some(export::Format::parse(value).ok_or_else(|| {
+                format!(
+                    "unknown format {}, expected atom, rss, org or ics",
+                    value
+                )
+            })?);
+        } else if arg == "--public" {
+            public = true;
+        } else {
+            return Err(format!("unexpected argument {}", arg).into());
+        }
+    }
+    Ok(Command::Export { format, public })
+}
+
+/// `feednotes add`: adds the words joined by spaces as a new note, or the
+/// whole of stdin when the only word is `-`.
+pub fn add(
+    data_file: &Path,
+    words: &[String],
+    webhooks: &[String],
+    cipher: Option<&Cipher>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let text = if words == ["-"] {
+        let mut text = String::new();
+        io::stdin().read_to_string(&mut text)?;
+        text.trim_end().to_string()
+    } else {
+        words.join(" ")
+    };
+    if text.trim().is_empty() {
+        return Err("nothing to add".into());
+    }
+    add_note(data_file, text, webhooks, cipher, true)
+}
+
+/// Tag of the notes added by `feednotes clip`.
+pub const CLIPPED_TAG: &str = "clipped";
+
+/// `feednotes clip`: adds the text on the clipboard as a note, tagged
+/// `#clipped` and with the address of the page it came from when the browser
+/// put that on the clipboard too.
+pub fn clip(
+    data_file: &Path,
+    webhooks: &[String],
+    cipher: Option<&Cipher>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut text = clipboard::paste_text()?.trim_end().to_string();
+    if text.trim().is_empty() {
+        return Err("the clipboard is empty".into());
+    }
+    text.push_str("\n\n");
+    if let Some(url) = clipboard::source_url() {
+        text += &format!("{}\n", url);
+    }
+    text += &format!("#{}", CLIPPED_TAG);
+    add_note(data_file, text, webhooks, cipher, true)
+}
+
+/// Adds `text` as a note to `data_file` and sends it to the webhooks, saying
+/// so when that fails rather than failing, the note being saved. When it
+/// writes a recent note again, asks whether to merge it into that one instead
+/// if `ask`, or says so and adds it anyway when not or when there is no
+/// terminal to ask on.
+pub fn add_note(
+    data_file: &Path,
+    text: String,
+    webhooks: &[String],
+    cipher: Option<&Cipher>,
+    ask: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut feed = store::load(data_file, cipher)?;
+    if let Some(i) = feed.duplicate_of(&text) {
+        let date = feed.notes[i].date.format("%Y-%m-%d %H:%M").to_string();
+        if !ask || !io::stdin().is_terminal() {
+            eprintln!("this reads like the note from {}, added anyway", date);
+        } else if ask_merge(&date)? {
+            feed.notes[i].set_text(text);
+            feed.touch(i);
+            store::save(data_file, &mut feed, cipher)?;
+            eprintln!("merged into the note from {}", date);
+            return Ok(());
+        }
+    }
+    feed.push(Note::new(text));
+    let note = feed.notes[0].clone();
+    store::save(data_file, &mut feed, cipher)?;
+    // the note is added all the same
+    if let Err(e) = webhooks::send(webhooks, &note) {
+        eprintln!("cannot send note {} on: {}", note.id, e);
+    }
+    Ok(())
+}
+
+/// Asks on the terminal whether to merge a note into the one from `date` it
+/// writes again, rather than keep both.
+fn ask_merge(date: &str) -> io::Result<bool> {
+    eprint!(
+        "this reads like the note from {}: [m]erge into it or [k]eep both? ",
+        date
+    );
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(answer.trim().eq_ignore_ascii_case("m"))
+}
+
+/// `feednotes import`: adds the notes found at `path` and reports how many
+/// were new.
+pub fn import(
+    data_file: &Path,
+    format: Format,
+    path: &Path,
+    cipher: Option<&Cipher>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let notes = import::read(format, path)?;
+    let found = notes.len();
+    let mut feed = store::load(data_file, cipher)?;
+    let added = import::merge(&mut feed, notes);
+    store::save(data_file, &mut feed, cipher)?;
+    println!(
+        "imported {} notes, skipped {} already in the feed",
+        added,
+        found - added
+    );
+    Ok(())
+}
+
+/// `feednotes search`: prints the notes matching `query`, written the same as
+/// a filter in the UI, newest first. Each note goes on one line after its
+/// date, or with `json` the notes are printed as a JSON array.
+pub fn search(
+    data_file: &Path,
+    query: &str,
+    json: bool,
+    date_format: &str,
+    cipher: Option<&Cipher>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let parsed = Query::parse(query).map_err(|e| e.to_string())?;
+    let mut feed = store::load(data_file, cipher)?;
+    if parsed.in_archive() {
+        for path in store::archives(data_file)? {
+            feed.notes.extend(store::read(&path, cipher)?.notes);
+        }
+        // the feed is kept newest first
+        feed.notes.make_contiguous().sort_by_key(|n| Reverse(n.date));
+    }
+    let view = FeedView::filter(&feed, query, Sort::Newest);
+    let notes: Vec<&Note> = view.refs.iter().map(|&i| &feed.notes[i]).collect();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&notes)?);
+        return Ok(());
+    }
+    let mut out = io::stdout().lock();
+    for note in notes {
+        let text: Vec<&str> = note.text.lines().collect();
+        // a closed pipe, as when piping into head, is not an error
+        if writeln!(
+            out,
+            "{}\t{}",
+            note.date.format(date_format),
+            text.join(" ")
+        )
+        .is_err()
+        {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// `feednotes export`: prints the notes as a feed, in `format` or else the
+/// one of the `[export]` config section, and only the public ones if `public`
+/// or the config says so.
+pub fn export(
+    data_file: &Path,
+    format: Option<export::Format>,
+    public: bool,
+    settings: &config::Export,
+    cipher: Option<&Cipher>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let feed = store::load(data_file, cipher)?;
+    let format = format.unwrap_or(settings.format);
+    print!("{}", render_export(data_file, &feed, format, public, settings));
+    Ok(())
+}
+
+/// `feednotes archive`: moves the notes written more than `months` months
+/// ago into the archive of the month each was written in, and reports how
+/// many were moved. Notes in the trash are left to be purged.
+pub fn archive(
+    data_file: &Path,
+    months: u32,
+    cipher: Option<&Cipher>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut feed = store::load(data_file, cipher)?;
+    let before = Local::now().date_naive() - Months::new(months);
+    let mut by_month: BTreeMap<_, Vec<&Note>> = BTreeMap::new();
+    for note in &feed.notes {
+        let day = note.date.date_naive();
+        if note.deleted_at.is_none() && day < before {
+            by_month.entry(day.with_day(1).unwrap()).or_default().push(note);
+        }
+    }
+
+    // the archives are written first, so a failure leaves notes in both
+    // places rather than in neither
+    let mut moved = HashSet::<NoteId>::new();
+    for (&month, notes) in &by_month {
+        let path = store::archive_path(data_file, month);
+        let mut archive = store::load(&path, cipher)?;
+        for &note in notes {
+            archive.put(note.clone());
+            moved.insert(note.id);
+        }
+        archive.rewrite = true;
+        store::save(&path, &mut archive, cipher)?;
+    }
+    let months = by_month.len();
+    for i in (0..feed.notes.len()).rev() {
+        if moved.contains(&feed.notes[i].id) {
+            feed.remove(i);
+        }
+    }
+    store::save(data_file, &mut feed, cipher)?;
+    println!("archived {} notes into {} months", moved.len(), months);
+    Ok(())
+}
+
+/// `feednotes stats`: prints the totals and a heatmap of the last year.
+pub fn stats(
+    data_file: &Path,
+    week_start: Weekday,
+    cipher: Option<&Cipher>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let feed = store::load(data_file, cipher)?;
+    let today = chrono::Local::now().date_naive();
+    let stats = Stats::new(&feed, today);
+    for line in stats.summary() {
+        println!("{}", line);
+    }
+    println!();
+    for row in stats.heatmap(today, 53, week_start) {
+        println!("{}", row);
+    }
+    Ok(())
+}
+
+/// `feednotes sync`: syncs the notes and says how it went.
+pub fn sync(
+    data_file: &Path,
+    settings: &config::Webdav,
+    cipher: Option<&Cipher>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match sync_notebook(data_file, settings, cipher)? {
+        0 => println!("synced"),
+        n => println!(
+            "synced, {} notes changed on both sides were kept twice, yours \
+             tagged #{}",
+            n,
+            store::CONFLICT_TAG
+        ),
+    }
+    Ok(())
+}
+
+/// Syncs the notebook at `data_file` through WebDAV when `settings` has a
+/// url, or else through the git repository holding the notebooks. Returns
+/// how many notes were kept twice for having changed on both sides.
+pub fn sync_notebook(
+    data_file: &Path,
+    settings: &config::Webdav,
+    cipher: Option<&Cipher>,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    if settings.url.is_some() {
+        return webdav::sync(data_file, settings, cipher);
+    }
+    let dir = store::notebook_dir(data_file);
+    if !sync::is_repo(&dir) {
+        return Err(format!(
+            "{} is not a git repository, and no url is set under [webdav]",
+            dir.display()
+        )
+        .into());
+    }
+    // the remote only ever sees whole notes files, which merge far better
+    // than two journals
+    let mut feed = store::load(data_file, cipher)?;
+    feed.rewrite = true;
+    store::save(data_file, &mut feed, cipher)?;
+    sync::sync(&dir)?;
+    Ok(0)
+}
+
+/// `feednotes dump`: prints every note as JSON in the schema described in
+/// [`dump`].
+pub fn dump(
+    data_file: &Path,
+    cipher: Option<&Cipher>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let feed = store::load(data_file, cipher)?;
+    println!("{}", dump::write(&feed)?);
+    Ok(())
+}
+
+/// `feednotes load`: replaces the notes with the ones of a dump read from
+/// stdin, backing up the notebook first.
+pub fn load(
+    data_file: &Path,
+    backups: usize,
+    cipher: Option<&Cipher>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut json = String::new();
+    io::stdin().read_to_string(&mut json)?;
+    let notes = dump::read(&json)?;
+    let mut feed = store::load(data_file, cipher)?;
+    store::backup(data_file, &feed, cipher, backups)?;
+    let removed = dump::replace(&mut feed, notes)?;
+    store::save(data_file, &mut feed, cipher)?;
+    println!("loaded {} notes, {} removed", feed.notes.len(), removed.len());
+    Ok(())
+}
+
+/// Rewrites the feed file of the `[export]` config section, if there is one.
+pub fn write_export(
+    data_file: &Path,
+    settings: &config::Export,
+    cipher: Option<&Cipher>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(path) = &settings.path else {
+        return Ok(());
+    };
+    let feed = store::load(data_file, cipher)?;
+    let xml = render_export(data_file, &feed, settings.format, false, settings);
+    fs::write(path, xml).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(())
+}
+
+fn render_export(
+    data_file: &Path,
+    feed: &Feed,
+    format: export::Format,
+    public: bool,
+    settings: &config::Export,
+) -> String {
+    let notebook = store::notebook_name(data_file);
+    let channel = export::Channel {
+        title: &settings.title,
+        author: &settings.author,
+        link: settings.link.as_deref(),
+        notebook: &notebook,
+    };
+    export::render(format, feed, &channel, public || settings.public_only)
+}
@@ -0,0 +1,102 @@
+use std::{
+    env,
+    io::{self, Write},
+    process::{Command, Stdio},
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+/// Puts `text` on the system clipboard with an OSC 52 escape sequence, which
+/// the terminal emulator handles itself, so it also works over SSH.
+pub fn copy(text: &str) -> io::Result<()> {
+    let mut stdout = io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", STANDARD.encode(text))?;
+    stdout.flush()
+}
+
+/// The text on the system clipboard, read with `wl-paste` on Wayland, `xclip`
+/// on X11 and `pbpaste` on macOS.
+pub fn paste_text() -> Result<String, Box<dyn std::error::Error>> {
+    let (program, args): (&str, &[&str]) = if cfg!(target_os = "macos") {
+        ("pbpaste", &[])
+    } else if env::var_os("WAYLAND_DISPLAY").is_some() {
+        ("wl-paste", &["--no-newline"])
+    } else {
+        ("xclip", &["-selection", "clipboard", "-out"])
+    };
+    let output = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .map_err(|e| format!("cannot run {}: {}", program, e))?;
+    if !output.status.success() {
+        return Err("the clipboard holds no text".into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// The address of the page the text on the clipboard was copied from, which
+/// Firefox and Chromium put on the clipboard beside it. Never found on macOS.
+pub fn source_url() -> Option<String> {
+    if cfg!(target_os = "macos") {
+        return None;
+    }
+    ["text/x-moz-url-priv", "chromium/x-source-url"].iter().find_map(|kind| {
+        let output = if env::var_os("WAYLAND_DISPLAY").is_some() {
+            Command::new("wl-paste")
+                .args(["--no-newline", "--type", kind])
+                .stdin(Stdio::null())
+                .stderr(Stdio::null())
+                .output()
+        } else {
+            Command::new("xclip")
+                .args(["-selection", "clipboard", "-target", kind, "-out"])
+                .stdin(Stdio::null())
+                .stderr(Stdio::null())
+                .output()
+        }
+        .ok()
+        .filter(|o| o.status.success())?;
+        // Firefox writes it in UTF-16
+        let text = if output.stdout.contains(&0) {
+            let units: Vec<u16> = output
+                .stdout
+                .chunks_exact(2)
+                .map(|c| u16::from_le_bytes([c[0], c[1]]))
+                .collect();
+            String::from_utf16_lossy(&units)
+        } else {
+            String::from_utf8_lossy(&output.stdout).into_owned()
+        };
+        let url = text
+            .lines()
+            .next()?
+            .trim_matches(|c: char| c.is_whitespace() || c == '\0');
+        (url.starts_with("https://") || url.starts_with("http://"))
+            .then(|| url.to_string())
+    })
+}
+
+const PNG_MAGIC: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// The image on the system clipboard as a PNG, or `None` when it holds
+/// something else. It is read with `wl-paste` on Wayland, `xclip` on X11 and
+/// `pngpaste` on macOS, which have to be installed.
+pub fn paste_image() -> Result<Option<Vec<u8>>, Box<dyn std::error::Error>> {
+    let (program, args): (&str, &[&str]) = if cfg!(target_os = "macos") {
+        ("pngpaste", &["-"])
+    } else if env::var_os("WAYLAND_DISPLAY").is_some() {
+        ("wl-paste", &["--no-newline", "--type", "image/png"])
+    } else {
+        ("xclip", &["-selection", "clipboard", "-target", "image/png", "-out"])
+    };
+    let output = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .map_err(|e| format!("cannot run {}: {}", program, e))?;
+    let image = output.status.success() && output.stdout.starts_with(PNG_MAGIC);
+    Ok(image.then_some(output.stdout))
+}
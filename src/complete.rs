@@ -0,0 +1,114 @@
+//! Completing the word before the composer's cursor from a list shown under
+//! it while typing. Emoji shortcodes are completed after a `:`.
+
+use tui_textarea::{CursorMove, TextArea};
+
+use crate::emoji;
+
+/// How many candidates are offered at most.
+const LIMIT: usize = 8;
+
+/// Something the word can be completed to.
+pub struct Candidate {
+    /// What it is shown as in the list.
+    pub label: String,
+    /// What the word is replaced with.
+    pub text: String,
+}
+
+pub struct Completion {
+    /// The row of the word being completed and the column it starts at, in
+    /// characters.
+    row: usize,
+    start: usize,
+    pub candidates: Vec<Candidate>,
+    pub selected: usize,
+}
+
+impl Completion {
+    /// What the word before the cursor of `textarea` can be completed to, if
+    /// it is one that is completed and anything matches it.
+    pub fn new(textarea: &TextArea) -> Option<Completion> {
+        let (row, col) = textarea.cursor();
+        let before: Vec<char> =
+            textarea.lines()[row].chars().take(col).collect();
+        let start = shortcode_start(&before)?;
+        let typed: String = before[start + 1..].iter().collect();
+        if typed.chars().count() < 2 {
+            return None;
+        }
+        let candidates: Vec<Candidate> = emoji::matching(&typed)
+            .into_iter()
+            .take(LIMIT)
+            .map(|(code, emoji)| Candidate {
+                label: format!("{} :{}:", emoji, code),
+                text: emoji.into(),
+            })
+            .collect();
+        if candidates.is_empty() {
+            return None;
+        }
+        Some(Completion { row, start, candidates, selected: 0 })
+    }
+
+    pub fn next(&mut self) {
+        self.selected = (self.selected + 1) % self.candidates.len();
+    }
+
+    pub fn previous(&mut self) {
+        self.selected =
+            (self.selected + self.candidates.len() - 1) % self.candidates.len();
+    }
+
+    /// Replaces the word in `textarea` with the selected candidate.
+    pub fn accept(self, textarea: &mut TextArea) {
+        let (_, col) = textarea.cursor();
+        textarea
+            .move_cursor(CursorMove::Jump(self.row as u16, self.start as u16));
+        textarea.delete_str(col - self.start);
+        textarea.insert_str(&self.candidates[self.selected].text);
+    }
+}
+
+/// Replaces a shortcode closed with the colon just before the cursor of
+/// `textarea`, like `:tada:`, with its emoji. Returns whether there was one.
+pub fn replace_shortcode(textarea: &mut TextArea) -> bool {
+    let (row, col) = textarea.cursor();
+    let before: Vec<char> = textarea.lines()[row].chars().take(col).collect();
+    let Some((':', before)) = before.split_last() else {
+        return false;
+    };
+    let Some(start) = shortcode_start(before) else {
+        return false;
+    };
+    let code: String = before[start + 1..].iter().collect();
+    let Some(emoji) = emoji::get(&code) else {
+        return false;
+    };
+    textarea.move_cursor(CursorMove::Jump(row as u16, start as u16));
+    textarea.delete_str(col - start);
+    textarea.insert_str(emoji);
+    true
+}
+
+/// Where the shortcode `line` ends with starts, at its colon. The colon has
+/// to start a word, so that times like `10:30` are left alone.
+fn shortcode_start(line: &[char]) -> Option<usize> {
+    let name = line
+        .iter()
+        .rev()
+        .take_while(|c| {
+            c.is_ascii_lowercase()
+                || c.is_ascii_digit()
+                || matches!(c, '_' | '+' | '-')
+        })
+        .count();
+    let colon = line.len().checked_sub(name + 1)?;
+    if name == 0 || line[colon] != ':' {
+        return None;
+    }
+    match colon.checked_sub(1).map(|i| line[i]) {
+        Some(c) if c.is_alphanumeric() || c == ':' => None,
+        _ => Some(colon),
+    }
+}
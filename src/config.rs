@@ -0,0 +1,417 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use chrono::{
+    format::{Item, StrftimeItems},
+    Weekday,
+};
+use ratatui::{style::Color, widgets::BorderType};
+use serde::{de, Deserialize, Deserializer};
+use toml_edit::{value, DocumentMut};
+
+use crate::{
+    backend::Backend, export, filter::Sort, geo::Place, i18n::Language, paths,
+    timefmt::Clock,
+};
+
+/// User settings read from `config.toml` in [`dir`]. Anything left
+/// out of the file keeps its default value.
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Ask before moving a note to the trash.
+    pub confirm_delete: bool,
+    /// Soft limit on the length of a note in characters. The composer's
+    /// counter turns red past it, but saving still works.
+    pub char_limit: Option<usize>,
+    /// Column `gq` rewraps paragraphs at in the composer, and lines are
+    /// broken at while typing with `auto_wrap`. Defaults to just inside the
+    /// composer.
+    pub text_width: Option<usize>,
+    /// Break lines at `text_width` while typing in the composer.
+    pub auto_wrap: bool,
+    /// Make `j` and `k` in the composer move by display lines, the parts of
+    /// a long line as wide as the composer, like `gj` and `gk`.
+    pub display_lines: bool,
+    /// Keep the line being written in the middle of the zen composer, opened
+    /// with `gz`, scrolling the text past it.
+    pub typewriter: bool,
+    /// Underline misspelled words in the composer, which needs hunspell.
+    pub spell_check: bool,
+    /// The hunspell dictionary to check spelling with.
+    pub spell_language: String,
+    /// Show what an edit changed before saving it.
+    pub review_edits: bool,
+    /// Encrypt the notes file with a passphrase asked for at startup.
+    pub encrypt: bool,
+    /// Lock the screen after this many minutes without input. It unlocks
+    /// with the passphrase of encrypted notes, and otherwise with one chosen
+    /// when feednotes starts.
+    pub lock_after: Option<u64>,
+    /// The order of the feed, changed from the feed with `s`.
+    pub sort: Sort,
+    /// Start in the journal layout, with the feed grouped by day.
+    pub journal: bool,
+    /// Start with a line for each note in the feed instead of a card,
+    /// changed from the feed with `D`.
+    pub compact: bool,
+    /// Show the feed as a list of titles beside the selected note, changed
+    /// from the feed with `P`.
+    pub split: bool,
+    /// Treat the first line of a note as its title: show it in bold in the
+    /// feed and name files the note is exported to after it.
+    pub titles: bool,
+    /// Save changes on their own shortly after they are made. Without it
+    /// they are saved with `:w` and on quitting.
+    pub autosave: bool,
+    /// Seconds changes wait to be saved with `autosave`, by default a
+    /// fraction of one.
+    pub autosave_after: Option<u64>,
+    /// How new notebooks are kept on disk: json, one JSON file with a journal
+    /// of the latest changes, jsonl, only the journal, or sqlite, an SQLite
+    /// database. Existing notebooks keep theirs.
+    pub storage: Backend,
+    /// The notebook opened when no file is given with `--data-file` or
+    /// `FEEDNOTES_DATA`.
+    pub notebook: String,
+    /// How many daily backups of each notebook to keep, 0 for none.
+    pub backups: usize,
+    /// The language of the UI, en or ja. Defaults to the one of the locale.
+    pub language: Option<Language>,
+    /// A command printing where the computer is as `<lat>,<lon>`, run to
+    /// give notes a location with `here`.
+    pub location_command: Option<String>,
+    /// Give every new note the location `location_command` prints.
+    pub locate_new_notes: bool,
+    /// Places notes can be given as their location and found near with
+    /// `near:<name>`.
+    pub places: BTreeMap<String, Place>,
+    /// Words replaced with a text when typed followed by a space, see
+    /// [`snippets`](crate::snippets).
+    pub snippets: BTreeMap<String, String>,
+    /// Addresses every new note is POSTed to as JSON.
+    pub webhooks: Vec<String>,
+    /// The day weeks start on in the calendar and the stats heatmap.
+    #[serde(deserialize_with = "deserialize_weekday")]
+    pub week_start: Weekday,
+    pub keys: Keys,
+    pub theme: Theme,
+    pub layout: Layout,
+    pub export: Export,
+    pub mastodon: Mastodon,
+    pub server: Server,
+    pub webdav: Webdav,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            confirm_delete: true,
+            char_limit: None,
+            text_width: None,
+            auto_wrap: false,
+            display_lines: false,
+            typewriter: false,
+            spell_check: false,
+            spell_language: "en_US".into(),
+            review_edits: false,
+            encrypt: false,
+            lock_after: None,
+            sort: Sort::default(),
+            journal: false,
+            compact: false,
+            split: false,
+            titles: false,
+            autosave: true,
+            autosave_after: None,
+            storage: Backend::default(),
+            notebook: "notes".into(),
+            backups: 7,
+            language: None,
+            location_command: None,
+            locate_new_notes: false,
+            places: BTreeMap::new(),
+            snippets: BTreeMap::new(),
+            webhooks: Vec::new(),
+            week_start: Weekday::Mon,
+            keys: Keys::default(),
+            theme: Theme::default(),
+            layout: Layout::default(),
+            export: Export::default(),
+            mastodon: Mastodon::default(),
+            server: Server::default(),
+            webdav: Webdav::default(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct Keys {
+    pub next: char,
+    pub previous: char,
+    /// Keys pressed in sequence to delete the selected note.
+    pub delete: String,
+    /// Keys pressed in sequence in the composer's normal mode to save.
+    pub save: String,
+    /// Milliseconds the keys of a command wait for the rest of them.
+    pub timeout: u64,
+    /// Keys typed in the feed that stand for others, like `"gt" = "/#todo\n"`.
+    pub map: HashMap<String, String>,
+}
+
+impl Keys {
+    pub fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout)
+    }
+}
+
+impl Default for Keys {
+    fn default() -> Self {
+        Keys {
+            next: 'j',
+            previous: 'k',
+            delete: "dd".into(),
+            save: "W".into(),
+            timeout: 1000,
+            map: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    #[serde(deserialize_with = "deserialize_color")]
+    pub selection_bg: Color,
+    #[serde(deserialize_with = "deserialize_border_type")]
+    pub border_type: BorderType,
+    /// Defaults to the date and the time on `clock`.
+    pub date_format: Option<String>,
+    /// A 24-hour or a 12-hour clock, for the default `date_format` and
+    /// relative dates.
+    pub clock: Clock,
+    /// Show how long ago notes were written instead of `date_format`.
+    pub relative_dates: bool,
+}
+
+impl Theme {
+    pub fn date_format(&self) -> &str {
+        self.date_format.as_deref().unwrap_or(self.clock.date_format())
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            selection_bg: Color::Rgb(45, 50, 55),
+            border_type: BorderType::Rounded,
+            date_format: None,
+            clock: Clock::default(),
+            relative_dates: false,
+        }
+    }
+}
+
+/// Sizes in terminal cells. Popups shrink to fit terminals smaller than
+/// these.
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct Layout {
+    /// Width of the feed, which is centered.
+    pub feed_width: u16,
+    /// Width of the composer and the other popups.
+    pub popup_width: u16,
+    pub composer_height: u16,
+    /// Rows between the top of the terminal and the popups.
+    pub popup_top: u16,
+    /// Space between the border of a note card and its text.
+    pub card_padding: u16,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Layout {
+            feed_width: 80,
+            popup_width: 60,
+            composer_height: 10,
+            popup_top: 10,
+            card_padding: 1,
+        }
+    }
+}
+
+/// Publishing the notes as an Atom or RSS feed, also done on demand with
+/// `feednotes export`.
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct Export {
+    /// File rewritten with the feed when the UI is closed and after `add` or
+    /// `import`. Nothing is written unless it is set.
+    pub path: Option<PathBuf>,
+    pub format: export::Format,
+    /// Only publish notes tagged `#public`.
+    pub public_only: bool,
+    pub title: String,
+    pub author: String,
+    /// Where the feed is published.
+    pub link: Option<String>,
+}
+
+impl Default for Export {
+    fn default() -> Self {
+        Export {
+            path: None,
+            format: export::Format::default(),
+            public_only: false,
+            title: "feednotes".into(),
+            author: "feednotes".into(),
+            link: None,
+        }
+    }
+}
+
+/// Posting notes to Mastodon with `p` from the feed.
+#[derive(Default, Deserialize)]
+#[serde(default)]
+pub struct Mastodon {
+    /// The server's address, e.g. `https://mastodon.social`.
+    pub instance: Option<String>,
+    /// An access token with the `write:statuses` scope.
+    pub token: Option<String>,
+    /// public, unlisted, private or direct. The account's default if unset.
+    pub visibility: Option<String>,
+}
+
+/// The HTTP API of `feednotes serve`.
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct Server {
+    /// The address and port to listen on.
+    pub listen: String,
+    /// Sent by clients as `Authorization: Bearer <token>`. The server does
+    /// not start without one.
+    pub token: Option<String>,
+}
+
+impl Default for Server {
+    fn default() -> Self {
+        Server { listen: "127.0.0.1:8087".into(), token: None }
+    }
+}
+
+/// Syncing the notebooks through a WebDAV server with `S` and
+/// `feednotes sync`, instead of git.
+#[derive(Default, Deserialize)]
+#[serde(default)]
+pub struct Webdav {
+    /// The folder the notebooks are kept in, e.g.
+    /// `https://cloud.example.com/remote.php/dav/files/me/feednotes`.
+    pub url: Option<String>,
+    pub user: Option<String>,
+    /// Better an app password than the one of the account.
+    pub password: Option<String>,
+}
+
+/// Where the config file and templates are kept, e.g. `~/.config/feednotes`.
+pub fn dir() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    paths::config_dir()
+}
+
+impl Config {
+    pub fn path() -> Result<String, Box<dyn std::error::Error>> {
+        Ok(dir()?.join("config.toml").to_string_lossy().into_owned())
+    }
+
+    pub fn load() -> Result<Config, Box<dyn std::error::Error>> {
+        let path = Config::path()?;
+        let config: Config = match fs::read_to_string(&path) {
+            Ok(s) => {
+                toml::from_str(&s).map_err(|e| format!("{}: {}", path, e))?
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Config::default(),
+            Err(e) => return Err(e.into()),
+        };
+
+        let date_format = config.theme.date_format();
+        if StrftimeItems::new(date_format).any(|i| matches!(i, Item::Error)) {
+            return Err(format!(
+                "{}: invalid date_format `{}`, see \
+                https://docs.rs/chrono/latest/chrono/format/strftime/ for \
+                what it can contain",
+                path, date_format
+            )
+            .into());
+        }
+        Ok(config)
+    }
+
+    /// Writes `sort` to the config file, leaving the rest of it, comments
+    /// included, as it was.
+    pub fn save_sort(sort: Sort) -> Result<(), Box<dyn std::error::Error>> {
+        Config::save_value("sort", value(sort.name()))
+    }
+
+    /// Writes `split` to the config file like [`Config::save_sort`].
+    pub fn save_split(split: bool) -> Result<(), Box<dyn std::error::Error>> {
+        Config::save_value("split", value(split))
+    }
+
+    fn save_value(
+        key: &str,
+        item: toml_edit::Item,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let path = Config::path()?;
+        let text = match fs::read_to_string(&path) {
+            Ok(s) => s,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+        let mut doc: DocumentMut =
+            text.parse().map_err(|e| format!("{}: {}", path, e))?;
+        doc[key] = item;
+        if let Some(dir) = Path::new(&path).parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&path, doc.to_string())?;
+        Ok(())
+    }
+}
+
+fn deserialize_color<'de, D: Deserializer<'de>>(
+    d: D,
+) -> Result<Color, D::Error> {
+    let s = String::deserialize(d)?;
+    s.parse().map_err(|_| de::Error::custom(format!("invalid color `{}`", s)))
+}
+
+fn deserialize_weekday<'de, D: Deserializer<'de>>(
+    d: D,
+) -> Result<Weekday, D::Error> {
+    let s = String::deserialize(d)?;
+    s.parse().map_err(|_| {
+        de::Error::custom(format!(
+            "invalid day `{}`, expected monday, tuesday, ... or sunday",
+            s
+        ))
+    })
+}
+
+fn deserialize_border_type<'de, D: Deserializer<'de>>(
+    d: D,
+) -> Result<BorderType, D::Error> {
+    match String::deserialize(d)?.as_str() {
+        "plain" => Ok(BorderType::Plain),
+        "rounded" => Ok(BorderType::Rounded),
+        "double" => Ok(BorderType::Double),
+        "thick" => Ok(BorderType::Thick),
+        s => Err(de::Error::custom(format!(
+            "invalid border type `{}`, expected plain, rounded, double or thick",
+            s
+        ))),
+    }
+}
@@ -0,0 +1,153 @@
+//! Encryption of the notes file at rest. An encrypted file is the magic
+//! header, the salt the key was derived with, a nonce and the
+//! ChaCha20-Poly1305 sealed JSON, in that order. The key is derived from a
+//! passphrase with Argon2id.
+
+use std::{
+    cell::RefCell,
+    fs::File,
+    io::{self, Read},
+    path::Path,
+};
+
+use argon2::Argon2;
+use chacha20poly1305::{
+    aead::{rand_core::RngCore, Aead, AeadCore, KeyInit, OsRng},
+    ChaCha20Poly1305, Key, Nonce,
+};
+
+use crate::backend;
+
+const MAGIC: &[u8] = b"feednotes-encrypted-v1\n";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// A passphrase and the key derived from it.
+#[derive(Clone)]
+pub struct Cipher {
+    passphrase: String,
+    salt: [u8; SALT_LEN],
+    key: Key,
+    /// Keys derived for other salts, so data written over several sessions
+    /// costs one derivation per session rather than one per record.
+    other_keys: RefCell<Vec<([u8; SALT_LEN], Key)>>,
+}
+
+impl Cipher {
+    /// Derives a key from `passphrase` with a fresh salt.
+    pub fn new(
+        passphrase: String,
+    ) -> Result<Cipher, Box<dyn std::error::Error>> {
+        let mut salt = [0; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        let key = derive_key(&passphrase, &salt)?;
+        Ok(Cipher { passphrase, salt, key, other_keys: RefCell::default() })
+    }
+
+    /// Whether `passphrase` is the one the key was derived from.
+    pub fn is_passphrase(&self, passphrase: &str) -> bool {
+        self.passphrase == passphrase
+    }
+
+    pub fn encrypt(
+        &self,
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let sealed = ChaCha20Poly1305::new(&self.key)
+            .encrypt(&nonce, plaintext)
+            .map_err(|_| "encryption failed")?;
+
+        let mut data = MAGIC.to_vec();
+        data.extend_from_slice(&self.salt);
+        data.extend_from_slice(&nonce);
+        data.extend_from_slice(&sealed);
+        Ok(data)
+    }
+
+    /// Opens `data` written by [`Cipher::encrypt`]. Files sealed under
+    /// another salt, like other notebooks, are opened by deriving their key
+    /// from the same passphrase.
+    pub fn decrypt(
+        &self,
+        data: &[u8],
+    ) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let header = MAGIC.len() + SALT_LEN + NONCE_LEN;
+        if !is_encrypted(data) || data.len() < header {
+            return Err("not an encrypted notes file".into());
+        }
+        let salt = &data[MAGIC.len()..MAGIC.len() + SALT_LEN];
+        let nonce = Nonce::from_slice(&data[MAGIC.len() + SALT_LEN..header]);
+
+        let key = if salt == self.salt {
+            self.key
+        } else {
+            let mut other_keys = self.other_keys.borrow_mut();
+            match other_keys.iter().find(|(s, _)| s == salt) {
+                Some((_, key)) => *key,
+                None => {
+                    let key = derive_key(&self.passphrase, salt)?;
+                    other_keys.push((salt.try_into()?, key));
+                    key
+                }
+            }
+        };
+        ChaCha20Poly1305::new(&key)
+            .decrypt(nonce, &data[header..])
+            .map_err(|_| "wrong passphrase or corrupted notes file".into())
+    }
+}
+
+fn derive_key(
+    passphrase: &str,
+    salt: &[u8],
+) -> Result<Key, Box<dyn std::error::Error>> {
+    let mut key = Key::default();
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| e.to_string())?;
+    Ok(key)
+}
+
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+/// Whether the file at `path` starts like one written by
+/// [`Cipher::encrypt`]. A missing file is not.
+pub fn file_is_encrypted(path: &Path) -> io::Result<bool> {
+    let mut head = [0; MAGIC.len()];
+    match File::open(path) {
+        Ok(mut file) => match file.read_exact(&mut head) {
+            Ok(()) => Ok(is_encrypted(&head)),
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+            Err(e) => Err(e),
+        },
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// Prompts for the passphrase when `data_file` is encrypted or `encrypt` is
+/// set, and `None` when neither is the case. A passphrase that is about to
+/// encrypt a plaintext file for the first time is asked for twice.
+pub fn unlock(
+    data_file: &Path,
+    encrypt: bool,
+) -> Result<Option<Cipher>, Box<dyn std::error::Error>> {
+    let encrypted = backend::open(data_file).encrypted()?;
+    if !encrypted && !encrypt {
+        return Ok(None);
+    }
+
+    let passphrase = rpassword::prompt_password("Passphrase: ")?;
+    if passphrase.is_empty() {
+        return Err("empty passphrase".into());
+    }
+    if !encrypted
+        && rpassword::prompt_password("Repeat passphrase: ")? != passphrase
+    {
+        return Err("passphrases do not match".into());
+    }
+    Ok(Some(Cipher::new(passphrase)?))
+}
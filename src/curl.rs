@@ -0,0 +1,76 @@
+//! Requests made by `curl`, for Mastodon, webhooks and WebDAV. The address
+//! and the rest of a request are given to curl on stdin, in the form of its
+//! config files, so tokens and passwords never show up in the process list.
+
+use std::{
+    io::Write,
+    process::{Child, Command, Stdio},
+};
+
+/// What makes up a request, as the lines of a curl config file.
+#[derive(Default)]
+pub struct Options(String);
+
+impl Options {
+    /// Adds the option `name`, e.g. `url` or `header`, set to `value`.
+    pub fn push(&mut self, name: &str, value: &str) {
+        self.0 += &format!("{} = \"{}\"\n", name, quote(value));
+    }
+}
+
+/// Runs `curl` with `args` and `options`, and returns what it printed,
+/// failing with curl's own message when it exits unsuccessfully.
+pub fn run(
+    args: &[&str],
+    options: &Options,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let output = spawn(args, options, true)?.wait_with_output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(stderr.trim().to_string().into());
+    }
+    Ok(output.stdout)
+}
+
+/// Starts `curl` with `args` and `options`, and leaves it running, what it
+/// prints thrown away.
+pub fn start(
+    args: &[&str],
+    options: &Options,
+) -> Result<Child, Box<dyn std::error::Error>> {
+    spawn(args, options, false)
+}
+
+fn spawn(
+    args: &[&str],
+    options: &Options,
+    piped: bool,
+) -> Result<Child, Box<dyn std::error::Error>> {
+    let output = || if piped { Stdio::piped() } else { Stdio::null() };
+    let mut curl = Command::new("curl")
+        .args(args)
+        .args(["--config", "-"])
+        .stdin(Stdio::piped())
+        .stdout(output())
+        .stderr(output())
+        .spawn()
+        .map_err(|e| format!("cannot run curl: {}", e))?;
+    curl.stdin.take().unwrap().write_all(options.0.as_bytes())?;
+    Ok(curl)
+}
+
+/// Escapes `s` for a double-quoted value of a curl config file.
+fn quote(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c => out.push(c),
+        }
+    }
+    out
+}
@@ -0,0 +1,116 @@
+//! Word by word differences between two versions of a note.
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    Same,
+    Removed,
+    Added,
+}
+
+/// Above this many pairs of differing words the changed part is shown as
+/// removed and added whole, rather than taking long to compare.
+const MAX_PAIRS: usize = 4_000_000;
+
+/// The words and the whitespace between them of `old` and `new`, in order,
+/// each marked as in both, only in `old` or only in `new`.
+pub fn words<'a>(old: &'a str, new: &'a str) -> Vec<(Change, &'a str)> {
+    let old = tokens(old);
+    let new = tokens(new);
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let a = &old[prefix..old.len() - suffix];
+    let b = &new[prefix..new.len() - suffix];
+
+    let mut diff: Vec<(Change, &str)> =
+        old[..prefix].iter().map(|t| (Change::Same, *t)).collect();
+    if a.len().saturating_mul(b.len()) > MAX_PAIRS {
+        diff.extend(a.iter().map(|t| (Change::Removed, *t)));
+        diff.extend(b.iter().map(|t| (Change::Added, *t)));
+    } else {
+        diff.extend(lcs(a, b));
+    }
+    diff.extend(old[old.len() - suffix..].iter().map(|t| (Change::Same, *t)));
+    diff
+}
+
+/// How alike `a` and `b` are word by word, from 0 when they have no words
+/// in common in the same order to 1 when they have the same words.
+pub fn similarity(a: &str, b: &str) -> f64 {
+    let (mut same, mut all) = (0, 0);
+    for (change, token) in words(a, b) {
+        if token.trim().is_empty() {
+            continue;
+        }
+        // a word in both counts for each of them
+        let n = if change == Change::Same { 2 } else { 1 };
+        all += n;
+        if change == Change::Same {
+            same += n;
+        }
+    }
+    if all == 0 {
+        return 1.0;
+    }
+    same as f64 / all as f64
+}
+
+/// The difference between `a` and `b` through their longest common
+/// subsequence, removals before additions where both happen.
+fn lcs<'a>(a: &[&'a str], b: &[&'a str]) -> Vec<(Change, &'a str)> {
+    // longest[i][j] is the length of the longest common subsequence of
+    // a[i..] and b[j..]
+    let width = b.len() + 1;
+    let mut longest = vec![0u32; (a.len() + 1) * width];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            longest[i * width + j] = if a[i] == b[j] {
+                longest[(i + 1) * width + j + 1] + 1
+            } else {
+                longest[(i + 1) * width + j].max(longest[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut diff = Vec::with_capacity(a.len() + b.len());
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            diff.push((Change::Same, a[i]));
+            i += 1;
+            j += 1;
+        } else if j == b.len()
+            || i < a.len()
+                && longest[(i + 1) * width + j] >= longest[i * width + j + 1]
+        {
+            diff.push((Change::Removed, a[i]));
+            i += 1;
+        } else {
+            diff.push((Change::Added, b[j]));
+            j += 1;
+        }
+    }
+    diff
+}
+
+/// Splits `text` into runs of whitespace and of everything else.
+fn tokens(text: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    let mut space = None;
+    for (i, c) in text.char_indices() {
+        if space.is_some_and(|s| s != c.is_whitespace()) {
+            tokens.push(&text[start..i]);
+            start = i;
+        }
+        space = Some(c.is_whitespace());
+    }
+    if start < text.len() {
+        tokens.push(&text[start..]);
+    }
+    tokens
+}
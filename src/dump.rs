@@ -0,0 +1,120 @@
+//! The notes of a notebook as JSON for other programs to read and write,
+//! with `feednotes dump` and `feednotes load`.
+//!
+//! The document is an object holding the `version` of its schema and the
+//! `notes`, newest first:
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "notes": [
+//!     {
+//!       "id": 2,
+//!       "text": "Finished the #book",
+//!       "date": "2024-05-01T21:30:00+02:00",
+//!       "modified": null,
+//!       "tags": ["book"],
+//!       "deleted_at": null,
+//!       "reply_to": 1,
+//!       "attachments": [],
+//!       "color": null,
+//!       "starred": false,
+//!       "posted": null,
+//!       "revisions": [{ "text": "...", "date": "..." }],
+//!       "location": { "lat": 35.6812, "lon": 139.7671, "name": "home" },
+//!       "revision": 3,
+//!       "last_writer": "laptop"
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! `id`, `text` and `date` are required when loading, and the other fields
+//! default to empty. Notes with id 0 are new and are given an id. Tags are
+//! always taken from the text. The version goes up whenever the schema
+//! changes in a way older readers would get wrong, and documents of older
+//! versions are migrated when loaded.
+
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::model::{extract_tags, Feed, Note, NoteId};
+
+/// The version of the schema written by [`write`].
+pub const VERSION: u64 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct Dump<N> {
+    version: u64,
+    notes: Vec<N>,
+}
+
+/// The notes of `feed`, trash included.
+pub fn write(feed: &Feed) -> serde_json::Result<String> {
+    let notes: Vec<&Note> = feed.notes.iter().collect();
+    serde_json::to_string_pretty(&Dump { version: VERSION, notes })
+}
+
+/// The notes of a document written by [`write`], of this or an older
+/// version.
+pub fn read(json: &str) -> Result<Vec<Note>, Box<dyn std::error::Error>> {
+    let dump: Value = serde_json::from_str(json)?;
+    let version = dump
+        .get("version")
+        .and_then(Value::as_u64)
+        .ok_or("missing schema version")?;
+    if version > VERSION {
+        return Err(format!(
+            "schema version {} is newer than the {} this feednotes reads",
+            version, VERSION
+        )
+        .into());
+    }
+    // migrations from older versions go here, each bringing `dump` one
+    // version forward
+    if version != VERSION {
+        return Err(format!("unknown schema version {}", version).into());
+    }
+    let dump: Dump<Note> = serde_json::from_value(dump)?;
+    Ok(dump.notes)
+}
+
+/// Replaces the notes of `feed` with `notes`, returning the notes no longer
+/// in it. Notes whose text changed become a new revision of the ones they
+/// replace.
+pub fn replace(
+    feed: &mut Feed,
+    mut notes: Vec<Note>,
+) -> Result<Vec<Note>, Box<dyn std::error::Error>> {
+    let mut ids = HashSet::new();
+    for note in &notes {
+        if note.id != 0 && !ids.insert(note.id) {
+            return Err(format!("two notes have the id {}", note.id).into());
+        }
+    }
+
+    let old: HashMap<NoteId, &Note> =
+        feed.notes.iter().map(|n| (n.id, n)).collect();
+    for note in &mut notes {
+        if let Some(old) = old.get(&note.id).filter(|o| o.text != note.text) {
+            note.revision = note.revision.max(old.revision);
+            note.written();
+        }
+    }
+
+    let removed: Vec<Note> =
+        feed.notes.drain(..).filter(|n| !ids.contains(&n.id)).collect();
+    feed.last_id = feed.last_id.max(ids.into_iter().max().unwrap_or(0));
+    for note in &mut notes {
+        if note.id == 0 {
+            note.id = feed.next_id();
+        }
+        note.tags = extract_tags(&note.text);
+    }
+    notes.sort_by_key(|n| std::cmp::Reverse(n.date));
+    feed.notes = notes.into();
+    feed.rewrite = true;
+    Ok(removed)
+}
@@ -0,0 +1,378 @@
+//! Publishing the feed as an Atom or RSS feed, for feed readers, as an Org
+//! file for keeping, or as an iCalendar file of the notes with a `due:` or
+//! `remind:` date, for calendar apps.
+
+use std::fmt::Write;
+
+use chrono::{DateTime, Duration, Local, NaiveTime, TimeZone, Utc};
+use serde::Deserialize;
+
+use crate::model::{Feed, Note, When};
+
+/// Tag that marks a note for publishing when only public notes are exported.
+pub const PUBLIC_TAG: &str = "public";
+
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    #[default]
+    Atom,
+    Rss,
+    Org,
+    Ics,
+}
+
+impl Format {
+    pub fn parse(s: &str) -> Option<Format> {
+        match s {
+            "atom" => Some(Format::Atom),
+            "rss" => Some(Format::Rss),
+            "org" => Some(Format::Org),
+            "ics" => Some(Format::Ics),
+            _ => None,
+        }
+    }
+}
+
+/// What goes into the feed besides the notes.
+pub struct Channel<'a> {
+    pub title: &'a str,
+    pub author: &'a str,
+    /// Where the feed is published, if anywhere. Notes link to it.
+    pub link: Option<&'a str>,
+    /// Identifies the notebook in the ids of the entries.
+    pub notebook: &'a str,
+}
+
+/// Renders the notes of `feed` outside the trash, newest first, optionally
+/// only those tagged `#public`.
+pub fn render(
+    format: Format,
+    feed: &Feed,
+    channel: &Channel,
+    public_only: bool,
+) -> String {
+    let notes: Vec<&Note> = feed
+        .notes
+        .iter()
+        .filter(|n| n.deleted_at.is_none())
+        .filter(|n| !public_only || n.tags.iter().any(|t| t == PUBLIC_TAG))
+        .collect();
+    match format {
+        Format::Atom => atom(&notes, channel),
+        Format::Rss => rss(&notes, channel),
+        Format::Org => org(&notes, channel),
+        Format::Ics => ics(&notes, channel),
+    }
+}
+
+fn atom(notes: &[&Note], channel: &Channel) -> String {
+    let last_updated =
+        notes.iter().map(|n| updated(n)).max().unwrap_or_else(Local::now);
+
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    out.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+    let _ = writeln!(out, "  <title>{}</title>", escape(channel.title));
+    let _ = writeln!(out, "  <id>{}</id>", escape(&feed_id(channel)));
+    let _ = writeln!(out, "  <updated>{}</updated>", last_updated.to_rfc3339());
+    let _ = writeln!(
+        out,
+        "  <author><name>{}</name></author>",
+        escape(channel.author)
+    );
+    if let Some(link) = channel.link {
+        let _ = writeln!(out, "  <link href=\"{}\"/>", escape(link));
+    }
+    for note in notes {
+        out.push_str("  <entry>\n");
+        let _ = writeln!(out, "    <title>{}</title>", escape(&title(note)));
+        let _ =
+            writeln!(out, "    <id>{}</id>", escape(&entry_id(note, channel)));
+        let _ = writeln!(
+            out,
+            "    <published>{}</published>",
+            note.date.to_rfc3339()
+        );
+        let _ = writeln!(
+            out,
+            "    <updated>{}</updated>",
+            updated(note).to_rfc3339()
+        );
+        if let Some(link) = channel.link {
+            let _ = writeln!(
+                out,
+                "    <link href=\"{}#note-{}\"/>",
+                escape(link),
+                note.id
+            );
+        }
+        for tag in &note.tags {
+            let _ = writeln!(out, "    <category term=\"{}\"/>", escape(tag));
+        }
+        let _ = writeln!(
+            out,
+            "    <content type=\"text\">{}</content>",
+            escape(&note.text)
+        );
+        out.push_str("  </entry>\n");
+    }
+    out.push_str("</feed>\n");
+    out
+}
+
+fn rss(notes: &[&Note], channel: &Channel) -> String {
+    let last_updated =
+        notes.iter().map(|n| updated(n)).max().unwrap_or_else(Local::now);
+
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    out.push_str("<rss version=\"2.0\">\n<channel>\n");
+    let _ = writeln!(out, "  <title>{}</title>", escape(channel.title));
+    let _ = writeln!(
+        out,
+        "  <link>{}</link>",
+        escape(channel.link.unwrap_or_default())
+    );
+    let _ = writeln!(
+        out,
+        "  <description>Notes by {}</description>",
+        escape(channel.author)
+    );
+    let _ = writeln!(
+        out,
+        "  <lastBuildDate>{}</lastBuildDate>",
+        last_updated.to_rfc2822()
+    );
+    for note in notes {
+        out.push_str("  <item>\n");
+        let _ = writeln!(out, "    <title>{}</title>", escape(&title(note)));
+        let _ = writeln!(
+            out,
+            "    <guid isPermaLink=\"false\">{}</guid>",
+            escape(&entry_id(note, channel))
+        );
+        let _ =
+            writeln!(out, "    <pubDate>{}</pubDate>", note.date.to_rfc2822());
+        if let Some(link) = channel.link {
+            let _ = writeln!(
+                out,
+                "    <link>{}#note-{}</link>",
+                escape(link),
+                note.id
+            );
+        }
+        for tag in &note.tags {
+            let _ = writeln!(out, "    <category>{}</category>", escape(tag));
+        }
+        let _ = writeln!(
+            out,
+            "    <description>{}</description>",
+            escape(&note.text)
+        );
+        out.push_str("  </item>\n");
+    }
+    out.push_str("</channel>\n</rss>\n");
+    out
+}
+
+/// A heading for every note: its title, tagged with its tags, over the time it
+/// was written and its text. Lines of the text starting with `*` are escaped
+/// with a `,`, so they are not taken for headings.
+fn org(notes: &[&Note], channel: &Channel) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "#+TITLE: {}", channel.title);
+    let _ = writeln!(out, "#+AUTHOR: {}", channel.author);
+    for note in notes {
+        let _ = write!(out, "\n* {}", note.title());
+        if !note.tags.is_empty() {
+            // Org tags cannot have dashes
+            let tags: Vec<String> =
+                note.tags.iter().map(|t| t.replace('-', "_")).collect();
+            let _ = write!(out, " :{}:", tags.join(":"));
+        }
+        out.push('\n');
+        let _ = writeln!(out, "{}", note.date.format("[%Y-%m-%d %a %H:%M]"));
+        for line in note.text.lines() {
+            if line.trim_start_matches(',').starts_with('*') {
+                out.push(',');
+            }
+            let _ = writeln!(out, "{}", line);
+        }
+    }
+    out
+}
+
+/// An event for every note with a `due:` or `remind:` date, on the day or at
+/// the time it is due, or else of the reminder. Reminders alert at their time,
+/// or at 9 in the morning of their day. The events have the ids of the
+/// notes, so calendars update them when the file is exported again instead of
+/// adding them twice.
+fn ics(notes: &[&Note], channel: &Channel) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".into(),
+        "PRODID:-//feednotes//feednotes//EN".into(),
+        format!("X-WR-CALNAME:{}", ics_text(channel.title)),
+    ];
+    for note in notes {
+        let (due, remind) = (note.due(), note.remind());
+        let Some(when) = due.or(remind) else {
+            continue;
+        };
+        // the dates are already the event's
+        let title = title(note);
+        let summary: Vec<&str> = title
+            .split_whitespace()
+            .filter(|w| !w.starts_with("due:") && !w.starts_with("remind:"))
+            .collect();
+        let summary = ics_text(&summary.join(" "));
+        lines.extend([
+            "BEGIN:VEVENT".into(),
+            format!("UID:{}", entry_id(note, channel)),
+            format!("DTSTAMP:{}", utc(updated(note))),
+            format!("LAST-MODIFIED:{}", utc(updated(note))),
+            // calendars only take changes to an event with a higher sequence
+            format!("SEQUENCE:{}", note.revisions.len()),
+            format!("SUMMARY:{}", summary),
+            format!("DESCRIPTION:{}", ics_text(&note.text)),
+        ]);
+        match when {
+            When::Day(day) => lines.extend([
+                format!("DTSTART;VALUE=DATE:{}", day.format("%Y%m%d")),
+                format!(
+                    "DTEND;VALUE=DATE:{}",
+                    (day + Duration::days(1)).format("%Y%m%d")
+                ),
+            ]),
+            When::At(at) => lines.push(format!("DTSTART:{}", utc(at))),
+        }
+        if !note.tags.is_empty() {
+            let tags: Vec<String> =
+                note.tags.iter().map(|t| ics_text(t)).collect();
+            lines.push(format!("CATEGORIES:{}", tags.join(",")));
+        }
+        if let Some(link) = channel.link {
+            lines.push(format!("URL:{}#note-{}", link, note.id));
+        }
+        let alarm = match remind {
+            Some(When::At(at)) => Some(at),
+            Some(When::Day(day)) => Local
+                .from_local_datetime(&day.and_time(NaiveTime::MIN))
+                .earliest()
+                .map(|midnight| midnight + Duration::hours(9)),
+            None => None,
+        };
+        if let Some(alarm) = alarm {
+            lines.extend([
+                "BEGIN:VALARM".into(),
+                "ACTION:DISPLAY".into(),
+                format!("DESCRIPTION:{}", summary),
+                format!("TRIGGER;VALUE=DATE-TIME:{}", utc(alarm)),
+                "END:VALARM".into(),
+            ]);
+        }
+        lines.push("END:VEVENT".into());
+    }
+    lines.push("END:VCALENDAR".into());
+    lines.iter().map(|l| fold(l) + "\r\n").collect()
+}
+
+/// `time` the way iCalendar writes times in UTC.
+fn utc(time: DateTime<Local>) -> String {
+    time.with_timezone(&Utc).format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// `s` with the characters iCalendar gives a meaning to escaped.
+fn ics_text(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// `line` broken into lines of at most 75 bytes, the ones after the first
+/// starting with a space, as iCalendar requires.
+fn fold(line: &str) -> String {
+    let mut out = String::new();
+    let mut len = 0;
+    for c in line.chars() {
+        if len + c.len_utf8() > 75 {
+            out.push_str("\r\n ");
+            len = 1;
+        }
+        out.push(c);
+        len += c.len_utf8();
+    }
+    out
+}
+
+fn updated(note: &Note) -> DateTime<Local> {
+    note.modified.unwrap_or(note.date)
+}
+
+fn feed_id(channel: &Channel) -> String {
+    match channel.link {
+        Some(link) => link.to_string(),
+        None => format!("urn:feednotes:{}", channel.notebook),
+    }
+}
+
+/// Stays the same when the note is edited, so readers do not show it again.
+fn entry_id(note: &Note, channel: &Channel) -> String {
+    match channel.link {
+        Some(link) => format!("{}#note-{}", link, note.id),
+        None => format!("urn:feednotes:{}:note:{}", channel.notebook, note.id),
+    }
+}
+
+/// The title of the note, shortened.
+fn title(note: &Note) -> String {
+    let line = note.title();
+    match line.char_indices().nth(80) {
+        Some((i, _)) => format!("{}…", &line[..i]),
+        None => line.to_string(),
+    }
+}
+
+/// A file name for the note made from its title, lowercase with the words
+/// joined by `-`, or from its id when it has no title.
+pub fn file_stem(note: &Note) -> String {
+    let words: Vec<String> = note
+        .title()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    let mut stem = String::new();
+    for word in words {
+        if stem.chars().count() + word.chars().count() > 60 {
+            break;
+        }
+        if !stem.is_empty() {
+            stem.push('-');
+        }
+        stem += &word;
+    }
+    if stem.is_empty() {
+        stem = format!("note-{}", note.id);
+    }
+    stem
+}
+
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            // not allowed in XML 1.0 at all
+            c if c.is_control() && !matches!(c, '\n' | '\t' | '\r') => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
@@ -0,0 +1,802 @@
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+use chrono::NaiveDate;
+use regex::{Regex, RegexBuilder};
+use serde::Deserialize;
+
+use crate::{
+    geo,
+    model::{Feed, Note, NoteColor, NoteId},
+};
+
+#[derive(Clone)]
+pub struct FeedView {
+    pub refs: Vec<usize>,
+    /// Match score of each note in `refs` when the view is ranked by a fuzzy
+    /// query, otherwise empty.
+    pub scores: Vec<i64>,
+    /// How deep in its thread each note in `refs` is, 0 for notes that are not
+    /// replies. Empty when the view is not threaded.
+    pub depths: Vec<usize>,
+    /// The day heading shown above each note in `refs` that starts a day in
+    /// the journal layout. Empty when the view is not grouped by day.
+    pub headers: Vec<Option<DayHeader>>,
+}
+
+#[derive(Clone)]
+pub struct DayHeader {
+    pub day: NaiveDate,
+    /// Notes written that day, including any hidden by collapsing it.
+    pub notes: usize,
+    /// Whether the notes after the first one are hidden.
+    pub collapsed: bool,
+}
+
+/// The order of the feed. Filtered views are sorted the same way, except
+/// fuzzy queries, which rank their results by score.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Sort {
+    #[default]
+    Newest,
+    Oldest,
+    /// Most recently edited or written first.
+    Modified,
+    Longest,
+}
+
+impl Sort {
+    /// The sort after this one, for cycling through them.
+    pub fn next(self) -> Sort {
+        match self {
+            Sort::Newest => Sort::Oldest,
+            Sort::Oldest => Sort::Modified,
+            Sort::Modified => Sort::Longest,
+            Sort::Longest => Sort::Newest,
+        }
+    }
+
+    /// The name used for the sort in the config file.
+    pub fn name(self) -> &'static str {
+        match self {
+            Sort::Newest => "newest",
+            Sort::Oldest => "oldest",
+            Sort::Modified => "modified",
+            Sort::Longest => "longest",
+        }
+    }
+
+    pub fn describe(self) -> &'static str {
+        match self {
+            Sort::Newest => "newest first",
+            Sort::Oldest => "oldest first",
+            Sort::Modified => "recently edited first",
+            Sort::Longest => "longest first",
+        }
+    }
+
+    /// Sorts `refs`, which are in feed order, i.e. newest first.
+    fn apply(self, feed: &Feed, refs: &mut [usize]) {
+        match self {
+            Sort::Newest => {}
+            Sort::Oldest => refs.reverse(),
+            Sort::Modified => refs.sort_by_key(|&i| {
+                let note = &feed.notes[i];
+                Reverse(note.modified.unwrap_or(note.date))
+            }),
+            Sort::Longest => refs
+                .sort_by_key(|&i| Reverse(feed.notes[i].text.chars().count())),
+        }
+    }
+}
+
+impl FeedView {
+    pub fn filter(feed: &Feed, pat: &str, sort: Sort) -> Self {
+        if pat.is_empty() {
+            let mut refs: Vec<usize> = feed
+                .notes
+                .iter()
+                .enumerate()
+                .filter(|(_, n)| n.deleted_at.is_none())
+                .map(|(i, _)| i)
+                .collect();
+            sort.apply(feed, &mut refs);
+            let (refs, depths) = thread(feed, &refs);
+            return FeedView {
+                refs,
+                scores: Vec::new(),
+                depths,
+                headers: Vec::new(),
+            };
+        }
+
+        // an invalid regex matches nothing
+        let query = Query::parse(pat).ok();
+        if let Some(Query::Fuzzy(terms)) = &query {
+            let mut ranked: Vec<(usize, i64)> = feed
+                .notes
+                .iter()
+                .enumerate()
+                .filter(|(_, n)| n.deleted_at.is_none())
+                .filter_map(|(i, n)| Some((i, fuzzy_terms(terms, &n.text)?)))
+                .collect();
+            // stable, so equal scores stay newest first
+            ranked.sort_by_key(|&(_, score)| Reverse(score));
+            return FeedView {
+                refs: ranked.iter().map(|(i, _)| *i).collect(),
+                scores: ranked.iter().map(|(_, s)| *s).collect(),
+                depths: Vec::new(),
+                headers: Vec::new(),
+            };
+        }
+        let mut refs: Vec<usize> = feed
+            .notes
+            .iter()
+            .enumerate()
+            .filter(|(_, n)| {
+                n.deleted_at.is_none()
+                    && query.as_ref().is_some_and(|q| q.matches(n))
+            })
+            .map(|(i, _)| i)
+            .collect();
+        sort.apply(feed, &mut refs);
+        FeedView {
+            refs,
+            scores: Vec::new(),
+            depths: Vec::new(),
+            headers: Vec::new(),
+        }
+    }
+
+    /// Groups the view under a heading for every day for the journal
+    /// layout. Replies stay under the day of the note they answer, and only
+    /// the first note of the days in `collapsed` is kept.
+    pub fn group_by_day(
+        self,
+        feed: &Feed,
+        collapsed: &HashSet<NaiveDate>,
+    ) -> FeedView {
+        let depth = |k: usize| self.depths.get(k).copied().unwrap_or(0);
+        let mut days: Vec<(NaiveDate, Vec<usize>)> = Vec::new();
+        for (k, &i) in self.refs.iter().enumerate() {
+            let day = feed.notes[i].date.date_naive();
+            match days.last_mut() {
+                Some((d, ks)) if *d == day || depth(k) > 0 => ks.push(k),
+                _ => days.push((day, vec![k])),
+            }
+        }
+
+        let mut view = FeedView {
+            refs: Vec::new(),
+            scores: Vec::new(),
+            depths: Vec::new(),
+            headers: Vec::new(),
+        };
+        for (day, ks) in days {
+            let is_collapsed = collapsed.contains(&day);
+            let shown = if is_collapsed { &ks[..1] } else { &ks[..] };
+            for (n, &k) in shown.iter().enumerate() {
+                view.headers.push((n == 0).then_some(DayHeader {
+                    day,
+                    notes: ks.len(),
+                    collapsed: is_collapsed,
+                }));
+                view.refs.push(self.refs[k]);
+                if let Some(&d) = self.depths.get(k) {
+                    view.depths.push(d);
+                }
+                if let Some(&s) = self.scores.get(k) {
+                    view.scores.push(s);
+                }
+            }
+        }
+        view
+    }
+
+    /// The position of the note that starts the next day after the one at
+    /// `from`, or with `forward` unset, the start of the day at `from`, or
+    /// of the day before when `from` already is the start.
+    pub fn day_start(
+        &self,
+        feed: &Feed,
+        from: usize,
+        forward: bool,
+    ) -> Option<usize> {
+        let day = |k: usize| feed.notes[self.refs[k]].date.date_naive();
+        if forward {
+            return (from + 1..self.refs.len()).find(|&k| day(k) != day(from));
+        }
+        let start = |mut k: usize| {
+            while k > 0 && day(k - 1) == day(k) {
+                k -= 1;
+            }
+            k
+        };
+        match start(from) {
+            0 if from == 0 => None,
+            s if s == from => Some(start(s - 1)),
+            s => Some(s),
+        }
+    }
+
+    pub fn trash(feed: &Feed) -> Self {
+        FeedView {
+            refs: feed
+                .notes
+                .iter()
+                .enumerate()
+                .filter(|(_, n)| n.deleted_at.is_some())
+                .map(|(i, _)| i)
+                .collect(),
+            scores: Vec::new(),
+            depths: Vec::new(),
+            headers: Vec::new(),
+        }
+    }
+}
+
+/// Orders `refs` so every reply comes right after its parent, older replies
+/// first, and gives the depth of each note in its thread. Notes that are not
+/// replies keep their order, and replies to notes outside `refs` are shown as
+/// if they were not replies.
+fn thread(feed: &Feed, refs: &[usize]) -> (Vec<usize>, Vec<usize>) {
+    let ids: HashSet<NoteId> = refs.iter().map(|&i| feed.notes[i].id).collect();
+    let mut replies: HashMap<NoteId, Vec<usize>> = HashMap::new();
+    let mut roots = Vec::new();
+    for &i in refs {
+        match feed.notes[i].reply_to {
+            Some(parent) if ids.contains(&parent) => {
+                replies.entry(parent).or_default().push(i)
+            }
+            _ => roots.push(i),
+        }
+    }
+
+    let mut order = Vec::with_capacity(refs.len());
+    let mut depths = Vec::with_capacity(refs.len());
+    let mut stack: Vec<(usize, usize)> =
+        roots.iter().rev().map(|&i| (i, 0)).collect();
+    while let Some((i, depth)) = stack.pop() {
+        order.push(i);
+        depths.push(depth);
+        if let Some(mut children) = replies.remove(&feed.notes[i].id) {
+            // newest pushed first, so the oldest reply is popped first
+            children.sort_by_key(|&c| Reverse(feed.notes[c].date));
+            stack.extend(children.into_iter().map(|c| (c, depth + 1)));
+        }
+    }
+    // replies caught in a cycle of broken ids never get reached
+    for children in replies.into_values() {
+        for c in children {
+            if !order.contains(&c) {
+                order.push(c);
+                depths.push(0);
+            }
+        }
+    }
+    (order, depths)
+}
+
+/// A parsed filter string. Matching is case-insensitive.
+pub enum Query {
+    /// `re:<pattern>` matches the note text against a regex.
+    Regex(Regex),
+    /// `~<terms>` matches notes containing the characters of every term in
+    /// order, ranked by how closely they do.
+    Fuzzy(Vec<String>),
+    /// Predicates combined with `AND`, `OR`, `NOT` and parentheses.
+    Expr(Expr),
+}
+
+/// Predicates next to each other must all match, as if joined by `AND`,
+/// which binds tighter than `OR`.
+pub enum Expr {
+    Predicate(Predicate),
+    Not(Box<Expr>),
+    /// Matches when every one does, so also when there are none.
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+}
+
+pub enum Predicate {
+    /// `#tag` or `tag:tag`: one of the note's tags.
+    Tag(String),
+    /// `date:<from>..<to>`: written between the two days, inclusive. Either
+    /// end may be left out, `date:<day>` matches a single day, and
+    /// `date:>day`, `date:>=day`, `date:<day` and `date:<=day` the days
+    /// after or before one.
+    Date { from: Option<NaiveDate>, to: Option<NaiveDate> },
+    /// `todo:open`: has a `- [ ]` checkbox left unchecked.
+    OpenTodo,
+    /// `color:<name>`: marked with that color.
+    Color(NoteColor),
+    /// `is:starred`, or `is:pinned`: starred with `*`.
+    Starred,
+    /// `in:archive`: matches every note, and has `feednotes search` look
+    /// through the notes archived by month as well.
+    InArchive,
+    /// `near:<place>` or `near:<lat>,<lon>`: written within `radius`
+    /// kilometers of a place from the config or of the coordinates.
+    Near { lat: f64, lon: f64, radius: f64 },
+    /// `title:<word>` or `title:"a phrase"`: appears in the first line of
+    /// the note that is not blank, as the feed shows it whether or not
+    /// `titles` is on.
+    Title(String),
+    /// Any other word, `"a phrase"` or `text:"a phrase"`: appears somewhere
+    /// in the text.
+    Word(String),
+}
+
+#[derive(Debug)]
+pub enum ParseError {
+    Regex(regex::Error),
+    Date(String),
+    Todo(String),
+    Color(String),
+    Is(String),
+    In(String),
+    Near(String),
+    /// A `"` with no `"` closing it.
+    Quote,
+    /// Operators or parentheses out of place.
+    Syntax(String),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Regex(e) => write!(f, "invalid regex: {}", e),
+            ParseError::Date(s) => {
+                write!(f, "invalid date `{}`, expected YYYY-MM-DD", s)
+            }
+            ParseError::Todo(s) => {
+                write!(f, "invalid todo filter `{}`, expected todo:open", s)
+            }
+            ParseError::Color(s) => write!(
+                f,
+                "invalid color `{}`, expected {}",
+                s,
+                NoteColor::ALL.map(NoteColor::name).join(", ")
+            ),
+            ParseError::Is(s) => {
+                write!(f, "invalid filter `{}`, expected is:starred", s)
+            }
+            ParseError::In(s) => {
+                write!(f, "invalid filter `{}`, expected in:archive", s)
+            }
+            ParseError::Near(s) => write!(
+                f,
+                "unknown place `{}`, expected one from [places] in the \
+                config or lat,lon",
+                s
+            ),
+            ParseError::Quote => write!(f, "unclosed `\"`"),
+            ParseError::Syntax(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// A piece of a filter string between spaces and parentheses.
+#[derive(PartialEq)]
+enum Token {
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+    /// A word with the quotes taken out of it, and whether it started with
+    /// one, making it a phrase to look for as it is.
+    Word(String, bool),
+}
+
+impl Query {
+    pub fn parse(pat: &str) -> Result<Query, ParseError> {
+        if let Some(re) = pat.strip_prefix("re:") {
+            return RegexBuilder::new(re)
+                .case_insensitive(true)
+                .build()
+                .map(Query::Regex)
+                .map_err(ParseError::Regex);
+        }
+        if let Some(terms) = pat.strip_prefix('~') {
+            return Ok(Query::Fuzzy(
+                terms.split_whitespace().map(str::to_string).collect(),
+            ));
+        }
+
+        let mut tokens = tokens(pat)?.into_iter().peekable();
+        let expr = parse_or(&mut tokens)?;
+        match tokens.next() {
+            None => Ok(Query::Expr(expr)),
+            Some(Token::Close) => {
+                Err(ParseError::Syntax("`)` without a `(`".into()))
+            }
+            // parse_or only stops at the end or a `)`
+            Some(_) => unreachable!(),
+        }
+    }
+
+    /// A regex finding the parts of a note's text the query matched on, for
+    /// highlighting. `None` when no part of the query is about the text.
+    pub fn highlighter(&self) -> Option<Regex> {
+        let pattern = match self {
+            Query::Regex(re) => re.as_str().to_string(),
+            Query::Fuzzy(_) => return None,
+            Query::Expr(expr) => {
+                let mut alternatives = Vec::new();
+                expr.highlighted(&mut alternatives);
+                if alternatives.is_empty() {
+                    return None;
+                }
+                alternatives.join("|")
+            }
+        };
+        RegexBuilder::new(&pattern).case_insensitive(true).build().ok()
+    }
+
+    /// Whether the query asks for archived notes too with `in:archive`.
+    pub fn in_archive(&self) -> bool {
+        match self {
+            Query::Expr(expr) => expr.in_archive(),
+            _ => false,
+        }
+    }
+
+    pub fn matches(&self, note: &Note) -> bool {
+        match self {
+            Query::Regex(re) => re.is_match(&note.text),
+            Query::Fuzzy(terms) => fuzzy_terms(terms, &note.text).is_some(),
+            Query::Expr(expr) => expr.matches(note, &note.text.to_lowercase()),
+        }
+    }
+}
+
+impl Expr {
+    /// Whether `note`, whose text lowercased is `text`, matches.
+    fn matches(&self, note: &Note, text: &str) -> bool {
+        match self {
+            Expr::Predicate(p) => p.matches(note, text),
+            Expr::Not(expr) => !expr.matches(note, text),
+            Expr::And(exprs) => exprs.iter().all(|e| e.matches(note, text)),
+            Expr::Or(exprs) => exprs.iter().any(|e| e.matches(note, text)),
+        }
+    }
+
+    fn in_archive(&self) -> bool {
+        match self {
+            Expr::Predicate(p) => matches!(p, Predicate::InArchive),
+            Expr::Not(expr) => expr.in_archive(),
+            Expr::And(exprs) | Expr::Or(exprs) => {
+                exprs.iter().any(Expr::in_archive)
+            }
+        }
+    }
+
+    /// Adds escaped patterns for the tags and words looked for to
+    /// `patterns`, leaving out those under a `NOT`.
+    fn highlighted(&self, patterns: &mut Vec<String>) {
+        match self {
+            Expr::Predicate(Predicate::Tag(tag)) => {
+                patterns.push(regex::escape(&format!("#{}", tag)))
+            }
+            Expr::Predicate(Predicate::Word(word) | Predicate::Title(word)) => {
+                patterns.push(regex::escape(word))
+            }
+            Expr::Predicate(_) | Expr::Not(_) => {}
+            Expr::And(exprs) | Expr::Or(exprs) => {
+                for expr in exprs {
+                    expr.highlighted(patterns);
+                }
+            }
+        }
+    }
+}
+
+impl Predicate {
+    fn parse(word: &str) -> Result<Predicate, ParseError> {
+        Ok(
+            if let Some(tag) = word.strip_prefix('#').filter(|t| !t.is_empty())
+            {
+                Predicate::Tag(tag.to_lowercase())
+            } else if let Some(tag) = word.strip_prefix("tag:") {
+                Predicate::Tag(tag.trim_start_matches('#').to_lowercase())
+            } else if let Some(text) = word.strip_prefix("text:") {
+                Predicate::Word(text.to_lowercase())
+            } else if let Some(title) = word.strip_prefix("title:") {
+                Predicate::Title(title.to_lowercase())
+            } else if let Some(range) = word.strip_prefix("date:") {
+                let (from, to) = parse_range(range)?;
+                Predicate::Date { from, to }
+            } else if let Some(state) = word.strip_prefix("todo:") {
+                if state != "open" {
+                    return Err(ParseError::Todo(word.to_string()));
+                }
+                Predicate::OpenTodo
+            } else if let Some(name) = word.strip_prefix("color:") {
+                let color = NoteColor::parse(&name.to_lowercase())
+                    .ok_or_else(|| ParseError::Color(name.to_string()))?;
+                Predicate::Color(color)
+            } else if let Some(state) = word.strip_prefix("is:") {
+                if state != "starred" && state != "pinned" {
+                    return Err(ParseError::Is(word.to_string()));
+                }
+                Predicate::Starred
+            } else if let Some(place) = word.strip_prefix("in:") {
+                if place != "archive" {
+                    return Err(ParseError::In(word.to_string()));
+                }
+                Predicate::InArchive
+            } else if let Some(name) = word.strip_prefix("near:") {
+                match (geo::place(name), geo::coordinates(name)) {
+                    (Some(place), _) => Predicate::Near {
+                        lat: place.lat,
+                        lon: place.lon,
+                        radius: place.radius,
+                    },
+                    (None, Some((lat, lon))) => {
+                        Predicate::Near { lat, lon, radius: 1.0 }
+                    }
+                    (None, None) => {
+                        return Err(ParseError::Near(name.to_string()))
+                    }
+                }
+            } else {
+                Predicate::Word(word.to_lowercase())
+            },
+        )
+    }
+
+    /// Whether `note`, whose text lowercased is `text`, matches.
+    fn matches(&self, note: &Note, text: &str) -> bool {
+        match self {
+            Predicate::Tag(tag) => {
+                note.tags.iter().any(|t| t.to_lowercase() == *tag)
+            }
+            Predicate::Date { from, to } => {
+                let day = note.date.date_naive();
+                from.is_none_or(|from| from <= day)
+                    && to.is_none_or(|to| day <= to)
+            }
+            Predicate::OpenTodo => note.has_open_todo(),
+            Predicate::Color(color) => note.color == Some(*color),
+            Predicate::Starred => note.starred,
+            Predicate::InArchive => true,
+            Predicate::Near { lat, lon, radius } => {
+                note.location.as_ref().is_some_and(|l| {
+                    geo::distance((l.lat, l.lon), (*lat, *lon)) <= *radius
+                })
+            }
+            Predicate::Title(word) => text
+                .lines()
+                .find(|l| !l.trim().is_empty())
+                .is_some_and(|l| l.contains(word)),
+            Predicate::Word(word) => text.contains(word),
+        }
+    }
+}
+
+/// Splits `pat` into parentheses, operators and words. Spaces and
+/// parentheses inside quotes are part of the word.
+fn tokens(pat: &str) -> Result<Vec<Token>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut chars = pat.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            _ if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' | ')' => {
+                chars.next();
+                tokens.push(if c == '(' { Token::Open } else { Token::Close });
+            }
+            _ => {
+                let mut word = String::new();
+                let mut quoted = false;
+                while let Some(&c) = chars.peek() {
+                    if !quoted && (c.is_whitespace() || c == '(' || c == ')') {
+                        break;
+                    }
+                    chars.next();
+                    if c == '"' {
+                        quoted = !quoted;
+                    } else {
+                        word.push(c);
+                    }
+                }
+                if quoted {
+                    return Err(ParseError::Quote);
+                }
+                let phrase = c == '"';
+                tokens.push(match word.as_str() {
+                    "AND" if !phrase => Token::And,
+                    "OR" if !phrase => Token::Or,
+                    "NOT" if !phrase => Token::Not,
+                    _ => Token::Word(word, phrase),
+                });
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+type Tokens = std::iter::Peekable<std::vec::IntoIter<Token>>;
+
+/// Expressions joined by `OR`, up to the end or a `)`.
+fn parse_or(tokens: &mut Tokens) -> Result<Expr, ParseError> {
+    let mut exprs = vec![parse_and(tokens)?];
+    while tokens.next_if_eq(&Token::Or).is_some() {
+        exprs.push(parse_and(tokens)?);
+    }
+    if exprs.len() > 1
+        && exprs.iter().any(|e| matches!(e, Expr::And(a) if a.is_empty()))
+    {
+        return Err(ParseError::Syntax(
+            "`OR` needs a filter on each side".into(),
+        ));
+    }
+    Ok(if exprs.len() == 1 { exprs.pop().unwrap() } else { Expr::Or(exprs) })
+}
+
+/// Expressions next to each other or joined by `AND`, up to the end, an
+/// `OR` or a `)`.
+fn parse_and(tokens: &mut Tokens) -> Result<Expr, ParseError> {
+    let mut exprs = Vec::new();
+    let mut and = false;
+    loop {
+        match tokens.peek() {
+            None | Some(Token::Or | Token::Close) => break,
+            Some(Token::And) if exprs.is_empty() || and => {
+                return Err(ParseError::Syntax(
+                    "`AND` needs a filter on each side".into(),
+                ))
+            }
+            Some(Token::And) => {
+                tokens.next();
+                and = true;
+                continue;
+            }
+            Some(_) => exprs.push(parse_not(tokens)?),
+        }
+        and = false;
+    }
+    if and {
+        return Err(ParseError::Syntax(
+            "`AND` needs a filter on each side".into(),
+        ));
+    }
+    Ok(if exprs.len() == 1 { exprs.pop().unwrap() } else { Expr::And(exprs) })
+}
+
+/// A predicate or a parenthesized expression, with any `NOT`s before it.
+fn parse_not(tokens: &mut Tokens) -> Result<Expr, ParseError> {
+    match tokens.next() {
+        Some(Token::Not) => match tokens.peek() {
+            None | Some(Token::And | Token::Or | Token::Close) => {
+                Err(ParseError::Syntax("`NOT` needs a filter after it".into()))
+            }
+            Some(_) => Ok(Expr::Not(Box::new(parse_not(tokens)?))),
+        },
+        Some(Token::Open) => {
+            let expr = parse_or(tokens)?;
+            if tokens.next() != Some(Token::Close) {
+                return Err(ParseError::Syntax("`(` without a `)`".into()));
+            }
+            if matches!(&expr, Expr::And(a) if a.is_empty()) {
+                return Err(ParseError::Syntax("empty `()`".into()));
+            }
+            Ok(expr)
+        }
+        Some(Token::Word(word, true)) => {
+            Ok(Expr::Predicate(Predicate::Word(word.to_lowercase())))
+        }
+        Some(Token::Word(word, false)) => {
+            Ok(Expr::Predicate(Predicate::parse(&word)?))
+        }
+        // parse_and only calls this on the start of an expression
+        _ => unreachable!(),
+    }
+}
+
+/// Sum of the fuzzy scores of `terms` in `text`, `None` unless every term
+/// matches.
+fn fuzzy_terms(terms: &[String], text: &str) -> Option<i64> {
+    terms.iter().map(|t| fuzzy_score(t, text)).sum()
+}
+
+/// Scores `pattern` against `text` the way fzf does, roughly: every
+/// character of the pattern has to appear in the text in order, and the
+/// match scores higher the more of it is consecutive or starts words.
+/// Case is ignored. `None` when the pattern does not match at all.
+pub fn fuzzy_score(pattern: &str, text: &str) -> Option<i64> {
+    let pattern: Vec<char> =
+        pattern.chars().flat_map(char::to_lowercase).collect();
+    if pattern.is_empty() {
+        return Some(0);
+    }
+    let text: Vec<char> =
+        text.chars().map(|c| c.to_lowercase().next().unwrap_or(c)).collect();
+
+    // find where the leftmost match ends, then walk back from there to the
+    // latest start, which gives the tightest window a greedy scan can find
+    let mut p = 0;
+    let mut end = None;
+    for (i, c) in text.iter().enumerate() {
+        if *c == pattern[p] {
+            p += 1;
+            if p == pattern.len() {
+                end = Some(i);
+                break;
+            }
+        }
+    }
+    let end = end?;
+    let mut start = end;
+    for i in (0..=end).rev() {
+        if text[i] == pattern[p - 1] {
+            p -= 1;
+            if p == 0 {
+                start = i;
+                break;
+            }
+        }
+    }
+
+    let mut score = 0;
+    let mut last: Option<usize> = None;
+    for (i, c) in text.iter().enumerate().take(end + 1).skip(start) {
+        if p == pattern.len() || *c != pattern[p] {
+            continue;
+        }
+        score += 16;
+        if i == 0 || !text[i - 1].is_alphanumeric() {
+            score += 8;
+        }
+        match last {
+            Some(l) if l + 1 == i => score += 8,
+            Some(l) => score -= (i - l - 1).min(8) as i64,
+            None => {}
+        }
+        last = Some(i);
+        p += 1;
+    }
+    Some(score)
+}
+
+/// Parses the days of a `date:` filter, `None` for an open end.
+fn parse_range(
+    range: &str,
+) -> Result<(Option<NaiveDate>, Option<NaiveDate>), ParseError> {
+    let day = |s: &str| {
+        parse_day(s)?.ok_or_else(|| ParseError::Date(range.to_string()))
+    };
+    Ok(if let Some(from) = range.strip_prefix(">=") {
+        (Some(day(from)?), None)
+    } else if let Some(to) = range.strip_prefix("<=") {
+        (None, Some(day(to)?))
+    } else if let Some(after) = range.strip_prefix('>') {
+        (day(after)?.succ_opt(), None)
+    } else if let Some(before) = range.strip_prefix('<') {
+        (None, day(before)?.pred_opt())
+    } else if let Some((from, to)) = range.split_once("..") {
+        (parse_day(from)?, parse_day(to)?)
+    } else {
+        let day = parse_day(range)?;
+        (day, day)
+    })
+}
+
+/// Parses a `YYYY-MM-DD` day, where an empty string is an open range end.
+fn parse_day(s: &str) -> Result<Option<NaiveDate>, ParseError> {
+    if s.is_empty() {
+        return Ok(None);
+    }
+    NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .map(Some)
+        .map_err(|_| ParseError::Date(s.to_string()))
+}
@@ -0,0 +1,28 @@
+//! Fuzzy matching of what is typed in the quick switcher against note titles.
+
+/// How well `text` matches `query`, higher being better, or `None` when the
+/// characters of `query` do not all appear in `text` in order. Case is
+/// ignored. Characters matched next to each other or at the start of words
+/// count for more, and skipped characters against.
+pub fn score(query: &str, text: &str) -> Option<i64> {
+    let text: Vec<char> = text.chars().flat_map(char::to_lowercase).collect();
+    let mut score = 0;
+    let mut from = 0;
+    let mut previous: Option<usize> = None;
+    for q in query.chars().flat_map(char::to_lowercase) {
+        if q.is_whitespace() {
+            continue;
+        }
+        let at = from + text[from..].iter().position(|&c| c == q)?;
+        if previous.is_some_and(|p| p + 1 == at) {
+            score += 8;
+        }
+        if at == 0 || !text[at - 1].is_alphanumeric() {
+            score += 4;
+        }
+        score -= (at - from) as i64;
+        previous = Some(at);
+        from = at + 1;
+    }
+    Some(score)
+}
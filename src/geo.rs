@@ -0,0 +1,103 @@
+//! Where notes were written. A note's location is typed in as coordinates
+//! or the name of a place from `[places]` in the config, or asked of a
+//! command like `termux-location` or `CoreLocationCLI`, and notes are found
+//! by it with `near:<place>` in filters.
+
+use std::{collections::BTreeMap, process::Command, sync::OnceLock};
+
+use serde::Deserialize;
+
+use crate::model::Location;
+
+/// A place named in the config.
+#[derive(Clone, Deserialize)]
+pub struct Place {
+    pub lat: f64,
+    pub lon: f64,
+    /// How many kilometers from it count as near it.
+    #[serde(default = "default_radius")]
+    pub radius: f64,
+}
+
+fn default_radius() -> f64 {
+    1.0
+}
+
+/// The places of the config, set once at startup so filters, which are
+/// parsed all over, can look them up by name.
+static PLACES: OnceLock<BTreeMap<String, Place>> = OnceLock::new();
+
+pub fn set_places(places: &BTreeMap<String, Place>) {
+    let _ = PLACES.set(places.clone());
+}
+
+/// The place of the config named `name`.
+pub fn place(name: &str) -> Option<Place> {
+    PLACES.get()?.get(name).cloned()
+}
+
+/// Reads a location typed in: the name of a place, or a latitude and a
+/// longitude like `35.6812, 139.7671`.
+pub fn parse(input: &str) -> Option<Location> {
+    if let Some(place) = place(input) {
+        return Some(Location {
+            lat: place.lat,
+            lon: place.lon,
+            name: Some(input.into()),
+        });
+    }
+    let (lat, lon) = coordinates(input)?;
+    Some(Location { lat, lon, name: None })
+}
+
+/// A latitude and a longitude separated by a comma or spaces.
+pub fn coordinates(input: &str) -> Option<(f64, f64)> {
+    let mut parts = input
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|p| !p.is_empty());
+    let lat: f64 = parts.next()?.parse().ok()?;
+    let lon: f64 = parts.next()?.parse().ok()?;
+    let valid = parts.next().is_none()
+        && (-90.0..=90.0).contains(&lat)
+        && (-180.0..=180.0).contains(&lon);
+    valid.then_some((lat, lon))
+}
+
+/// The location `command`, run by the shell, prints as a latitude and a
+/// longitude.
+pub fn locate(command: &str) -> Result<Location, Box<dyn std::error::Error>> {
+    let (shell, flag) =
+        if cfg!(windows) { ("cmd", "/C") } else { ("sh", "-c") };
+    let output = Command::new(shell)
+        .args([flag, command])
+        .output()
+        .map_err(|e| format!("cannot run {}: {}", command, e))?;
+    if !output.status.success() {
+        return Err(format!("{} failed", command).into());
+    }
+    let printed = String::from_utf8_lossy(&output.stdout);
+    let (lat, lon) = coordinates(printed.trim()).ok_or_else(|| {
+        format!("{} printed `{}`, not coordinates", command, printed.trim())
+    })?;
+    Ok(Location { lat, lon, name: None })
+}
+
+/// How `location` is shown: the name of its place and its coordinates.
+pub fn describe(location: &Location) -> String {
+    let at = format!("{:.5}, {:.5}", location.lat, location.lon);
+    match &location.name {
+        Some(name) => format!("{} ({})", name, at),
+        None => at,
+    }
+}
+
+/// Kilometers between two points along the surface of the earth.
+pub fn distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    const EARTH_RADIUS: f64 = 6371.0;
+    let (lat1, lat2) = (a.0.to_radians(), b.0.to_radians());
+    let dlat = lat2 - lat1;
+    let dlon = (b.1 - a.1).to_radians();
+    let h = (dlat / 2.0).sin().powi(2)
+        + lat1.cos() * lat2.cos() * (dlon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS * h.sqrt().asin()
+}
@@ -0,0 +1,743 @@
+//! The words of the UI in the languages it speaks, picked with `language` in
+//! the config or otherwise from the locale the environment sets.
+//!
+//! A language is a [`Strings`] holding every title, hint and label the UI
+//! shows, along with the messages reporting what a key did, so one left out of
+//! a translation fails to compile. Errors coming from elsewhere, like those
+//! of the system, are shown as they are.
+
+use std::{env, fmt::Display};
+
+use serde::Deserialize;
+
+use crate::store::CONFLICT_TAG;
+
+#[derive(Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    En,
+    Ja,
+}
+
+impl Language {
+    /// The language of the first of `LC_ALL`, `LC_MESSAGES` and `LANG` that
+    /// is set, e.g. `ja_JP.UTF-8`, falling back to English.
+    pub fn from_env() -> Language {
+        let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .into_iter()
+            .filter_map(|name| env::var(name).ok())
+            .find(|value| !value.is_empty())
+            .unwrap_or_default();
+        match locale.split(['_', '.', '-']).next() {
+            Some("ja") => Language::Ja,
+            _ => Language::En,
+        }
+    }
+
+    pub fn strings(self) -> &'static Strings {
+        match self {
+            Language::En => &EN,
+            Language::Ja => &JA,
+        }
+    }
+}
+
+pub struct Strings {
+    pub modes: Modes,
+    /// Names of the views in the status bar.
+    pub labels: Labels,
+    pub titles: Titles,
+    /// The keys a popup takes, shown along its bottom border.
+    pub hints: Hints,
+    /// Messages shown in the corner.
+    pub status: Status,
+    /// What the composer's counter counts.
+    pub words: &'static str,
+    pub chars: &'static str,
+    /// A number of notes, as counted in the status bar and the topics popup.
+    pub notes: fn(usize) -> String,
+    /// How many notes are marked, in the status bar.
+    pub marked: fn(usize) -> String,
+    /// Asked before deleting that many marked notes, or the selected one for 0.
+    pub confirm_delete: fn(usize) -> String,
+    /// Put after the date of a note, with the date it was edited and the
+    /// machine that last changed it.
+    pub edited: fn(&dyn Display) -> String,
+    pub changed_on: fn(&dyn Display) -> String,
+    /// Heads the notes linking to the one being read.
+    pub linked_from: &'static str,
+    /// What the lock popup asks for, when unlocking and when choosing.
+    pub passphrase: &'static str,
+    pub choose_passphrase: &'static str,
+    pub repeat_passphrase: &'static str,
+    /// The entry of the color popup taking the color off.
+    pub no_color: &'static str,
+    /// Heads of the columns of the topics popup.
+    pub topic_words: &'static str,
+    pub topic_tags: &'static str,
+}
+
+/// The modes of the composer.
+pub struct Modes {
+    pub normal: &'static str,
+    pub insert: &'static str,
+    pub view: &'static str,
+    pub view_line: &'static str,
+}
+
+pub struct Labels {
+    pub feed: &'static str,
+    pub trash: &'static str,
+    pub delete: &'static str,
+    pub tags: &'static str,
+    pub notebooks: &'static str,
+    pub read: &'static str,
+    pub attachments: &'static str,
+    pub toggle: &'static str,
+    pub split: &'static str,
+    pub templates: &'static str,
+    pub stats: &'static str,
+    pub topics: &'static str,
+    pub color: &'static str,
+    pub spelling: &'static str,
+    pub review: &'static str,
+    pub versions: &'static str,
+    pub go_to_note: &'static str,
+    pub links: &'static str,
+    pub calendar: &'static str,
+    pub locked: &'static str,
+    pub scratch: &'static str,
+    pub compose: &'static str,
+    pub filter: &'static str,
+    pub go_to_date: &'static str,
+    pub new_notebook: &'static str,
+    pub attach: &'static str,
+    pub tag: &'static str,
+    pub export: &'static str,
+    pub command: &'static str,
+    pub location: &'static str,
+    pub select: &'static str,
+    pub messages: &'static str,
+    pub duplicate: &'static str,
+    pub read_only: &'static str,
+}
+
+pub struct Titles {
+    pub new_note: &'static str,
+    pub reply: &'static str,
+    pub capture: &'static str,
+    pub filtering: &'static str,
+    pub go_to_date: &'static str,
+    pub attach_file: &'static str,
+    pub add_tag: &'static str,
+    pub export_to_file: &'static str,
+    pub new_notebook: &'static str,
+    pub suggestions: &'static str,
+    pub go_to_note: &'static str,
+    pub save_changes: &'static str,
+    pub tags: &'static str,
+    pub notebooks: &'static str,
+    pub move_note_to: &'static str,
+    pub move_notes_to: &'static str,
+    pub attachments: &'static str,
+    pub versions: &'static str,
+    pub lock: &'static str,
+    pub locked: &'static str,
+    pub scratch: &'static str,
+    pub review: &'static str,
+    pub links: &'static str,
+    pub color: &'static str,
+    pub templates: &'static str,
+    pub stats: &'static str,
+    pub split: &'static str,
+    pub toggle: &'static str,
+    pub command: &'static str,
+    pub location: &'static str,
+    pub select: &'static str,
+    pub messages: &'static str,
+    pub duplicate: &'static str,
+}
+
+pub struct Hints {
+    pub capture: &'static str,
+    pub links: &'static str,
+    pub save_changes: &'static str,
+    pub notebooks: &'static str,
+    pub attachments: &'static str,
+    pub versions: &'static str,
+    pub scratch: &'static str,
+    pub select: &'static str,
+    pub calendar: &'static str,
+    pub topics: &'static str,
+    pub split: &'static str,
+    pub toggle: &'static str,
+    pub review: &'static str,
+    pub duplicate: &'static str,
+    pub trash: &'static str,
+}
+
+/// What a key did, or why it did nothing. The messages of errors go before
+/// the error, after a colon.
+pub struct Status {
+    pub read_only: &'static str,
+    pub saved: &'static str,
+    pub unsaved: &'static str,
+    pub saving_failed: &'static str,
+    pub cannot_reload: &'static str,
+    pub not_watching: &'static str,
+    pub merged_in: fn(usize) -> String,
+    pub synced: fn(usize) -> String,
+    pub switched: fn(&dyn Display) -> String,
+    pub already_in: fn(&dyn Display) -> String,
+    pub moved: fn(usize, &dyn Display) -> String,
+    pub invalid_notebook: fn(&dyn Display) -> String,
+    pub nothing_to_undo: &'static str,
+    pub restored_deleted: fn(usize) -> String,
+    pub reverted_edit: &'static str,
+    pub joined_split: &'static str,
+    pub edited: &'static str,
+    pub discarded_edit: &'static str,
+    pub written_before: &'static str,
+    pub merged_into: &'static str,
+    pub mark_to_merge: &'static str,
+    pub merged: fn(usize) -> String,
+    pub single_line: &'static str,
+    pub nothing_to_split: &'static str,
+    pub split: &'static str,
+    pub restored: &'static str,
+    pub deleted_forever: &'static str,
+    pub never_edited: &'static str,
+    pub restored_version: fn(&dyn Display) -> String,
+    pub no_template: fn(usize) -> String,
+    pub no_templates: fn(&dyn Display) -> String,
+    pub no_earlier_position: &'static str,
+    pub no_note_called: fn(&dyn Display) -> String,
+    pub copied_notes: fn(usize) -> String,
+    pub copied_chars: fn(usize) -> String,
+    pub no_links: &'static str,
+    pub opened: fn(&dyn Display) -> String,
+    pub cannot_open: fn(&dyn Display, &dyn Display) -> String,
+    pub sorted: fn(&dyn Display) -> String,
+    pub sort_not_saved: fn(&dyn Display, &dyn Display) -> String,
+    pub saving_layout_failed: &'static str,
+    pub starred: fn(usize, bool) -> String,
+    pub no_starred: &'static str,
+    pub already_posted: fn(&dyn Display) -> String,
+    pub posted: fn(&dyn Display) -> String,
+    pub posting_failed: &'static str,
+    pub no_attachments: &'static str,
+    pub attached: fn(&dyn Display) -> String,
+    pub cannot_attach: fn(&dyn Display, &dyn Display) -> String,
+    pub attachment_removed: &'static str,
+    pub pasted: fn(&dyn Display) -> String,
+    pub no_image: &'static str,
+    pub cannot_paste: &'static str,
+    pub no_checkboxes: &'static str,
+    pub invalid_tag: fn(&dyn Display) -> String,
+    pub tagged: fn(usize, usize, &dyn Display) -> String,
+    pub already_archived: &'static str,
+    pub archive_search: &'static str,
+    pub no_notes_from: fn(&dyn Display) -> String,
+    pub no_notes_on: fn(&dyn Display) -> String,
+    pub no_notes_around: fn(&dyn Display, bool) -> String,
+    pub invalid_date: fn(&dyn Display) -> String,
+    pub no_location_command: &'static str,
+    pub unknown_place: fn(&dyn Display) -> String,
+    pub located: fn(usize, &dyn Display) -> String,
+    pub unlocated: fn(usize) -> String,
+    pub cannot_locate: &'static str,
+    pub exported: fn(usize, &dyn Display) -> String,
+    pub cannot_export: fn(&dyn Display, &dyn Display) -> String,
+    pub cannot_send: &'static str,
+    pub unknown_command: fn(&dyn Display) -> String,
+    pub spelling_off: &'static str,
+    pub no_word: &'static str,
+    pub spelled_right: fn(&dyn Display) -> String,
+    pub no_suggestions: fn(&dyn Display) -> String,
+    pub not_locking: &'static str,
+    pub empty_passphrase: &'static str,
+    pub passphrases_differ: &'static str,
+    pub wrong_passphrase: &'static str,
+}
+
+const EN: Strings = Strings {
+    modes: Modes {
+        normal: "Normal",
+        insert: "Insert",
+        view: "View",
+        view_line: "View Line",
+    },
+    labels: Labels {
+        feed: "FEED",
+        trash: "TRASH",
+        delete: "DELETE",
+        tags: "TAGS",
+        notebooks: "NOTEBOOKS",
+        read: "READ",
+        attachments: "ATTACHMENTS",
+        toggle: "TOGGLE",
+        split: "SPLIT",
+        templates: "TEMPLATES",
+        stats: "STATS",
+        topics: "TOPICS",
+        color: "COLOR",
+        spelling: "SPELLING",
+        review: "REVIEW",
+        versions: "VERSIONS",
+        go_to_note: "GO TO NOTE",
+        links: "LINKS",
+        calendar: "CALENDAR",
+        locked: "LOCKED",
+        scratch: "SCRATCH",
+        compose: "COMPOSE",
+        filter: "FILTER",
+        go_to_date: "GO TO DATE",
+        new_notebook: "NEW NOTEBOOK",
+        attach: "ATTACH",
+        tag: "TAG",
+        export: "EXPORT",
+        command: "COMMAND",
+        location: "LOCATION",
+        select: "SELECT",
+        messages: "MESSAGES",
+        duplicate: "DUPLICATE",
+        read_only: "READ ONLY",
+    },
+    titles: Titles {
+        new_note: "New Note",
+        reply: "Reply",
+        capture: "Capture",
+        filtering: "Filtering",
+        go_to_date: "Go to date",
+        attach_file: "Attach file",
+        add_tag: "Add tag",
+        export_to_file: "Export to file",
+        new_notebook: "New notebook",
+        suggestions: "Suggestions",
+        go_to_note: "Go to note",
+        save_changes: "Save changes?",
+        tags: "Tags",
+        notebooks: "Notebooks",
+        move_note_to: "Move note to",
+        move_notes_to: "Move notes to",
+        attachments: "Attachments",
+        versions: "Versions",
+        lock: "Lock",
+        locked: "Locked",
+        scratch: "Scratch",
+        review: "Review",
+        links: "Links",
+        color: "Color",
+        templates: "Templates",
+        stats: "Stats",
+        split: "Split",
+        toggle: "Toggle",
+        command: "Command",
+        location: "Location",
+        select: "Select text",
+        messages: "Messages",
+        duplicate: "Written before?",
+    },
+    hints: Hints {
+        capture: "ctrl-s save · ctrl-c discard",
+        links: "tab: links, enter: follow",
+        save_changes: "y: save · n: keep editing · d: discard",
+        notebooks: "n: new notebook",
+        attachments: "x: remove",
+        versions: "enter: restore",
+        scratch: "V then n: make a note",
+        select: "v or V, then y: copy",
+        calendar: "enter: show the day's notes",
+        topics: "h/l: move · w/m: week/month · enter: notes",
+        split: "enter: split before the line",
+        toggle: "space: toggle",
+        review: "space: keep · a: archive · t: tag · d: delete · q: stop",
+        duplicate: "m: merge into it · b: keep both · n: keep editing",
+        trash: "Trash: r restore, dd delete forever, q back",
+    },
+    status: Status {
+        read_only: "read only",
+        saved: "saved",
+        unsaved: "unsaved changes, :wq or :q!",
+        saving_failed: "saving failed",
+        cannot_reload: "cannot reload notes",
+        not_watching: "not watching for changes",
+        merged_in: |n| match n {
+            0 => "merged notes saved by another feednotes".into(),
+            1 => format!(
+                "a note was changed by another feednotes too, kept both \
+                 versions and tagged yours #{}",
+                CONFLICT_TAG
+            ),
+            n => format!(
+                "{} notes were changed by another feednotes too, kept both \
+                 versions and tagged yours #{}",
+                n, CONFLICT_TAG
+            ),
+        },
+        synced: |n| match n {
+            0 => "synced".into(),
+            n => format!(
+                "synced, {} notes changed on both sides were kept twice, \
+                 yours tagged #{}",
+                n, CONFLICT_TAG
+            ),
+        },
+        switched: |name| format!("switched to notebook {}", name),
+        already_in: |name| format!("note is already in {}", name),
+        moved: |n, name| match n {
+            1 => format!("moved note to {}", name),
+            n => format!("moved {} notes to {}", n, name),
+        },
+        invalid_notebook: |name| format!("invalid notebook name `{}`", name),
+        nothing_to_undo: "nothing to undo",
+        restored_deleted: |n| match n {
+            1 => "restored deleted note".into(),
+            n => format!("restored {} deleted notes", n),
+        },
+        reverted_edit: "reverted edit",
+        joined_split: "joined the split note again",
+        edited: "note edited — press u to undo",
+        discarded_edit: "discarded the edit",
+        written_before: "written before, so nothing was added",
+        merged_into: "merged into the note written before — press u to undo",
+        mark_to_merge: "mark the notes to merge with v",
+        merged: |n| format!("merged {} notes — press u to undo", n),
+        single_line: "the note has a single line",
+        nothing_to_split: "nothing to split off there",
+        split: "split the note — press u to undo",
+        restored: "note restored",
+        deleted_forever: "note deleted forever",
+        never_edited: "the note was never edited",
+        restored_version: |date| format!("restored the version from {}", date),
+        no_template: |n| format!("there is no template {}", n),
+        no_templates: |dir| format!("no templates in {}", dir),
+        no_earlier_position: "no earlier position",
+        no_note_called: |name| format!("no note called `{}`", name),
+        copied_notes: |n| match n {
+            1 => "copied note to clipboard".into(),
+            n => format!("copied {} notes to clipboard", n),
+        },
+        copied_chars: |n| format!("copied {} characters", n),
+        no_links: "no links in this note",
+        opened: |url| format!("opened {}", url),
+        cannot_open: |url, e| format!("cannot open {}: {}", url, e),
+        sorted: |sort| format!("sorted {}", sort),
+        sort_not_saved: |sort, e| {
+            format!("sorted {}, but saving it failed: {}", sort, e)
+        },
+        saving_layout_failed: "saving the layout failed",
+        starred: |n, star| match (n, star) {
+            (1, true) => "starred note".into(),
+            (1, false) => "unstarred note".into(),
+            (n, true) => format!("starred {} notes", n),
+            (n, false) => format!("unstarred {} notes", n),
+        },
+        no_starred: "no starred notes",
+        already_posted: |url| format!("already posted at {}", url),
+        posted: |url| format!("posted at {}", url),
+        posting_failed: "posting failed",
+        no_attachments: "no attachments",
+        attached: |name| format!("attached {}", name),
+        cannot_attach: |name, e| format!("cannot attach {}: {}", name, e),
+        attachment_removed: "attachment removed",
+        pasted: |name| format!("pasted {}", name),
+        no_image: "no image on the clipboard",
+        cannot_paste: "cannot paste an image",
+        no_checkboxes: "no checkboxes in this note",
+        invalid_tag: |tag| format!("invalid tag `{}`", tag),
+        tagged: |n, of, tag| format!("tagged {} of {} notes #{}", n, of, tag),
+        already_archived: "already in the archive",
+        archive_search: "archived notes are only searched by feednotes search",
+        no_notes_from: |period| format!("no notes from {}", period),
+        no_notes_on: |day| format!("no notes on {}", day),
+        no_notes_around: |day, after| match after {
+            true => format!("no notes on or after {}", day),
+            false => format!("no notes on or before {}", day),
+        },
+        invalid_date: |day| {
+            format!("invalid date `{}`, expected YYYY-MM-DD", day)
+        },
+        no_location_command: "set location_command in the config to use here",
+        unknown_place: |place| {
+            format!("unknown place or coordinates `{}`", place)
+        },
+        located: |n, place| format!("located {} notes at {}", n, place),
+        unlocated: |n| format!("removed the location of {} notes", n),
+        cannot_locate: "cannot locate the note",
+        exported: |n, path| format!("exported {} notes to {}", n, path),
+        cannot_export: |path, e| format!("cannot export to {}: {}", path, e),
+        cannot_send: "cannot send the note on",
+        unknown_command: |command| format!("unknown command `{}`", command),
+        spelling_off: "spell checking is off",
+        no_word: "no word under the cursor",
+        spelled_right: |word| format!("`{}` is spelled right", word),
+        no_suggestions: |word| format!("no suggestions for `{}`", word),
+        not_locking: "no passphrase chosen, not locking",
+        empty_passphrase: "empty passphrase",
+        passphrases_differ: "passphrases do not match",
+        wrong_passphrase: "wrong passphrase",
+    },
+
+    words: "words",
+    chars: "chars",
+    notes: |n| match n {
+        1 => "1 note".into(),
+        n => format!("{} notes", n),
+    },
+    marked: |n| format!("{} marked", n),
+    confirm_delete: |n| match n {
+        0 => "Delete this note? (y/n)".into(),
+        n => format!("Delete {} notes? (y/n)", n),
+    },
+    edited: |date| format!(" (edited {})", date),
+    changed_on: |machine| format!(" · changed on {}", machine),
+    linked_from: "Linked from",
+    passphrase: "Passphrase",
+    choose_passphrase: "Choose a passphrase to lock with",
+    repeat_passphrase: "Repeat the passphrase",
+    no_color: "none",
+    topic_words: "words",
+    topic_tags: "tags",
+};
+
+const JA: Strings = Strings {
+    modes: Modes {
+        normal: "ノーマル",
+        insert: "挿入",
+        view: "選択",
+        view_line: "行選択",
+    },
+    labels: Labels {
+        feed: "フィード",
+        trash: "ゴミ箱",
+        delete: "削除",
+        tags: "タグ",
+        notebooks: "ノートブック",
+        read: "閲覧",
+        attachments: "添付ファイル",
+        toggle: "チェック",
+        split: "分割",
+        templates: "テンプレート",
+        stats: "統計",
+        topics: "トピック",
+        color: "色",
+        spelling: "スペル",
+        review: "振り返り",
+        versions: "履歴",
+        go_to_note: "ノートへ移動",
+        links: "リンク",
+        calendar: "カレンダー",
+        locked: "ロック中",
+        scratch: "スクラッチ",
+        compose: "作成",
+        filter: "絞り込み",
+        go_to_date: "日付へ移動",
+        new_notebook: "新しいノートブック",
+        attach: "添付",
+        tag: "タグ付け",
+        export: "書き出し",
+        command: "コマンド",
+        location: "位置",
+        select: "選択",
+        messages: "メッセージ",
+        duplicate: "重複",
+        read_only: "読み取り専用",
+    },
+    titles: Titles {
+        new_note: "新しいノート",
+        reply: "返信",
+        capture: "クイックメモ",
+        filtering: "絞り込み",
+        go_to_date: "日付へ移動",
+        attach_file: "ファイルを添付",
+        add_tag: "タグを追加",
+        export_to_file: "ファイルに書き出し",
+        new_notebook: "新しいノートブック",
+        suggestions: "候補",
+        go_to_note: "ノートへ移動",
+        save_changes: "変更を保存しますか?",
+        tags: "タグ",
+        notebooks: "ノートブック",
+        move_note_to: "ノートの移動先",
+        move_notes_to: "ノートの移動先",
+        attachments: "添付ファイル",
+        versions: "以前の版",
+        lock: "ロック",
+        locked: "ロック中",
+        scratch: "スクラッチ",
+        review: "振り返り",
+        links: "リンク",
+        color: "色",
+        templates: "テンプレート",
+        stats: "統計",
+        split: "分割",
+        toggle: "チェック",
+        command: "コマンド",
+        location: "位置情報",
+        select: "テキストを選択",
+        messages: "メッセージ",
+        duplicate: "既に書いたノート?",
+    },
+    hints: Hints {
+        capture: "ctrl-s 保存 · ctrl-c 破棄",
+        links: "tab: リンク, enter: 開く",
+        save_changes: "y: 保存 · n: 編集を続ける · d: 破棄",
+        notebooks: "n: 新しいノートブック",
+        attachments: "x: 削除",
+        versions: "enter: 復元",
+        scratch: "V の後 n: ノートにする",
+        select: "v か V の後 y: コピー",
+        calendar: "enter: この日のノート",
+        topics: "h/l: 移動 · w/m: 週/月 · enter: ノート",
+        split: "enter: この行の前で分割",
+        toggle: "space: 切り替え",
+        review: "space: 残す · a: アーカイブ · t: タグ · d: 削除 · q: 終了",
+        duplicate: "m: 統合 · b: 両方残す · n: 編集を続ける",
+        trash: "ゴミ箱: r 復元, dd 完全に削除, q 戻る",
+    },
+    status: Status {
+        read_only: "読み取り専用です",
+        saved: "保存しました",
+        unsaved: "保存していない変更があります、:wq か :q!",
+        saving_failed: "保存に失敗しました",
+        cannot_reload: "ノートを再読み込みできません",
+        not_watching: "変更を監視していません",
+        merged_in: |n| match n {
+            0 => "別の feednotes が保存したノートを取り込みました".into(),
+            n => format!(
+                "{}件のノートが別の feednotes でも変更されたため、\
+                 両方の版を残し、こちらの版に #{} を付けました",
+                n, CONFLICT_TAG
+            ),
+        },
+        synced: |n| match n {
+            0 => "同期しました".into(),
+            n => format!(
+                "同期しました、両方で変更された{}件のノートは\
+                 両方の版を残し、こちらの版に #{} を付けました",
+                n, CONFLICT_TAG
+            ),
+        },
+        switched: |name| format!("ノートブック {} に切り替えました", name),
+        already_in: |name| format!("ノートは既に {} にあります", name),
+        moved: |n, name| format!("{}件のノートを {} に移動しました", n, name),
+        invalid_notebook: |name| {
+            format!("`{}` はノートブックの名前に使えません", name)
+        },
+        nothing_to_undo: "元に戻す操作はありません",
+        restored_deleted: |n| {
+            format!("削除した{}件のノートを元に戻しました", n)
+        },
+        reverted_edit: "編集を元に戻しました",
+        joined_split: "分割したノートを元に戻しました",
+        edited: "ノートを編集しました — u で元に戻せます",
+        discarded_edit: "編集を破棄しました",
+        written_before: "既に書いたノートなので、追加しませんでした",
+        merged_into: "既に書いたノートに統合しました — u で元に戻せます",
+        mark_to_merge: "統合するノートを v で選んでください",
+        merged: |n| format!("{}件のノートを統合しました — u で元に戻せます", n),
+        single_line: "ノートが1行しかありません",
+        nothing_to_split: "そこでは分割できません",
+        split: "ノートを分割しました — u で元に戻せます",
+        restored: "ノートを元に戻しました",
+        deleted_forever: "ノートを完全に削除しました",
+        never_edited: "このノートは編集されたことがありません",
+        restored_version: |date| format!("{} の版に戻しました", date),
+        no_template: |n| format!("テンプレート {} はありません", n),
+        no_templates: |dir| format!("{} にテンプレートがありません", dir),
+        no_earlier_position: "これより前の位置はありません",
+        no_note_called: |name| format!("`{}` というノートはありません", name),
+        copied_notes: |n| {
+            format!("{}件のノートをクリップボードにコピーしました", n)
+        },
+        copied_chars: |n| format!("{}文字をコピーしました", n),
+        no_links: "このノートにリンクはありません",
+        opened: |url| format!("{} を開きました", url),
+        cannot_open: |url, e| format!("{} を開けません: {}", url, e),
+        sorted: |sort| format!("並べ替えました: {}", sort),
+        sort_not_saved: |sort, e| {
+            format!("並べ替えました: {}、ただし保存に失敗しました: {}", sort, e)
+        },
+        saving_layout_failed: "レイアウトの保存に失敗しました",
+        starred: |n, star| match star {
+            true => format!("{}件のノートにスターを付けました", n),
+            false => format!("{}件のノートのスターを外しました", n),
+        },
+        no_starred: "スター付きのノートはありません",
+        already_posted: |url| format!("{} に投稿済みです", url),
+        posted: |url| format!("{} に投稿しました", url),
+        posting_failed: "投稿に失敗しました",
+        no_attachments: "添付ファイルはありません",
+        attached: |name| format!("{} を添付しました", name),
+        cannot_attach: |name, e| format!("{} を添付できません: {}", name, e),
+        attachment_removed: "添付ファイルを削除しました",
+        pasted: |name| format!("{} を貼り付けました", name),
+        no_image: "クリップボードに画像がありません",
+        cannot_paste: "画像を貼り付けられません",
+        no_checkboxes: "このノートにチェックボックスはありません",
+        invalid_tag: |tag| format!("`{}` はタグに使えません", tag),
+        tagged: |n, of, tag| {
+            format!("{}件中{}件のノートに #{} を付けました", of, n, tag)
+        },
+        already_archived: "既にアーカイブにあります",
+        archive_search:
+            "アーカイブのノートは feednotes search でしか検索できません",
+        no_notes_from: |period| format!("{}のノートはありません", period),
+        no_notes_on: |day| format!("{} のノートはありません", day),
+        no_notes_around: |day, after| match after {
+            true => format!("{} 以降のノートはありません", day),
+            false => format!("{} 以前のノートはありません", day),
+        },
+        invalid_date: |day| {
+            format!(
+                "`{}` は日付ではありません、YYYY-MM-DD で入力してください",
+                day
+            )
+        },
+        no_location_command:
+            "here を使うには設定で location_command を指定してください",
+        unknown_place: |place| {
+            format!("`{}` という場所や座標は分かりません", place)
+        },
+        located: |n, place| {
+            format!("{}件のノートの位置を {} にしました", n, place)
+        },
+        unlocated: |n| format!("{}件のノートの位置情報を削除しました", n),
+        cannot_locate: "ノートの位置を取得できません",
+        exported: |n, path| {
+            format!("{}件のノートを {} に書き出しました", n, path)
+        },
+        cannot_export: |path, e| format!("{} に書き出せません: {}", path, e),
+        cannot_send: "ノートを送信できません",
+        unknown_command: |command| {
+            format!("`{}` というコマンドはありません", command)
+        },
+        spelling_off: "スペルチェックは無効です",
+        no_word: "カーソルの下に単語がありません",
+        spelled_right: |word| format!("`{}` のつづりは正しいです", word),
+        no_suggestions: |word| format!("`{}` の候補はありません", word),
+        not_locking: "パスフレーズが選ばれなかったので、ロックしません",
+        empty_passphrase: "パスフレーズが空です",
+        passphrases_differ: "パスフレーズが一致しません",
+        wrong_passphrase: "パスフレーズが違います",
+    },
+
+    words: "語",
+    chars: "文字",
+    notes: |n| format!("{}件のノート", n),
+    marked: |n| format!("{}件をマーク", n),
+    confirm_delete: |n| match n {
+        0 => "このノートを削除しますか? (y/n)".into(),
+        n => format!("{}件のノートを削除しますか? (y/n)", n),
+    },
+    edited: |date| format!(" (編集 {})", date),
+    changed_on: |machine| format!(" · {}で変更", machine),
+    linked_from: "リンク元",
+    passphrase: "パスフレーズ",
+    choose_passphrase: "ロックに使うパスフレーズ",
+    repeat_passphrase: "パスフレーズをもう一度",
+    no_color: "なし",
+    topic_words: "単語",
+    topic_tags: "タグ",
+};
@@ -0,0 +1,278 @@
+//! Reading notes kept elsewhere: a directory of Markdown or plain text
+//! files, one note per file, Org files with a note per heading, or a JSON
+//! file written by any version of feednotes.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone};
+
+use crate::model::{extract_tags, Feed, Note};
+
+#[derive(Clone, Copy)]
+pub enum Format {
+    /// `*.md` files, dated by a `date:` line in their front matter if they
+    /// have one.
+    Markdown,
+    /// `*.txt` files.
+    Text,
+    /// A notes file, either a feed or a bare list of notes.
+    Json,
+    /// `*.org` files, with a note for every top-level heading, like the ones
+    /// `feednotes export --format org` writes.
+    Org,
+}
+
+impl Format {
+    pub fn parse(s: &str) -> Option<Format> {
+        match s {
+            "md" | "markdown" => Some(Format::Markdown),
+            "txt" | "text" => Some(Format::Text),
+            "json" => Some(Format::Json),
+            "org" => Some(Format::Org),
+            _ => None,
+        }
+    }
+}
+
+/// Reads the notes at `path`, which is a file or, for Markdown, text and Org,
+/// a directory searched recursively. Files without a date of their own are
+/// dated by their modification time.
+pub fn read(
+    format: Format,
+    path: &Path,
+) -> Result<Vec<Note>, Box<dyn std::error::Error>> {
+    if let Format::Json = format {
+        let data = fs::read(path)?;
+        let notes = match serde_json::from_slice::<Feed>(&data) {
+            Ok(feed) => feed.notes.into(),
+            Err(_) => serde_json::from_slice::<Vec<Note>>(&data)?,
+        };
+        return Ok(notes
+            .into_iter()
+            .map(|mut note| {
+                if note.tags.is_empty() {
+                    note.tags = extract_tags(&note.text);
+                }
+                note
+            })
+            .collect());
+    }
+
+    let extension = match format {
+        Format::Markdown => "md",
+        Format::Org => "org",
+        _ => "txt",
+    };
+    let mut files = Vec::new();
+    if path.is_dir() {
+        find_files(path, extension, &mut files)?;
+    } else {
+        files.push(path.to_path_buf());
+    }
+    files.sort();
+
+    let mut notes = Vec::new();
+    for file in files {
+        let content = fs::read_to_string(&file)?;
+        if let Format::Org = format {
+            let modified: DateTime<Local> =
+                fs::metadata(&file)?.modified()?.into();
+            for (date, text) in org_headings(&content) {
+                let mut note = Note::new(text);
+                note.date = date.unwrap_or(modified);
+                notes.push(note);
+            }
+            continue;
+        }
+        let (date, text) = match format {
+            Format::Markdown => front_matter_date(&content),
+            _ => (None, content.as_str()),
+        };
+        let text = text.trim();
+        if text.is_empty() {
+            continue;
+        }
+        let date = match date {
+            Some(date) => date,
+            None => fs::metadata(&file)?.modified()?.into(),
+        };
+        let mut note = Note::new(text.to_string());
+        note.date = date;
+        notes.push(note);
+    }
+    Ok(notes)
+}
+
+/// Adds `notes` to `feed` in date order, leaving out those whose text is
+/// already in the feed so importing the same files twice adds nothing the
+/// second time. Returns how many were added.
+pub fn merge(feed: &mut Feed, notes: Vec<Note>) -> usize {
+    let mut added = 0;
+    for note in notes {
+        if feed.notes.iter().any(|n| n.text.trim() == note.text.trim()) {
+            continue;
+        }
+        feed.insert(note);
+        added += 1;
+    }
+    added
+}
+
+/// Collects the files in `dir` and below with the given extension, skipping
+/// hidden directories like `.git`.
+fn find_files(
+    dir: &Path,
+    extension: &str,
+    files: &mut Vec<PathBuf>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            if !path
+                .file_name()
+                .is_some_and(|n| n.to_string_lossy().starts_with('.'))
+            {
+                find_files(&path, extension, files)?;
+            }
+        } else if path.extension().is_some_and(|e| e == extension) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Splits off a `---` delimited front matter block, returning the `date:` in
+/// it, if any, and the text after it.
+fn front_matter_date(content: &str) -> (Option<DateTime<Local>>, &str) {
+    let Some(rest) = content.strip_prefix("---\n") else {
+        return (None, content);
+    };
+    let Some(end) = rest.find("\n---") else {
+        return (None, content);
+    };
+    let (front, body) = rest.split_at(end);
+    let body = body["\n---".len()..].trim_start_matches('-');
+    let date = front.lines().find_map(|line| {
+        let value = line.strip_prefix("date:")?.trim().trim_matches('"');
+        parse_date(value)
+    });
+    (date, body)
+}
+
+/// The date and text of the note under each top-level heading of an Org file.
+/// A timestamp on the first line under the heading, or a `CREATED` property,
+/// dates the note. The text is the heading followed by what is under it,
+/// unless that already starts with the heading, and gets the heading's tags
+/// that it does not have yet.
+fn org_headings(content: &str) -> Vec<(Option<DateTime<Local>>, String)> {
+    let mut headings: Vec<(&str, Vec<&str>)> = Vec::new();
+    for line in content.lines() {
+        if let Some(heading) = line.strip_prefix("* ") {
+            headings.push((heading, Vec::new()));
+        } else if let Some((_, body)) = headings.last_mut() {
+            body.push(line);
+        }
+    }
+
+    let mut notes = Vec::new();
+    for (heading, body) in headings {
+        let (heading, tags) =
+            match heading.trim_end().rsplit_once(char::is_whitespace) {
+                Some((rest, tags))
+                    if tags.len() > 2
+                        && tags.starts_with(':')
+                        && tags.ends_with(':') =>
+                {
+                    let tags: Vec<&str> =
+                        tags.split(':').filter(|t| !t.is_empty()).collect();
+                    (rest.trim_end(), tags)
+                }
+                _ => (heading.trim(), Vec::new()),
+            };
+
+        let mut lines =
+            body.into_iter().skip_while(|l| l.trim().is_empty()).peekable();
+        let mut date = None;
+        if lines.next_if(|l| l.trim() == ":PROPERTIES:").is_some() {
+            for line in lines.by_ref() {
+                let line = line.trim();
+                if line == ":END:" {
+                    break;
+                }
+                if let Some(created) = line.strip_prefix(":CREATED:") {
+                    date = org_timestamp(created.trim());
+                }
+            }
+        }
+        if let Some(stamp) = lines.peek().and_then(|l| org_timestamp(l.trim()))
+        {
+            date = Some(stamp);
+            lines.next();
+        }
+        let body: Vec<&str> = lines
+            .map(|line| match line.strip_prefix(',') {
+                Some(rest) if rest.trim_start_matches(',').starts_with('*') => {
+                    rest
+                }
+                _ => line,
+            })
+            .collect();
+        let body = body.join("\n");
+        let body = body.trim();
+
+        let mut text = if body.is_empty() {
+            heading.to_string()
+        } else if Note::new(body.to_string()).title() == heading {
+            body.to_string()
+        } else {
+            format!("{}\n\n{}", heading, body)
+        };
+        let have = extract_tags(&text);
+        let missing: Vec<String> = tags
+            .iter()
+            .filter(|t| !have.iter().any(|h| h.replace('-', "_") == **t))
+            .map(|t| format!("#{}", t))
+            .collect();
+        if !missing.is_empty() {
+            text = format!("{}\n\n{}", text, missing.join(" "));
+        }
+        if !text.is_empty() {
+            notes.push((date, text));
+        }
+    }
+    notes
+}
+
+/// Parses an Org timestamp like `[2024-05-01 Wed 21:30]` or `<2024-05-01>`.
+fn org_timestamp(s: &str) -> Option<DateTime<Local>> {
+    let inner = s
+        .strip_prefix('[')
+        .and_then(|s| s.strip_suffix(']'))
+        .or_else(|| s.strip_prefix('<').and_then(|s| s.strip_suffix('>')))?;
+    // without the day of the week
+    let parts: Vec<&str> = inner
+        .split_whitespace()
+        .filter(|p| !p.chars().all(char::is_alphabetic))
+        .collect();
+    parse_date(&parts.join(" "))
+}
+
+/// Parses the usual ways of writing a date in front matter, taking dates
+/// without an offset as local time.
+fn parse_date(s: &str) -> Option<DateTime<Local>> {
+    if let Ok(date) = DateTime::parse_from_rfc3339(s) {
+        return Some(date.with_timezone(&Local));
+    }
+    let naive = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S"]
+        .iter()
+        .find_map(|f| NaiveDateTime::parse_from_str(s, f).ok())
+        .or_else(|| {
+            NaiveDate::parse_from_str(s, "%Y-%m-%d")
+                .ok()
+                .and_then(|d| d.and_hms_opt(0, 0, 0))
+        })?;
+    Local.from_local_datetime(&naive).earliest()
+}
@@ -0,0 +1,41 @@
+//! Core of feednotes: the note model, persistence and filtering, plus the
+//! terminal frontend built on top of them.
+
+pub mod attachments;
+pub mod backend;
+pub mod chord;
+pub mod cli;
+pub mod clipboard;
+pub mod complete;
+pub mod config;
+pub mod crypt;
+pub mod curl;
+pub mod diff;
+pub mod dump;
+pub mod emoji;
+pub mod export;
+pub mod filter;
+pub mod fuzzy;
+pub mod geo;
+pub mod i18n;
+pub mod import;
+pub mod mastodon;
+pub mod model;
+pub mod paths;
+pub mod saver;
+pub mod server;
+pub mod setup;
+pub mod signals;
+pub mod snippets;
+pub mod spell;
+pub mod stats;
+pub mod store;
+pub mod sync;
+pub mod templates;
+pub mod timefmt;
+pub mod toasts;
+pub mod topics;
+pub mod ui;
+pub mod watch;
+pub mod webdav;
+pub mod webhooks;
@@ -0,0 +1,71 @@
+//! Posting notes to Mastodon, through [`curl`].
+
+use serde::Deserialize;
+
+use crate::{config, curl, model::Note};
+
+#[derive(Deserialize)]
+struct Status {
+    url: Option<String>,
+    uri: String,
+}
+
+#[derive(Deserialize)]
+struct ApiError {
+    error: String,
+}
+
+/// Posts the text of `note`, from the notebook named `notebook`, as a new
+/// status and returns its address.
+pub fn post(
+    settings: &config::Mastodon,
+    notebook: &str,
+    note: &Note,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let (Some(instance), Some(token)) = (&settings.instance, &settings.token)
+    else {
+        return Err(
+            "set instance and token under [mastodon] in the config".into()
+        );
+    };
+
+    let mut options = curl::Options::default();
+    options.push(
+        "url",
+        &format!("{}/api/v1/statuses", instance.trim_end_matches('/')),
+    );
+    options.push("header", &format!("Authorization: Bearer {}", token));
+    // posting the same note twice in a row makes one status
+    options.push(
+        "header",
+        &format!("Idempotency-Key: {}", idempotency_key(notebook, note)),
+    );
+    options.push("data-urlencode", &format!("status={}", note.text));
+    if let Some(visibility) = &settings.visibility {
+        options.push("data-urlencode", &format!("visibility={}", visibility));
+    }
+
+    let output = curl::run(&["--silent", "--show-error"], &options)?;
+    match serde_json::from_slice::<Status>(&output) {
+        Ok(status) => Ok(status.url.unwrap_or(status.uri)),
+        Err(_) => match serde_json::from_slice::<ApiError>(&output) {
+            Ok(e) => Err(format!("{}: {}", instance, e.error).into()),
+            Err(_) => Err(format!("{}: unexpected response", instance).into()),
+        },
+    }
+}
+
+/// The same for every try at posting one revision of a note, and different
+/// for notes of other notebooks with its id. Characters of the notebook name
+/// that may not go in a header are percent-encoded.
+fn idempotency_key(notebook: &str, note: &Note) -> String {
+    let mut key = String::from("feednotes-");
+    for b in notebook.bytes() {
+        if b.is_ascii_alphanumeric() || b == b'_' {
+            key.push(b as char);
+        } else {
+            key += &format!("%{:02X}", b);
+        }
+    }
+    key + &format!("-{}-{}", note.id, note.revision)
+}
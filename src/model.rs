@@ -0,0 +1,664 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    ops::Range,
+    path::PathBuf,
+    sync::OnceLock,
+};
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone};
+use serde::{Deserialize, Serialize};
+
+use crate::{diff, store::Snapshot};
+
+/// Trashed notes older than this many days are purged when the feed is loaded.
+pub const TRASH_RETENTION_DAYS: i64 = 30;
+
+/// How many days back a new note is compared with the notes before it to
+/// find it is one of them written again.
+pub const DUPLICATE_DAYS: i64 = 30;
+
+/// How alike, word by word, a note has to be to one written before it to be
+/// taken for it.
+const DUPLICATE_SIMILARITY: f64 = 0.9;
+
+/// Identifies a note within its feed. Ids are never reused, so they stay valid
+/// however the feed is reordered or trimmed.
+pub type NoteId = u64;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Note {
+    /// 0 until the note is added to a feed.
+    #[serde(default)]
+    pub id: NoteId,
+    pub text: String,
+    pub date: DateTime<Local>,
+    /// Set the last time the note was edited.
+    #[serde(default)]
+    pub modified: Option<DateTime<Local>>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Set when the note is in the trash.
+    #[serde(default)]
+    pub deleted_at: Option<DateTime<Local>>,
+    /// The note this one is a reply to.
+    #[serde(default)]
+    pub reply_to: Option<NoteId>,
+    /// Names of the attached files in the attachments directory.
+    #[serde(default)]
+    pub attachments: Vec<PathBuf>,
+    #[serde(default)]
+    pub color: Option<NoteColor>,
+    /// Kept at hand with `*`, and found with `is:starred`.
+    #[serde(default)]
+    pub starred: bool,
+    /// Where the note was posted to Mastodon.
+    #[serde(default)]
+    pub posted: Option<String>,
+    /// The texts the note had before it was edited, oldest first.
+    #[serde(default)]
+    pub revisions: Vec<Revision>,
+    /// Where the note was written, set with `@`.
+    #[serde(default)]
+    pub location: Option<Location>,
+    /// Goes up with each save that changes the note, to tell a note changed
+    /// elsewhere since it was loaded from one that was not.
+    #[serde(default)]
+    pub revision: u64,
+    /// The machine the note was last changed on, see [`machine`].
+    #[serde(default)]
+    pub last_writer: Option<String>,
+}
+
+/// A point on the earth, in degrees.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Location {
+    pub lat: f64,
+    pub lon: f64,
+    /// The place from the config it was picked as, if it was.
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+/// A text a note had before an edit.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Revision {
+    pub text: String,
+    /// When the note was given this text.
+    pub date: DateTime<Local>,
+}
+
+impl Revision {
+    /// Like [`Note::title`].
+    pub fn title(&self) -> &str {
+        title(&self.text)
+    }
+}
+
+/// A color a note can be marked with, to tell kinds of notes apart at a
+/// glance.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NoteColor {
+    Red,
+    Yellow,
+    Green,
+    Cyan,
+    Blue,
+    Magenta,
+}
+
+impl NoteColor {
+    pub const ALL: [NoteColor; 6] = [
+        NoteColor::Red,
+        NoteColor::Yellow,
+        NoteColor::Green,
+        NoteColor::Cyan,
+        NoteColor::Blue,
+        NoteColor::Magenta,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            NoteColor::Red => "red",
+            NoteColor::Yellow => "yellow",
+            NoteColor::Green => "green",
+            NoteColor::Cyan => "cyan",
+            NoteColor::Blue => "blue",
+            NoteColor::Magenta => "magenta",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<NoteColor> {
+        NoteColor::ALL.into_iter().find(|c| c.name() == s)
+    }
+}
+
+impl Note {
+    /// A note written now, with its tags taken from the text.
+    pub fn new(text: String) -> Note {
+        Note {
+            id: 0,
+            tags: extract_tags(&text),
+            text,
+            date: Local::now(),
+            modified: None,
+            deleted_at: None,
+            reply_to: None,
+            attachments: Vec::new(),
+            color: None,
+            starred: false,
+            posted: None,
+            revisions: Vec::new(),
+            location: None,
+            revision: 0,
+            last_writer: None,
+        }
+    }
+
+    /// Marks the note as a new revision, written on this machine.
+    pub fn written(&mut self) {
+        self.revision += 1;
+        self.last_writer = Some(machine().to_string());
+    }
+
+    /// Edits the note to read `text`, keeping the text it had as a revision.
+    pub fn set_text(&mut self, text: String) {
+        if text == self.text {
+            return;
+        }
+        let old = std::mem::replace(&mut self.text, text);
+        self.revisions.push(Revision {
+            text: old,
+            date: self.modified.unwrap_or(self.date),
+        });
+        self.tags = extract_tags(&self.text);
+        self.modified = Some(Local::now());
+    }
+
+    /// The first line of the note that is not blank, without a Markdown
+    /// heading's `#`.
+    pub fn title(&self) -> &str {
+        title(&self.text)
+    }
+
+    /// When the note is due, from a `due:2024-05-01` or `due:2024-05-01T09:00`
+    /// word in it.
+    pub fn due(&self) -> Option<When> {
+        when(&self.text, "due:")
+    }
+
+    /// When the note should remind of itself, from a `remind:` word written
+    /// the same way.
+    pub fn remind(&self) -> Option<When> {
+        when(&self.text, "remind:")
+    }
+
+    /// The indices of the lines of the note that are checkboxes.
+    pub fn checkboxes(&self) -> Vec<usize> {
+        self.text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| checkbox(line).is_some())
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Whether the note has a checkbox left unchecked.
+    pub fn has_open_todo(&self) -> bool {
+        self.text.lines().any(|line| checkbox(line).is_some_and(|(c, _)| !c))
+    }
+
+    /// Checks or unchecks the checkbox on line `line`, if there is one there.
+    pub fn toggle_checkbox(&mut self, line: usize) {
+        let lines: Vec<String> = self
+            .text
+            .lines()
+            .enumerate()
+            .map(|(i, l)| match checkbox(l) {
+                Some((checked, rest)) if i == line => {
+                    let indent = &l[..l.len() - l.trim_start().len()];
+                    let mark = if checked { ' ' } else { 'x' };
+                    format!("{}- [{}] {}", indent, mark, rest)
+                        .trim_end()
+                        .to_string()
+                }
+                _ => l.to_string(),
+            })
+            .collect();
+        let trailing = self.text.ends_with('\n');
+        self.text = lines.join("\n");
+        if trailing {
+            self.text.push('\n');
+        }
+    }
+}
+
+fn title(text: &str) -> &str {
+    let line = text.lines().find(|l| !l.trim().is_empty());
+    let line = line.unwrap_or("").trim();
+    match line.trim_start_matches('#').strip_prefix(' ') {
+        Some(heading) if line.starts_with('#') => heading.trim(),
+        _ => line,
+    }
+}
+
+/// A day, or a time of a day.
+#[derive(Clone, Copy)]
+pub enum When {
+    Day(NaiveDate),
+    At(DateTime<Local>),
+}
+
+/// The day or time in the first word of `text` starting with `prefix`.
+fn when(text: &str, prefix: &str) -> Option<When> {
+    text.split_whitespace().find_map(|word| {
+        let value = word
+            .strip_prefix(prefix)?
+            .trim_end_matches(|c: char| !c.is_ascii_digit());
+        if let Ok(at) = NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M") {
+            return Some(When::At(Local.from_local_datetime(&at).earliest()?));
+        }
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").ok().map(When::Day)
+    })
+}
+
+/// Splits a `- [ ] item` or `- [x] item` line into whether it is checked and
+/// the item. `None` for any other line.
+pub fn checkbox(line: &str) -> Option<(bool, &str)> {
+    let rest = line.trim_start().strip_prefix("- [")?;
+    let checked = match rest.chars().next()? {
+        ' ' => false,
+        'x' | 'X' => true,
+        _ => return None,
+    };
+    let rest = rest[1..].strip_prefix(']')?;
+    match rest.strip_prefix(' ') {
+        Some(item) => Some((checked, item)),
+        None if rest.is_empty() => Some((checked, rest)),
+        None => None,
+    }
+}
+
+/// The `[[target]]` links in `text`, as the byte range of each link with its
+/// target.
+pub fn links(text: &str) -> Vec<(Range<usize>, &str)> {
+    let mut links = Vec::new();
+    let mut from = 0;
+    while let Some(start) = text[from..].find("[[").map(|i| from + i) {
+        let Some(len) = text[start + 2..].find("]]") else {
+            break;
+        };
+        let target = &text[start + 2..start + 2 + len];
+        if target.trim().is_empty() || target.contains(['[', '\n']) {
+            from = start + 1;
+            continue;
+        }
+        from = start + 2 + len + 2;
+        links.push((start..from, target.trim()));
+    }
+    links
+}
+
+/// The byte ranges of the `http://` and `https://` addresses in `text`,
+/// without punctuation that follows them in a sentence.
+pub fn urls(text: &str) -> Vec<Range<usize>> {
+    let mut urls = Vec::new();
+    let mut from = 0;
+    while let Some(start) = text[from..].find("http").map(|i| from + i) {
+        let rest = &text[start..];
+        from = start + 4;
+        let scheme =
+            ["https://", "http://"].into_iter().find(|s| rest.starts_with(s));
+        // part of a longer word
+        let inside = text[..start]
+            .chars()
+            .next_back()
+            .is_some_and(char::is_alphanumeric);
+        let (Some(scheme), false) = (scheme, inside) else {
+            continue;
+        };
+        let mut url = rest.split(char::is_whitespace).next().unwrap();
+        loop {
+            let trimmed =
+                url.trim_end_matches(['.', ',', ';', ':', '!', '?', '\'', '"']);
+            // a closing bracket is kept when the address opened it
+            let trimmed = match trimmed.chars().next_back() {
+                Some(c @ (')' | ']' | '>')) => {
+                    let open = match c {
+                        ')' => '(',
+                        ']' => '[',
+                        _ => '<',
+                    };
+                    if trimmed.matches(open).count()
+                        < trimmed.matches(c).count()
+                    {
+                        &trimmed[..trimmed.len() - 1]
+                    } else {
+                        trimmed
+                    }
+                }
+                _ => trimmed,
+            };
+            if trimmed == url {
+                break;
+            }
+            url = trimmed;
+        }
+        if url.len() > scheme.len() {
+            urls.push(start..start + url.len());
+            from = start + url.len();
+        }
+    }
+    urls
+}
+
+/// Collects the distinct `#tag` words of `text`, without the leading `#`.
+pub fn extract_tags(text: &str) -> Vec<String> {
+    let mut tags = Vec::new();
+    for word in text.split_whitespace() {
+        let Some(tag) = word.strip_prefix('#') else {
+            continue;
+        };
+        let tag: String = tag
+            .chars()
+            .take_while(|c| c.is_alphanumeric() || *c == '_' || *c == '-')
+            .collect();
+        if !tag.is_empty() && !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+/// The name of this machine, written into the notes changed on it.
+pub fn machine() -> &'static str {
+    static MACHINE: OnceLock<String> = OnceLock::new();
+    MACHINE.get_or_init(|| {
+        #[cfg(unix)]
+        {
+            let mut name = [0u8; 256];
+            let ok = unsafe {
+                libc::gethostname(name.as_mut_ptr().cast(), name.len())
+            } == 0;
+            let end = name.iter().position(|&b| b == 0).unwrap_or(0);
+            if ok && end > 0 {
+                return String::from_utf8_lossy(&name[..end]).into_owned();
+            }
+        }
+        std::env::var("COMPUTERNAME").unwrap_or_else(|_| "unknown".into())
+    })
+}
+
+/// The words of `text` in lower case, without punctuation and one space
+/// apart.
+fn normalized(text: &str) -> String {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Feed {
+    pub notes: VecDeque<Note>,
+    /// The highest id ever given out, kept so ids of purged notes are not
+    /// handed out again.
+    #[serde(default)]
+    pub last_id: NoteId,
+    /// Ids of the notes added, changed or removed since the feed was last
+    /// saved, so saving only has to append those to the journal.
+    #[serde(skip)]
+    pub changed: HashSet<NoteId>,
+    /// The dates of the notes removed since the feed was last saved, to tell
+    /// them from notes another process gave the same id meanwhile.
+    #[serde(skip)]
+    pub removed: HashMap<NoteId, DateTime<Local>>,
+    /// Set when the whole file has to be written on the next save.
+    #[serde(skip)]
+    pub rewrite: bool,
+    /// The notes file as it was when the feed was last loaded or saved, to
+    /// notice another process saving to it in between.
+    #[serde(skip)]
+    pub on_disk: Snapshot,
+    /// Set when a save brought in notes saved by another process, which
+    /// leaves indices into the feed taken before it stale.
+    #[serde(skip)]
+    pub merged: bool,
+    /// How many notes changed here were found changed by another process
+    /// too when merging, and were kept as copies.
+    #[serde(skip)]
+    pub conflicts: usize,
+    /// Goes up with each note added, changed or removed here, for what is
+    /// worked out from the notes to tell when it is out of date.
+    #[serde(skip)]
+    pub edits: u64,
+}
+
+impl Feed {
+    pub fn new() -> Feed {
+        Feed::default()
+    }
+
+    /// Marks the note at `i` as changed, to be written by the next save, and
+    /// as a new revision unless it already is one since the last save.
+    pub fn touch(&mut self, i: usize) {
+        self.edits += 1;
+        if self.changed.insert(self.notes[i].id) {
+            self.notes[i].written();
+        }
+    }
+
+    /// Puts `note`, a copy of the note at `i` from before it changed, back in
+    /// its place. Its revision goes on from the one it replaces, so the note
+    /// does not look older than what was saved of it.
+    pub fn restore(&mut self, i: usize, mut note: Note) {
+        note.revision = self.notes[i].revision;
+        note.last_writer = self.notes[i].last_writer.take();
+        self.notes[i] = note;
+        self.touch(i);
+    }
+
+    /// Takes the note at `i` out of the feed.
+    pub fn remove(&mut self, i: usize) -> Note {
+        let note = self.notes.remove(i).unwrap();
+        self.edits += 1;
+        self.changed.insert(note.id);
+        self.removed.insert(note.id, note.date);
+        note
+    }
+
+    /// Gives out an id no note of the feed ever had.
+    pub fn next_id(&mut self) -> NoteId {
+        let max = self.notes.iter().map(|n| n.id).max().unwrap_or(0);
+        self.last_id = self.last_id.max(max) + 1;
+        self.last_id
+    }
+
+    /// Adds a newly written note to the top of the feed, giving it an id.
+    pub fn push(&mut self, mut note: Note) -> NoteId {
+        note.id = self.next_id();
+        note.written();
+        self.changed.insert(note.id);
+        self.notes.push_front(note);
+        self.last_id
+    }
+
+    /// Splits the note at `i` before its line `line`: the lines above stay
+    /// in it, and the rest become a new note at the top of the feed, taking
+    /// along the attachments only they mention. Blank lines at the cut are
+    /// dropped. Returns false, changing nothing, when either part would be
+    /// blank.
+    pub fn split(&mut self, i: usize, line: usize) -> bool {
+        let lines: Vec<&str> = self.notes[i].text.lines().collect();
+        let first = lines[..line.min(lines.len())].join("\n");
+        let rest = lines[line.min(lines.len())..].join("\n");
+        let (first, rest) = (first.trim_end(), rest.trim_start_matches('\n'));
+        if first.trim().is_empty() || rest.trim().is_empty() {
+            return false;
+        }
+        let mut note = Note::new(rest.to_string());
+        let first = first.to_string();
+        let (moved, kept) =
+            self.notes[i].attachments.drain(..).partition(|a| {
+                let name = a.to_string_lossy();
+                rest.contains(&*name) && !first.contains(&*name)
+            });
+        note.attachments = moved;
+        self.notes[i].attachments = kept;
+        self.notes[i].set_text(first);
+        self.touch(i);
+        self.push(note);
+        true
+    }
+
+    /// Merges the notes at `indices` into the oldest of them, their texts
+    /// in date order with `---` lines between them, and moves the others to
+    /// the trash. The merged note keeps every attachment, and is starred if
+    /// any of them was.
+    pub fn merge(&mut self, indices: &[usize]) {
+        let mut indices = indices.to_vec();
+        indices.sort_by_key(|&i| self.notes[i].date);
+        let Some((&into, rest)) = indices.split_first() else {
+            return;
+        };
+        let mut text = self.notes[into].text.trim_end().to_string();
+        for &i in rest {
+            text.push_str("\n\n---\n\n");
+            text.push_str(self.notes[i].text.trim());
+            let attachments = std::mem::take(&mut self.notes[i].attachments);
+            self.notes[into].attachments.extend(attachments);
+            self.notes[into].starred |= self.notes[i].starred;
+            self.notes[i].deleted_at = Some(Local::now());
+            self.touch(i);
+        }
+        self.notes[into].set_text(text);
+        self.touch(into);
+    }
+
+    /// Every tag used in the feed with the number of notes carrying it,
+    /// sorted by name.
+    pub fn tags(&self) -> Vec<(String, usize)> {
+        let mut tags: Vec<(String, usize)> = Vec::new();
+        for tag in self
+            .notes
+            .iter()
+            .filter(|n| n.deleted_at.is_none())
+            .flat_map(|n| n.tags.iter())
+        {
+            match tags.iter_mut().find(|(t, _)| t == tag) {
+                Some((_, count)) => *count += 1,
+                None => tags.push((tag.clone(), 1)),
+            }
+        }
+        tags.sort();
+        tags
+    }
+
+    /// Inserts `note` before the first older note, keeping the feed sorted
+    /// newest first. The note comes from another feed, so it gets a new id
+    /// and is no longer a reply.
+    pub fn insert(&mut self, mut note: Note) {
+        note.id = self.next_id();
+        note.reply_to = None;
+        self.put(note);
+    }
+
+    /// Adds `note` in date order under the id it has, or replaces the note
+    /// that has that id.
+    pub fn put(&mut self, note: Note) {
+        self.changed.insert(note.id);
+        self.last_id = self.last_id.max(note.id);
+        if let Some(existing) = self.notes.iter_mut().find(|n| n.id == note.id)
+        {
+            *existing = note;
+            return;
+        }
+        let i = self
+            .notes
+            .iter()
+            .position(|n| n.date < note.date)
+            .unwrap_or(self.notes.len());
+        self.notes.insert(i, note);
+    }
+
+    /// The note outside the trash a `[[target]]` link points to: the one with
+    /// that id, or else the first one titled `target`, ignoring case.
+    pub fn resolve(&self, target: &str) -> Option<usize> {
+        let live = |i: &usize| self.notes[*i].deleted_at.is_none();
+        if let Ok(id) = target.parse::<NoteId>() {
+            if let Some(i) = (0..self.notes.len())
+                .filter(live)
+                .find(|&i| self.notes[i].id == id)
+            {
+                return Some(i);
+            }
+        }
+        (0..self.notes.len())
+            .filter(live)
+            .find(|&i| self.notes[i].title().eq_ignore_ascii_case(target))
+    }
+
+    /// The notes outside the trash linking to the note at `i`.
+    pub fn backlinks(&self, i: usize) -> Vec<usize> {
+        let note = &self.notes[i];
+        let id = note.id.to_string();
+        (0..self.notes.len())
+            .filter(|&j| j != i && self.notes[j].deleted_at.is_none())
+            .filter(|&j| {
+                links(&self.notes[j].text).iter().any(|(_, target)| {
+                    // checked cheaply before resolving, which goes through
+                    // the whole feed
+                    (*target == id || note.title().eq_ignore_ascii_case(target))
+                        && self.resolve(target) == Some(i)
+                })
+            })
+            .collect()
+    }
+
+    /// The newest note outside the trash from the last [`DUPLICATE_DAYS`]
+    /// days that `text` writes again: with the same words once case,
+    /// punctuation and spacing are left aside, or nearly all of them.
+    pub fn duplicate_of(&self, text: &str) -> Option<usize> {
+        let text = normalized(text);
+        let length = text.split(' ').count();
+        if text.is_empty() {
+            return None;
+        }
+        let since = Local::now() - chrono::Duration::days(DUPLICATE_DAYS);
+        (0..self.notes.len())
+            .filter(|&i| {
+                self.notes[i].deleted_at.is_none()
+                    && self.notes[i].date >= since
+            })
+            .find(|&i| {
+                let other = normalized(&self.notes[i].text);
+                let words = other.split(' ').count();
+                let (short, long) = (length.min(words), length.max(words));
+                // too different in length to be alike enough, which is
+                // cheaper to tell than comparing them
+                if (2 * short) as f64 / ((short + long) as f64)
+                    < DUPLICATE_SIMILARITY
+                {
+                    return false;
+                }
+                other == text
+                    || diff::similarity(&other, &text) >= DUPLICATE_SIMILARITY
+            })
+    }
+
+    /// Permanently removes notes that have been in the trash for more than
+    /// `days` days, returning them.
+    pub fn purge_trash(&mut self, days: i64) -> Vec<Note> {
+        let now = Local::now();
+        let (purged, kept): (VecDeque<Note>, VecDeque<Note>) =
+            self.notes.drain(..).partition(|n| {
+                n.deleted_at
+                    .is_some_and(|d| now - d >= chrono::Duration::days(days))
+            });
+        self.notes = kept;
+        self.changed.extend(purged.iter().map(|n| n.id));
+        self.removed.extend(purged.iter().map(|n| (n.id, n.date)));
+        purged.into()
+    }
+}
@@ -0,0 +1,48 @@
+//! The directories the config and the notes are kept in, the platform's own:
+//! `$XDG_CONFIG_HOME` and `$XDG_DATA_HOME` on Linux, `Library/Application
+//! Support` on macOS and `AppData\Roaming` on Windows. Directories left at
+//! `~/.config/feednotes` and `~/.local/share/feednotes` by older versions are
+//! moved there the first time they are looked for.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use directories::ProjectDirs;
+
+fn project() -> Result<ProjectDirs, Box<dyn std::error::Error>> {
+    Ok(ProjectDirs::from("", "", "feednotes")
+        .ok_or("cannot find the home directory")?)
+}
+
+/// Where the config file and the templates are kept.
+pub fn config_dir() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    Ok(migrate(".config/feednotes", project()?.config_dir()))
+}
+
+/// Where the notebooks are kept.
+pub fn data_dir() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    Ok(migrate(".local/share/feednotes", project()?.data_dir()))
+}
+
+/// `dir`, after moving the directory at `legacy` under the home directory
+/// to it if only that one exists. The legacy directory keeps being used if it
+/// cannot be moved.
+fn migrate(legacy: &str, dir: &Path) -> PathBuf {
+    let Ok(home) = std::env::var("HOME") else {
+        return dir.to_path_buf();
+    };
+    let legacy = Path::new(&home).join(legacy);
+    if legacy == dir || dir.exists() || !legacy.is_dir() {
+        return dir.to_path_buf();
+    }
+    let moved = dir
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| fs::rename(&legacy, dir));
+    match moved {
+        Ok(()) => dir.to_path_buf(),
+        Err(_) => legacy,
+    }
+}
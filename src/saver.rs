@@ -0,0 +1,255 @@
+//! Saving notes on a thread of their own, so the UI does not stall while a
+//! large notebook is written out. Changes are gathered for a while, by
+//! default [`DEBOUNCE`], and then saved together.
+
+use std::{
+    mem,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, Sender},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    crypt::Cipher,
+    model::Feed,
+    store::{self, Snapshot},
+};
+
+/// How long after the first change not yet saved the notes are saved,
+/// unless the config says otherwise.
+pub const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// How often changes due while a save is still running check whether it is
+/// done.
+const POLL: Duration = Duration::from_millis(50);
+
+/// A copy of a feed to save to `path`.
+struct Job {
+    path: PathBuf,
+    feed: Feed,
+}
+
+enum Done {
+    /// Saved, leaving the files as in the snapshot.
+    Saved(Snapshot),
+    /// Saved after merging in what another process had written, with how
+    /// many notes both changed.
+    Merged(usize),
+    Failed(String),
+}
+
+pub struct Saver {
+    jobs: Option<Sender<Job>>,
+    done: Receiver<Done>,
+    worker: Option<JoinHandle<()>>,
+    /// Jobs sent and not done yet.
+    pending: usize,
+    /// When the feed was first changed since it was last sent to be saved.
+    dirty: Option<Instant>,
+    /// How long changes wait to be saved, `None` leaving them to [`flush`].
+    ///
+    /// [`flush`]: Saver::flush
+    delay: Option<Duration>,
+}
+
+impl Saver {
+    /// Starts the thread saving notes, encrypted with `cipher` if there is
+    /// one, `delay` after they change or only when flushed if it is `None`.
+    pub fn start(cipher: Option<Cipher>, delay: Option<Duration>) -> Saver {
+        let (jobs, inbox) = mpsc::channel::<Job>();
+        let (outbox, done) = mpsc::channel();
+        let worker = thread::spawn(move || {
+            for mut job in inbox {
+                let result = match store::save(
+                    &job.path,
+                    &mut job.feed,
+                    cipher.as_ref(),
+                ) {
+                    Ok(()) if job.feed.merged => {
+                        Done::Merged(job.feed.conflicts)
+                    }
+                    Ok(()) => Done::Saved(job.feed.on_disk),
+                    Err(e) => Done::Failed(e.to_string()),
+                };
+                if outbox.send(result).is_err() {
+                    break;
+                }
+            }
+        });
+        Saver {
+            jobs: Some(jobs),
+            done,
+            worker: Some(worker),
+            pending: 0,
+            dirty: None,
+            delay,
+        }
+    }
+
+    /// Notes that the feed changed and is to be saved.
+    pub fn changed(&mut self) {
+        self.dirty.get_or_insert_with(Instant::now);
+    }
+
+    /// Whether changes are waiting to be saved or being saved.
+    pub fn busy(&self) -> bool {
+        self.dirty.is_some() || self.pending > 0
+    }
+
+    /// Whether there are changes not sent to be saved yet.
+    pub fn unsaved(&self) -> bool {
+        self.dirty.is_some()
+    }
+
+    /// How long until the changes waiting are due to be saved, or to check
+    /// again whether the save before them is done.
+    pub fn due_in(&self) -> Option<Duration> {
+        let delay = self.delay?;
+        let due = delay.saturating_sub(self.dirty?.elapsed());
+        match self.pending {
+            0 => Some(due),
+            _ => Some(due.max(POLL)),
+        }
+    }
+
+    /// Takes in the saves that are done and sends `feed` off to be saved to
+    /// `path` once its changes have settled and the last save is done.
+    /// Returns whether a save had to
+    /// merge in notes another process wrote, which `feed` is then missing
+    /// and should be reloaded for.
+    pub fn poll(
+        &mut self,
+        path: &Path,
+        feed: &mut Feed,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        let mut merged = false;
+        let mut error = None;
+        while let Ok(done) = self.done.try_recv() {
+            match self.finish(done, feed) {
+                Ok(m) => merged |= m,
+                Err(e) => error = Some(e),
+            }
+        }
+        // a save sent now would carry the files as they were before the
+        // one still running, and take its writes for another process's
+        if self.pending == 0 && self.due_in() == Some(Duration::ZERO) {
+            self.send(path, feed);
+        }
+        match error {
+            Some(e) => Err(e),
+            None => Ok(merged),
+        }
+    }
+
+    /// Saves `feed` to `path` now and waits for every save to be done, for
+    /// when nothing may be left unsaved, like before quitting or switching
+    /// notebooks.
+    pub fn flush(
+        &mut self,
+        path: &Path,
+        feed: &mut Feed,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        let mut merged = false;
+        let mut error = None;
+        let mut sent = false;
+        loop {
+            while self.pending > 0 {
+                let Ok(done) = self.done.recv() else {
+                    return Err("the thread saving notes stopped".into());
+                };
+                match self.finish(done, feed) {
+                    Ok(m) => merged |= m,
+                    Err(e) => error = Some(e),
+                }
+            }
+            let changed = self.dirty.is_some()
+                || feed.rewrite
+                || !feed.changed.is_empty();
+            if sent || !changed {
+                break;
+            }
+            self.send(path, feed);
+            sent = true;
+        }
+        match error {
+            Some(e) => Err(e),
+            None => Ok(merged),
+        }
+    }
+
+    /// Hands a copy of `feed` to the worker. The changes it carries are no
+    /// longer `feed`'s to save.
+    fn send(&mut self, path: &Path, feed: &mut Feed) {
+        self.dirty = None;
+        let job = Job { path: path.to_path_buf(), feed: feed.clone() };
+        if let Some(jobs) = &self.jobs {
+            if jobs.send(job).is_ok() {
+                self.pending += 1;
+                feed.changed.clear();
+                feed.removed.clear();
+                feed.rewrite = false;
+            }
+        }
+    }
+
+    fn finish(
+        &mut self,
+        done: Done,
+        feed: &mut Feed,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        self.pending -= 1;
+        match done {
+            Done::Saved(on_disk) => {
+                feed.on_disk = on_disk;
+                Ok(false)
+            }
+            Done::Merged(conflicts) => {
+                feed.conflicts += conflicts;
+                Ok(true)
+            }
+            Done::Failed(e) => {
+                // which notes changed went with the job, so the next save
+                // writes them all
+                feed.rewrite = true;
+                Err(e.into())
+            }
+        }
+    }
+}
+
+impl Drop for Saver {
+    /// Lets the worker finish the saves sent to it.
+    fn drop(&mut self) {
+        drop(mem::take(&mut self.jobs));
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::model::Note;
+
+    #[test]
+    fn saves_in_a_row_are_not_taken_for_another_process() {
+        let dir = std::env::temp_dir()
+            .join(format!("feednotes-saver-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("notes.json");
+        let mut feed = store::load(&path, None).unwrap();
+        let mut saver = Saver::start(None, Some(Duration::ZERO));
+        for text in ["first", "second"] {
+            feed.push(Note::new(text.into()));
+            saver.changed();
+            assert!(!saver.poll(&path, &mut feed).unwrap());
+        }
+        assert!(!saver.flush(&path, &mut feed).unwrap());
+        assert_eq!(store::read(&path, None).unwrap().notes.len(), 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
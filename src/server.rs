@@ -0,0 +1,245 @@
+//! `feednotes serve`: a small JSON API over HTTP, for adding notes from a
+//! phone or from other programs. Every request needs the token of the
+//! `[server]` config section as `Authorization: Bearer <token>`.
+//!
+//! - `GET /notes?q=<query>`: the notes matching a filter query, newest
+//!   first, or all of them without `q`
+//! - `GET /notes/<id>`: a note
+//! - `POST /notes`: adds a note
+//! - `PUT /notes/<id>`: replaces the text of a note
+//! - `DELETE /notes/<id>`: moves a note to the trash
+//!
+//! Notes come back in the form `feednotes dump` prints them. A note sent is
+//! `{"text": "..."}`, or just the text when it is not sent as JSON, of at
+//! most a megabyte. Errors come back as `{"error": "..."}`.
+//!
+//! Requests are answered one at a time. Each first merges in what another
+//! feednotes saved since the last one, and a change is saved right away
+//! through the lock of the notes file, the way the UI saves: a UI open on
+//! the same notes picks it up, and a note changed in both is kept twice
+//! rather than overwritten.
+
+use std::{io::Read, path::Path};
+
+use chrono::Local;
+use serde::Deserialize;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::{
+    cli,
+    config::Config,
+    crypt::Cipher,
+    filter::{FeedView, Query, Sort},
+    model::{Feed, Note, NoteId},
+    store, webhooks,
+};
+
+/// A note as sent in a request.
+#[derive(Deserialize)]
+struct Sent {
+    text: String,
+}
+
+/// The status code and the JSON body of a response.
+type Reply = (u16, String);
+
+const NO_TEXT: &str =
+    "send the text of the note, as plain text or as {\"text\": \"...\"}";
+
+/// The most a request sends, in bytes.
+const LIMIT: u64 = 1 << 20;
+
+/// Answers requests on the address of the `[server]` config section until
+/// killed.
+pub fn serve(
+    data_file: &Path,
+    config: &Config,
+    cipher: Option<&Cipher>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(token) = config.server.token.as_deref().filter(|t| !t.is_empty())
+    else {
+        return Err("set token under [server] in the config".into());
+    };
+    let listen = &config.server.listen;
+    let server = Server::http(listen)
+        .map_err(|e| format!("cannot listen on {}: {}", listen, e))?;
+    eprintln!("serving {} on http://{}", data_file.display(), listen);
+
+    let mut feed = store::load(data_file, cipher)?;
+    for mut request in server.incoming_requests() {
+        let (status, body) = if !authorized(&request, token) {
+            error(401, "missing or wrong token")
+        } else {
+            handle(&mut request, data_file, &mut feed, config, cipher)
+                .unwrap_or_else(|e| error(500, &e.to_string()))
+        };
+        let json = Header::from_bytes("Content-Type", "application/json");
+        let response = Response::from_string(body)
+            .with_status_code(status)
+            .with_header(json.unwrap());
+        // a client that hung up is not worth stopping for
+        let _ = request.respond(response);
+    }
+    Ok(())
+}
+
+/// Whether `request` carries `token`, compared in constant time.
+fn authorized(request: &Request, token: &str) -> bool {
+    let Some(sent) = request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv("Authorization"))
+        .and_then(|h| h.value.as_str().strip_prefix("Bearer "))
+    else {
+        return false;
+    };
+    sent.len() == token.len()
+        && sent.bytes().zip(token.bytes()).fold(0, |d, (a, b)| d | (a ^ b)) == 0
+}
+
+fn error(status: u16, message: &str) -> Reply {
+    (status, serde_json::json!({ "error": message }).to_string())
+}
+
+fn handle(
+    request: &mut Request,
+    data_file: &Path,
+    feed: &mut Feed,
+    config: &Config,
+    cipher: Option<&Cipher>,
+) -> Result<Reply, Box<dyn std::error::Error>> {
+    store::reload(data_file, feed, cipher)?;
+    let url = request.url().to_string();
+    let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+    let id = match path.strip_prefix("/notes") {
+        Some("" | "/") => None,
+        Some(rest) => match rest.strip_prefix('/').map(str::parse::<NoteId>) {
+            Some(Ok(id)) => Some(id),
+            _ => return Ok(error(404, "not found")),
+        },
+        None => return Ok(error(404, "not found")),
+    };
+    let Some(id) = id else {
+        return match request.method() {
+            Method::Get => list(feed, query),
+            Method::Post => {
+                let text = match read_text(request) {
+                    Ok(text) => text,
+                    Err(reply) => return Ok(reply),
+                };
+                feed.push(Note::new(text));
+                let note = feed.notes[0].clone();
+                save(data_file, feed, config, cipher)?;
+                // the note is added all the same
+                if let Err(e) = webhooks::send(&config.webhooks, &note) {
+                    eprintln!("cannot send note {} on: {}", note.id, e);
+                }
+                Ok((201, serde_json::to_string(&note)?))
+            }
+            _ => Ok(error(405, "use GET or POST on /notes")),
+        };
+    };
+    let Some(i) =
+        feed.notes.iter().position(|n| n.id == id && n.deleted_at.is_none())
+    else {
+        return Ok(error(404, "no such note"));
+    };
+    match request.method() {
+        Method::Get => Ok((200, serde_json::to_string(&feed.notes[i])?)),
+        Method::Put => {
+            let text = match read_text(request) {
+                Ok(text) => text,
+                Err(reply) => return Ok(reply),
+            };
+            feed.notes[i].set_text(text);
+            feed.touch(i);
+            let note = feed.notes[i].clone();
+            save(data_file, feed, config, cipher)?;
+            Ok((200, serde_json::to_string(&note)?))
+        }
+        Method::Delete => {
+            feed.notes[i].deleted_at = Some(Local::now());
+            feed.touch(i);
+            save(data_file, feed, config, cipher)?;
+            Ok((200, "{}".into()))
+        }
+        _ => Ok(error(405, "use GET, PUT or DELETE on /notes/<id>")),
+    }
+}
+
+/// The notes matching the `q` parameter of `query`, as for
+/// `feednotes search --json`.
+fn list(feed: &Feed, query: &str) -> Result<Reply, Box<dyn std::error::Error>> {
+    let q = query
+        .split('&')
+        .filter_map(|param| param.split_once('='))
+        .find(|(name, _)| *name == "q")
+        .map(|(_, value)| decode(value))
+        .unwrap_or_default();
+    if let Err(e) = Query::parse(&q) {
+        return Ok(error(400, &e.to_string()));
+    }
+    let view = FeedView::filter(feed, &q, Sort::Newest);
+    let notes: Vec<&Note> = view.refs.iter().map(|&i| &feed.notes[i]).collect();
+    Ok((200, serde_json::to_string(&notes)?))
+}
+
+/// The text of the note in the body of `request`, or the reply to send when
+/// there is none or it is too long.
+fn read_text(request: &mut Request) -> Result<String, Reply> {
+    let json = request.headers().iter().any(|h| {
+        h.field.equiv("Content-Type")
+            && h.value.as_str().starts_with("application/json")
+    });
+    let mut body = Vec::new();
+    // a byte more than allowed, to tell a body of the limit from a longer one
+    let read = request.as_reader().take(LIMIT + 1).read_to_end(&mut body);
+    if body.len() as u64 > LIMIT {
+        return Err(error(413, "a note is at most a megabyte"));
+    }
+    read.map_err(|_| error(400, NO_TEXT))?;
+    let body = String::from_utf8(body).map_err(|_| error(400, NO_TEXT))?;
+    let text = match json {
+        true => {
+            serde_json::from_str::<Sent>(&body)
+                .map_err(|_| error(400, NO_TEXT))?
+                .text
+        }
+        false => body,
+    };
+    let text = text.trim_end().to_string();
+    Some(text)
+        .filter(|t| !t.trim().is_empty())
+        .ok_or_else(|| error(400, NO_TEXT))
+}
+
+/// Saves `feed` and rewrites the export file, if there is one.
+fn save(
+    data_file: &Path,
+    feed: &mut Feed,
+    config: &Config,
+    cipher: Option<&Cipher>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    store::save(data_file, feed, cipher)?;
+    cli::write_export(data_file, &config.export, cipher)
+}
+
+/// Decodes a parameter of a query string: `+` for a space and `%XX` escapes.
+fn decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = value.get(i + 1..i + 3);
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' if hex.is_some_and(|h| u8::from_str_radix(h, 16).is_ok()) => {
+                decoded.push(u8::from_str_radix(hex.unwrap(), 16).unwrap());
+                i += 2;
+            }
+            b => decoded.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
@@ -0,0 +1,157 @@
+//! The setup run the first time feednotes is opened: a few questions on the
+//! terminal, answered before the UI starts, whose answers are written to a
+//! new config file, and a first note on how to get around.
+
+use std::{
+    fs,
+    io::{self, IsTerminal, Write},
+    path::{Path, PathBuf},
+};
+
+use chrono::Local;
+
+use crate::{config::Config, model::Note, store, timefmt::Clock};
+
+/// The first note, left in the feed once the setup is done.
+const TUTORIAL: &str = "\
+Welcome to feednotes
+
+Notes are listed newest first, and written in a composer with vim keys.
+
+- `n` writes a new note, `W` saves it, `backspace` leaves the composer
+- `i` edits the selected note, `j` and `k` move between notes
+- `/` shows only the notes matching a filter, like #feednotes
+- `dd` moves a note to the trash, `u` brings it back
+- `q` quits, and everything is saved as it is written
+
+- [ ] tick this box with `Space`
+- [ ] delete this note with `dd`
+
+#feednotes";
+
+/// Runs the setup if feednotes is opened for the first time: there is no
+/// config file and no notebook at `data_file`, or at the default one when it
+/// is `None`, and someone at the terminal to answer.
+pub fn run(data_file: Option<&Path>) -> Result<(), Box<dyn std::error::Error>> {
+    let config_path = PathBuf::from(Config::path()?);
+    let given = data_file.map(Path::to_path_buf).or_else(store::env_data_file);
+    let defaults = Config::default();
+    let first = match &given {
+        Some(path) => path.clone(),
+        None => store::default_data_file(defaults.storage, &defaults.notebook)?,
+    };
+    if config_path.exists() || first.exists() || !io::stdin().is_terminal() {
+        return Ok(());
+    }
+
+    eprintln!(
+        "Welcome to feednotes! A few questions to set it up, each can be \
+         changed later in\n{}. Enter keeps the answer in brackets.",
+        config_path.display()
+    );
+    let light = choose(
+        "The terminal's background is",
+        &["dark".into(), "light".into()],
+    )? == 1;
+    let border = ["rounded", "plain", "double", "thick"];
+    let border = border[choose(
+        "Borders around the notes",
+        &[
+            "rounded ╭─╮".into(),
+            "plain   ┌─┐".into(),
+            "double  ╔═╗".into(),
+            "thick   ┏━┓".into(),
+        ],
+    )?];
+    let now = Local::now();
+    let dates = choose(
+        "Dates of the notes",
+        &[
+            now.format(Clock::H24.date_format()).to_string(),
+            now.format(Clock::H12.date_format()).to_string(),
+            "5 minutes ago".into(),
+        ],
+    )?;
+    let notebook = match given {
+        Some(_) => None,
+        None => Some(ask_notebook(&defaults.notebook)?),
+    };
+
+    let mut config = String::from(
+        "# Written by the setup on the first start. Every setting is \
+         described\n# under Configuration in the README.\n",
+    );
+    if let Some(name) = &notebook {
+        config += &format!("notebook = {}\n", toml_edit::Value::from(name));
+    }
+    config += &format!(
+        "\n[theme]\nselection_bg = \"{}\"\nborder_type = \"{}\"\n\
+         clock = \"{}\"\nrelative_dates = {}\n",
+        if light { "#dde2e7" } else { "#2d3237" },
+        border,
+        if dates == 1 { "12h" } else { "24h" },
+        dates == 2,
+    );
+    if let Some(dir) = config_path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&config_path, config)?;
+
+    let data_file = match notebook {
+        Some(name) => store::default_data_file(defaults.storage, &name)?,
+        None => first,
+    };
+    fs::create_dir_all(store::notebook_dir(&data_file))?;
+    let mut feed = store::load(&data_file, None)?;
+    if feed.notes.is_empty() {
+        feed.push(Note::new(TUTORIAL.into()));
+        store::save(&data_file, &mut feed, None)?;
+    }
+    eprintln!("\nWrote {}.", config_path.display());
+    Ok(())
+}
+
+/// Asks `question` on the terminal until one of `options` is picked by its
+/// number, and returns its index. Enter, or the end of input, picks the
+/// first.
+fn choose(question: &str, options: &[String]) -> io::Result<usize> {
+    eprintln!("\n{}:", question);
+    for (i, option) in options.iter().enumerate() {
+        eprintln!("  {}) {}", i + 1, option);
+    }
+    loop {
+        let answer = ask("[1]")?;
+        if answer.is_empty() {
+            return Ok(0);
+        }
+        match answer.parse::<usize>() {
+            Ok(n) if (1..=options.len()).contains(&n) => return Ok(n - 1),
+            _ => eprintln!("a number from 1 to {}", options.len()),
+        }
+    }
+}
+
+/// Asks for the name of the notebook to open, `default` if none is typed.
+fn ask_notebook(default: &str) -> io::Result<String> {
+    eprintln!("\nName of the notebook, more can be made later with `b`:");
+    loop {
+        let name = ask(&format!("[{}]", default))?;
+        if name.is_empty() {
+            return Ok(default.into());
+        }
+        if store::is_notebook_name(&name) {
+            return Ok(name);
+        }
+        eprintln!("a name without slashes that does not start with a dot");
+    }
+}
+
+/// Prompts with `prompt` and reads a line, trimmed, empty at the end of
+/// input.
+fn ask(prompt: &str) -> io::Result<String> {
+    eprint!("{} ", prompt);
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(answer.trim().to_string())
+}
@@ -0,0 +1,83 @@
+//! Quitting the UI as if `q` was pressed when the terminal it runs in is
+//! closed (SIGHUP) or a service manager stops it (SIGTERM), so changes not
+//! saved yet are saved and the terminal is restored instead of the process
+//! just ending.
+
+use std::{
+    io,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+pub struct Signals {
+    caught: Arc<AtomicBool>,
+    #[cfg(unix)]
+    handlers: Vec<signal_hook::SigId>,
+}
+
+impl Signals {
+    /// Catches the signals from now until dropped, instead of letting them
+    /// end the process.
+    pub fn catch() -> io::Result<Signals> {
+        let caught = Arc::new(AtomicBool::new(false));
+        #[cfg(unix)]
+        {
+            use signal_hook::consts::{SIGHUP, SIGTERM};
+            let handlers = vec![
+                signal_hook::flag::register(SIGTERM, Arc::clone(&caught))?,
+                signal_hook::flag::register(SIGHUP, Arc::clone(&caught))?,
+                detach_on_hangup()?,
+            ];
+            Ok(Signals { caught, handlers })
+        }
+        #[cfg(not(unix))]
+        Ok(Signals { caught })
+    }
+
+    /// Whether one of the signals came.
+    pub fn caught(&self) -> bool {
+        self.caught.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for Signals {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        for &handler in &self.handlers {
+            signal_hook::low_level::unregister(handler);
+        }
+    }
+}
+
+/// Swaps the closed terminal for an empty pipe as stdin and `/dev/null` as
+/// stdout and stderr on SIGHUP. Reading a terminal that hung up keeps giving
+/// nothing, and crossterm would keep reading it forever instead of coming
+/// back to the UI to quit; an empty pipe tells it there is nothing yet.
+#[cfg(unix)]
+fn detach_on_hangup() -> io::Result<signal_hook::SigId> {
+    use std::{fs::File, os::fd::IntoRawFd};
+
+    let mut pipe = [0; 2];
+    // the end written to stays open, or reading would give nothing again
+    if unsafe { libc::pipe(pipe.as_mut_ptr()) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    if unsafe { libc::fcntl(pipe[0], libc::F_SETFL, libc::O_NONBLOCK) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let null = File::options().write(true).open("/dev/null")?.into_raw_fd();
+    let stdin = pipe[0];
+    // only dup2, which is safe to call in a signal handler
+    unsafe {
+        signal_hook::low_level::register(
+            signal_hook::consts::SIGHUP,
+            move || {
+                libc::dup2(stdin, libc::STDIN_FILENO);
+                libc::dup2(null, libc::STDOUT_FILENO);
+                libc::dup2(null, libc::STDERR_FILENO);
+            },
+        )
+    }
+}
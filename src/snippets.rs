@@ -0,0 +1,27 @@
+//! Abbreviations from `[snippets]` in the config, like `;date`, replaced with
+//! their text when typed followed by a space. The text can hold the
+//! placeholders of [`templates`], filled in when it is typed.
+
+use std::collections::BTreeMap;
+
+use chrono::Local;
+use tui_textarea::{CursorMove, TextArea};
+
+use crate::templates;
+
+/// Replaces the word just before the cursor of `textarea` with the text of
+/// the snippet it names, if it names one, and returns the text put in.
+pub fn expand(
+    textarea: &mut TextArea,
+    snippets: &BTreeMap<String, String>,
+) -> Option<String> {
+    let (row, col) = textarea.cursor();
+    let before: String = textarea.lines()[row].chars().take(col).collect();
+    let name = before.rsplit(char::is_whitespace).next()?;
+    let text = templates::expand(snippets.get(name)?, Local::now());
+    let start = col - name.chars().count();
+    textarea.move_cursor(CursorMove::Jump(row as u16, start as u16));
+    textarea.delete_str(col - start);
+    textarea.insert_str(&text);
+    Some(text)
+}
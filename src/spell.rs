@@ -0,0 +1,144 @@
+//! Spell checking with hunspell, kept running in its pipe mode while the UI
+//! is open. Words are sent to it once and the answers remembered.
+
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, BufReader, Write},
+    ops::Range,
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+};
+
+pub struct Speller {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    /// The suggestions for each word checked, `None` for words spelled
+    /// right.
+    checked: HashMap<String, Option<Vec<String>>>,
+}
+
+impl Speller {
+    /// Starts hunspell with the dictionary for `language`, e.g. `en_US`.
+    pub fn start(
+        language: &str,
+    ) -> Result<Speller, Box<dyn std::error::Error>> {
+        let mut child = Command::new("hunspell")
+            .args(["-a", "-d", language])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| format!("cannot run hunspell: {}", e))?;
+        let stdin = child.stdin.take().unwrap();
+        let mut stdout = BufReader::new(child.stdout.take().unwrap());
+        // the version banner, or nothing when the dictionary is missing
+        let mut banner = String::new();
+        if stdout.read_line(&mut banner)? == 0 {
+            let _ = child.wait();
+            return Err(
+                format!("no {} dictionary for hunspell", language).into()
+            );
+        }
+        Ok(Speller { child, stdin, stdout, checked: HashMap::new() })
+    }
+
+    /// Checks the words of `lines` not checked before.
+    pub fn check(&mut self, lines: &[String]) -> io::Result<()> {
+        let mut new: Vec<&str> = Vec::new();
+        for line in lines {
+            for (_, word) in words(line) {
+                if !self.checked.contains_key(word) && !new.contains(&word) {
+                    new.push(word);
+                }
+            }
+        }
+        if new.is_empty() {
+            return Ok(());
+        }
+
+        // `^` keeps the line from being taken as a command
+        writeln!(self.stdin, "^{}", new.join(" "))?;
+        self.stdin.flush()?;
+        let mut misspelled = HashMap::new();
+        loop {
+            let mut line = String::new();
+            if self.stdout.read_line(&mut line)? == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "hunspell quit",
+                ));
+            }
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            // `& word count offset: one, two` or `# word offset`
+            let mut fields = line.splitn(3, ' ');
+            match (fields.next(), fields.next()) {
+                (Some("&"), Some(word)) => {
+                    let suggestions = line
+                        .split_once(": ")
+                        .map(|(_, s)| s.split(", ").map(String::from).collect())
+                        .unwrap_or_default();
+                    misspelled.insert(word.to_string(), suggestions);
+                }
+                (Some("#"), Some(word)) => {
+                    misspelled.insert(word.to_string(), Vec::new());
+                }
+                _ => {}
+            }
+        }
+        for word in new {
+            let suggestions = misspelled.remove(word);
+            self.checked.insert(word.to_string(), suggestions);
+        }
+        Ok(())
+    }
+
+    /// Whether `word` was checked and found misspelled.
+    pub fn is_misspelled(&self, word: &str) -> bool {
+        matches!(self.checked.get(word), Some(Some(_)))
+    }
+
+    /// What `word` may have been meant to be, if it is misspelled.
+    pub fn suggestions(&self, word: &str) -> Option<&[String]> {
+        self.checked.get(word)?.as_deref()
+    }
+}
+
+impl Drop for Speller {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// The words of `line` to check, with their byte ranges: runs of letters,
+/// with apostrophes inside them. Tags and addresses are left out.
+pub fn words(line: &str) -> Vec<(Range<usize>, &str)> {
+    let mut words = Vec::new();
+    let mut from = 0;
+    for chunk in line.split_whitespace() {
+        let start = from + line[from..].find(chunk).unwrap();
+        from = start + chunk.len();
+        if chunk.starts_with('#') || chunk.contains("://") {
+            continue;
+        }
+        let mut word: Option<usize> = None;
+        for (i, c) in chunk.char_indices().chain([(chunk.len(), ' ')]) {
+            let letter =
+                c.is_alphabetic() || (c == '\'' || c == '’') && word.is_some();
+            match word {
+                None if letter => word = Some(i),
+                Some(w) if !letter => {
+                    let text = chunk[w..i].trim_end_matches(['\'', '’']);
+                    let range = start + w..start + w + text.len();
+                    words.push((range, text));
+                    word = None;
+                }
+                _ => {}
+            }
+        }
+    }
+    words
+}
@@ -0,0 +1,147 @@
+//! How much and how regularly notes are written.
+
+use std::collections::BTreeMap;
+
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+
+use crate::model::{Feed, Note};
+
+pub struct Stats {
+    pub total: usize,
+    /// Notes written on each day that has any.
+    pub per_day: BTreeMap<NaiveDate, usize>,
+    /// Days in a row with notes up to today, or up to yesterday while nothing
+    /// has been written today yet.
+    pub current_streak: usize,
+    pub longest_streak: usize,
+    /// In characters.
+    pub average_length: usize,
+}
+
+impl Stats {
+    /// Counts the notes of `feed` outside the trash.
+    pub fn new(feed: &Feed, today: NaiveDate) -> Stats {
+        let notes: Vec<&Note> =
+            feed.notes.iter().filter(|n| n.deleted_at.is_none()).collect();
+        let per_day = per_day(feed);
+
+        let mut longest_streak = 0;
+        let mut run = 0;
+        let mut previous: Option<NaiveDate> = None;
+        for &day in per_day.keys() {
+            run = match previous {
+                Some(p) if day - p == Duration::days(1) => run + 1,
+                _ => 1,
+            };
+            longest_streak = longest_streak.max(run);
+            previous = Some(day);
+        }
+
+        let mut current_streak = 0;
+        let mut day = today;
+        if !per_day.contains_key(&day) {
+            day -= Duration::days(1);
+        }
+        while per_day.contains_key(&day) {
+            current_streak += 1;
+            day -= Duration::days(1);
+        }
+
+        let chars: usize = notes.iter().map(|n| n.text.chars().count()).sum();
+        Stats {
+            total: notes.len(),
+            per_day,
+            current_streak,
+            longest_streak,
+            average_length: chars.checked_div(notes.len()).unwrap_or(0),
+        }
+    }
+
+    /// The totals, one per line.
+    pub fn summary(&self) -> Vec<String> {
+        vec![
+            format!(
+                "{} notes, {} characters long on average",
+                self.total, self.average_length
+            ),
+            format!(
+                "current streak {}, longest {}",
+                days(self.current_streak),
+                days(self.longest_streak)
+            ),
+        ]
+    }
+
+    /// The notes written per day over the last `weeks` weeks up to `today`,
+    /// one row per weekday starting on `week_start` and one column per week,
+    /// the days shaded by how many notes were written on them.
+    pub fn heatmap(
+        &self,
+        today: NaiveDate,
+        weeks: usize,
+        week_start: Weekday,
+    ) -> Vec<String> {
+        let max = self.per_day.values().copied().max().unwrap_or(0);
+        let first_day = today
+            - Duration::days(
+                today.weekday().days_since(week_start) as i64
+                    + 7 * (weeks as i64 - 1),
+            );
+        let mut rows: Vec<String> = (0..7)
+            .map(|weekday| {
+                // every other row is named
+                let name = if weekday % 2 == 0 {
+                    (first_day + Duration::days(weekday as i64))
+                        .weekday()
+                        .to_string()
+                } else {
+                    String::new()
+                };
+                let mut row = format!("{:<4}", name);
+                for week in 0..weeks {
+                    let day =
+                        first_day + Duration::days((week * 7 + weekday) as i64);
+                    if day > today {
+                        break;
+                    }
+                    let count = self.per_day.get(&day).copied().unwrap_or(0);
+                    row.push(shade(count, max));
+                }
+                row
+            })
+            .collect();
+        rows.push(format!(
+            "{:<4}less {} more",
+            "",
+            SHADES.iter().collect::<String>()
+        ));
+        rows
+    }
+}
+
+/// How many notes outside the trash were written on each day that has any.
+pub fn per_day(feed: &Feed) -> BTreeMap<NaiveDate, usize> {
+    let mut per_day = BTreeMap::new();
+    for note in feed.notes.iter().filter(|n| n.deleted_at.is_none()) {
+        *per_day.entry(note.date.date_naive()).or_insert(0) += 1;
+    }
+    per_day
+}
+
+const SHADES: [char; 5] = ['·', '░', '▒', '▓', '█'];
+
+/// The shade for a day with `count` notes, by quarters of the busiest day's
+/// `max`.
+fn shade(count: usize, max: usize) -> char {
+    if count == 0 {
+        return SHADES[0];
+    }
+    SHADES[(count * 4).div_ceil(max).min(4)]
+}
+
+fn days(n: usize) -> String {
+    match n {
+        1 => "1 day".into(),
+        n => format!("{} days", n),
+    }
+}
@@ -0,0 +1,685 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use chrono::{Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    backend::{self, Backend},
+    crypt::{self, Cipher},
+    model::{extract_tags, Feed, NoteId},
+    paths, sync,
+};
+
+/// The sizes and modification times of the files a notebook is saved to,
+/// which change whenever it is saved.
+#[derive(Clone, Default, PartialEq)]
+pub struct Snapshot {
+    files: Vec<Option<(u64, SystemTime)>>,
+}
+
+impl Snapshot {
+    fn of(path: &Path) -> Snapshot {
+        let stat = |p: &Path| {
+            let meta = fs::metadata(p).ok()?;
+            Some((meta.len(), meta.modified().ok()?))
+        };
+        let files = backend::open(path).watch();
+        Snapshot { files: files.iter().map(|f| stat(f)).collect() }
+    }
+}
+
+/// Reads the feed from `path` with the [`backend`] its extension picks. A
+/// missing file gives an empty feed, and the parent directory is created so
+/// the first save succeeds. An encrypted file needs `cipher`, while a plaintext
+/// one is read as is either way. The notes of a recovery file left behind by a
+/// crash that differ from the ones saved are merged in first, as if it had
+/// saved them, see [`merge_from_disk`].
+pub fn load(
+    path: &Path,
+    cipher: Option<&Cipher>,
+) -> Result<Feed, Box<dyn std::error::Error>> {
+    let _lock = lock(path)?;
+    let recovery = recovery_path(path);
+    if recovery.exists() {
+        let mut feed = read_feed(&recovery, cipher)?;
+        let disk = read_feed(path, cipher)?;
+        feed.changed = feed
+            .notes
+            .iter()
+            .filter(|n| match disk.notes.iter().find(|d| d.id == n.id) {
+                Some(d) => {
+                    n.revision > d.revision
+                        || n.revision == d.revision && n.text != d.text
+                }
+                None => true,
+            })
+            .map(|n| n.id)
+            .collect();
+        merge_from_disk(path, &mut feed, cipher)?;
+        save_locked(path, &mut feed, cipher)?;
+        fs::remove_file(&recovery)?;
+    }
+    read_feed(path, cipher)
+}
+
+/// Reads the feed like [`load`], but without writing anything: no lock is
+/// taken and a recovery file is read instead of the data file, for browsing
+/// notes where they must not or cannot be changed.
+pub fn read(
+    path: &Path,
+    cipher: Option<&Cipher>,
+) -> Result<Feed, Box<dyn std::error::Error>> {
+    let recovery = recovery_path(path);
+    if recovery.exists() {
+        return read_feed(&recovery, cipher);
+    }
+    read_feed(path, cipher)
+}
+
+fn read_feed(
+    path: &Path,
+    cipher: Option<&Cipher>,
+) -> Result<Feed, Box<dyn std::error::Error>> {
+    let mut feed = backend::open(path).load(cipher)?;
+
+    // files written before tags and ids existed
+    for i in (0..feed.notes.len()).rev() {
+        if feed.notes[i].tags.is_empty() {
+            feed.notes[i].tags = extract_tags(&feed.notes[i].text);
+        }
+        if feed.notes[i].id == 0 {
+            feed.notes[i].id = feed.next_id();
+            feed.rewrite = true;
+        }
+    }
+
+    feed.changed.clear();
+    feed.on_disk = Snapshot::of(path);
+    Ok(feed)
+}
+
+/// The file locked while a notes file is read or written, so another
+/// feednotes never sees it half written.
+fn lock_path(path: &Path) -> PathBuf {
+    let mut lock = path.as_os_str().to_owned();
+    lock.push(".lock");
+    PathBuf::from(lock)
+}
+
+/// Waits for the lock on `path`, which is held until the returned file is
+/// dropped.
+fn lock(path: &Path) -> io::Result<File> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(lock_path(path))?;
+    file.lock()?;
+    Ok(file)
+}
+
+/// Whether another process saved to `path` since `feed` was loaded from it
+/// or saved.
+pub fn changed_on_disk(path: &Path, feed: &Feed) -> bool {
+    Snapshot::of(path) != feed.on_disk
+}
+
+/// Merges what another process saved to `path` since `feed` was loaded or
+/// last saved into `feed`, see [`merge_from_disk`].
+pub fn reload(
+    path: &Path,
+    feed: &mut Feed,
+    cipher: Option<&Cipher>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let _lock = lock(path)?;
+    if changed_on_disk(path, feed) {
+        merge_from_disk(path, feed, cipher)?;
+    }
+    Ok(())
+}
+
+/// Saves the notes that changed since the last save to `path`, see
+/// [`NoteStore::append`], or the whole feed when [`Feed::rewrite`] is set.
+///
+/// If another process saved to `path` since `feed` was loaded or last saved,
+/// its notes are merged into `feed` first, see [`merge_from_disk`].
+///
+/// Everything is encrypted with `cipher` if there is one, and the new
+/// version is committed when the notebooks are kept in git.
+pub fn save(
+    path: &Path,
+    feed: &mut Feed,
+    cipher: Option<&Cipher>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let _lock = lock(path)?;
+    if changed_on_disk(path, feed) {
+        merge_from_disk(path, feed, cipher)?;
+    }
+    save_locked(path, feed, cipher)
+}
+
+fn save_locked(
+    path: &Path,
+    feed: &mut Feed,
+    cipher: Option<&Cipher>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let store = backend::open(path);
+    if feed.rewrite {
+        store.save(feed, cipher)?;
+        feed.rewrite = false;
+    } else if !feed.changed.is_empty() {
+        store.append(feed, cipher)?;
+    }
+    feed.changed.clear();
+    feed.removed.clear();
+    feed.on_disk = Snapshot::of(path);
+
+    let dir = notebook_dir(path);
+    if sync::is_repo(&dir) {
+        let files = store.watch();
+        let files: Vec<&Path> = files.iter().map(PathBuf::as_path).collect();
+        sync::commit(&dir, &files)?;
+    }
+    Ok(())
+}
+
+/// Tag of the copies kept of notes changed both here and by another process,
+/// see [`merge_from_disk`].
+pub const CONFLICT_TAG: &str = "conflict";
+
+/// Replaces the notes of `feed` with the ones on disk at `path`, which
+/// another process has saved to, keeping the notes changed in `feed` since
+/// its last save. Ones that got an id the other process gave out too get a
+/// new id.
+///
+/// A note changed in `feed` wins over the version on disk unless that one is
+/// of its revision or a later one, so was changed by the other process too
+/// since `feed` loaded it. Then, unless both have the same text, the version
+/// on disk stays and the one in `feed` is added next to it as a copy tagged
+/// `#conflict`, counted in [`Feed::conflicts`]. Sets [`Feed::merged`].
+fn merge_from_disk(
+    path: &Path,
+    feed: &mut Feed,
+    cipher: Option<&Cipher>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut disk = read_feed(path, cipher)?;
+    disk.last_id = disk.last_id.max(feed.last_id);
+    let mut renamed = HashMap::new();
+    let mut changed = HashSet::new();
+    for &id in &feed.changed {
+        match feed.notes.iter().find(|n| n.id == id) {
+            Some(note) => {
+                let mut note = note.clone();
+                let theirs = disk
+                    .notes
+                    .iter()
+                    .find(|n| n.id == id)
+                    .map(|n| (n.date, n.revision, n.text == note.text));
+                match theirs {
+                    // a different note, written at the same time as this one
+                    Some((date, ..)) if date != note.date => {
+                        note.id = disk.next_id();
+                        renamed.insert(id, note.id);
+                    }
+                    Some((_, revision, same)) if revision >= note.revision => {
+                        note.revision = revision + 1;
+                        if !same {
+                            note.id = disk.next_id();
+                            note.text = format!(
+                                "{}\n\n#{}",
+                                note.text.trim_end(),
+                                CONFLICT_TAG
+                            );
+                            note.tags = extract_tags(&note.text);
+                            feed.conflicts += 1;
+                        }
+                    }
+                    _ => {}
+                }
+                changed.insert(note.id);
+                disk.put(note);
+            }
+            // only the note removed here, not one written at the same time
+            // as it that got its id
+            None => {
+                let date = feed.removed.get(&id);
+                disk.notes.retain(|n| {
+                    n.id != id || date.is_some_and(|&d| d != n.date)
+                });
+                changed.insert(id);
+            }
+        }
+    }
+    for note in disk.notes.iter_mut().filter(|n| changed.contains(&n.id)) {
+        if let Some(&parent) =
+            note.reply_to.as_ref().and_then(|p| renamed.get(p))
+        {
+            note.reply_to = Some(parent);
+        }
+    }
+
+    feed.notes = disk.notes;
+    feed.last_id = disk.last_id;
+    feed.changed = changed;
+    feed.rewrite |= disk.rewrite;
+    feed.on_disk = disk.on_disk;
+    feed.merged = true;
+    Ok(())
+}
+
+/// The file the feed of `path` is saved to when the app crashes.
+pub fn recovery_path(path: &Path) -> PathBuf {
+    let mut recovery = path.as_os_str().to_owned();
+    recovery.push(".recovery");
+    PathBuf::from(recovery)
+}
+
+/// Saves the feed of `path` to its recovery file, to be picked up by the
+/// next [`load`]. Used when the app is going down, so it does nothing but
+/// write the file.
+pub fn save_recovery(
+    path: &Path,
+    feed: &Feed,
+    cipher: Option<&Cipher>,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let recovery = recovery_path(path);
+    write(&recovery, feed, cipher)?;
+    Ok(recovery)
+}
+
+/// Writes `feed` to `path` as JSON, encrypted with `cipher` if there is one.
+pub fn write(
+    path: &Path,
+    feed: &Feed,
+    cipher: Option<&Cipher>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    write_data(path, &serde_json::to_vec(feed)?, cipher)
+}
+
+/// Writes `data` to `path` through a temporary file, so a crash leaves
+/// either the old or the new file.
+pub fn write_data(
+    path: &Path,
+    data: &[u8],
+    cipher: Option<&Cipher>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+
+    let data = match cipher {
+        Some(cipher) => cipher.encrypt(data)?,
+        None => data.to_vec(),
+    };
+    let mut file = File::create(&tmp)?;
+    file.write_all(&data)?;
+    file.sync_all()?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// The scratch pad kept next to the notebooks of `data_file`, shared by
+/// all of them.
+pub fn scratch_path(data_file: &Path) -> PathBuf {
+    notebook_dir(data_file).join("scratch.txt")
+}
+
+/// The text of the scratch pad, empty if it was never written.
+pub fn load_scratch(
+    data_file: &Path,
+    cipher: Option<&Cipher>,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let data = match fs::read(scratch_path(data_file)) {
+        Ok(data) => data,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return Ok(String::new())
+        }
+        Err(e) => return Err(e.into()),
+    };
+    if crypt::is_encrypted(&data) {
+        let cipher = cipher.ok_or("the scratch pad is encrypted")?;
+        return Ok(String::from_utf8(cipher.decrypt(&data)?)?);
+    }
+    Ok(String::from_utf8(data)?)
+}
+
+pub fn save_scratch(
+    data_file: &Path,
+    text: &str,
+    cipher: Option<&Cipher>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    fs::create_dir_all(notebook_dir(data_file))?;
+    write_data(&scratch_path(data_file), text.as_bytes(), cipher)
+}
+
+/// Where the UI left off in a notebook, picked up the next time it is opened.
+#[derive(Default, Serialize, Deserialize)]
+pub struct Session {
+    /// The note that was selected.
+    pub selected: Option<NoteId>,
+    /// The note last read in the read view, and how far down it was scrolled.
+    pub read: Option<(NoteId, u16)>,
+    /// The newest id given out, telling the notes added since apart.
+    pub last_id: NoteId,
+}
+
+/// The file the session of the notebook at `path` is kept in.
+pub fn session_path(path: &Path) -> PathBuf {
+    let mut session = path.as_os_str().to_owned();
+    session.push(".session");
+    PathBuf::from(session)
+}
+
+/// The session of the notebook at `path`, or `None` if it was never opened.
+/// It only holds note ids and is never encrypted.
+pub fn load_session(
+    path: &Path,
+) -> Result<Option<Session>, Box<dyn std::error::Error>> {
+    match fs::read(session_path(path)) {
+        Ok(data) => Ok(Some(serde_json::from_slice(&data)?)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub fn save_session(
+    path: &Path,
+    session: &Session,
+) -> Result<(), Box<dyn std::error::Error>> {
+    write_data(&session_path(path), &serde_json::to_vec(session)?, None)
+}
+
+/// The directory holding the backups of the notebooks next to `data_file`.
+pub fn backup_dir(data_file: &Path) -> PathBuf {
+    notebook_dir(data_file).join("backups")
+}
+
+/// Writes `feed`, as just loaded from `path`, to today's backup of the
+/// notebook, `backups/<name>-<YYYY-MM-DD>.json`, and deletes its backups
+/// older than the last `keep`. Nothing is backed up while there is no file
+/// yet or `keep` is 0.
+pub fn backup(
+    path: &Path,
+    feed: &Feed,
+    cipher: Option<&Cipher>,
+    keep: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if keep == 0 || !path.exists() {
+        return Ok(());
+    }
+    let dir = backup_dir(path);
+    fs::create_dir_all(&dir)?;
+    let name = notebook_name(path);
+    let today = Local::now().format("%Y-%m-%d");
+    write(&dir.join(format!("{}-{}.json", name, today)), feed, cipher)?;
+
+    let prefix = format!("{}-", name);
+    let mut backups = Vec::new();
+    for entry in fs::read_dir(&dir)? {
+        let path = entry?.path();
+        let is_backup = path
+            .file_name()
+            .and_then(|f| f.to_str())
+            .and_then(|f| f.strip_prefix(&prefix)?.strip_suffix(".json"))
+            .is_some_and(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").is_ok());
+        if is_backup {
+            backups.push(path);
+        }
+    }
+    // the dates sort the same as the names
+    backups.sort();
+    for old in &backups[..backups.len().saturating_sub(keep)] {
+        fs::remove_file(old)?;
+    }
+    Ok(())
+}
+
+/// The directory holding the notes archived by month, next to the notebooks
+/// of `data_file`.
+pub fn archive_dir(data_file: &Path) -> PathBuf {
+    notebook_dir(data_file).join("archive")
+}
+
+/// The archive of the notebook at `data_file` for the month `month` is in,
+/// `archive/<name>-<YYYY-MM>.json`.
+pub fn archive_path(data_file: &Path, month: NaiveDate) -> PathBuf {
+    archive_dir(data_file).join(format!(
+        "{}-{}.json",
+        notebook_name(data_file),
+        month.format("%Y-%m")
+    ))
+}
+
+/// The month archives of the notebook at `data_file`, oldest first.
+pub fn archives(data_file: &Path) -> io::Result<Vec<PathBuf>> {
+    let prefix = format!("{}-", notebook_name(data_file));
+    let mut archives = Vec::new();
+    let entries = match fs::read_dir(archive_dir(data_file)) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(archives),
+        Err(e) => return Err(e),
+    };
+    for entry in entries {
+        let path = entry?.path();
+        let is_archive = path
+            .file_name()
+            .and_then(|f| f.to_str())
+            .and_then(|f| f.strip_prefix(&prefix)?.strip_suffix(".json"))
+            .is_some_and(|m| {
+                NaiveDate::parse_from_str(&format!("{}-01", m), "%Y-%m-%d")
+                    .is_ok()
+            });
+        if is_archive {
+            archives.push(path);
+        }
+    }
+    archives.sort();
+    Ok(archives)
+}
+
+/// `$FEEDNOTES_DATA`, if set.
+pub fn env_data_file() -> Option<PathBuf> {
+    std::env::var_os("FEEDNOTES_DATA")
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+}
+
+/// `$FEEDNOTES_DATA` if set, otherwise `notebook` in the data directory, e.g.
+/// `~/.local/share/feednotes/notes.json`, with the extension of `storage`
+/// unless there is one already.
+pub fn default_data_file(
+    storage: Backend,
+    notebook: &str,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    match env_data_file() {
+        Some(path) => Ok(path),
+        None => Ok(notebook_path(&paths::data_dir()?, notebook, storage)),
+    }
+}
+
+/// The directory holding the notebooks, i.e. the one `data_file` is in.
+pub fn notebook_dir(data_file: &Path) -> PathBuf {
+    match data_file.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
+
+/// The file backing notebook `name` in `dir`, whichever backend it was
+/// made with, or a new one for `storage`.
+pub fn notebook_path(dir: &Path, name: &str, storage: Backend) -> PathBuf {
+    Backend::ALL
+        .into_iter()
+        .map(|b| dir.join(format!("{}.{}", name, b.extension())))
+        .find(|path| path.exists())
+        .unwrap_or_else(|| {
+            dir.join(format!("{}.{}", name, storage.extension()))
+        })
+}
+
+/// Whether `name` can name a notebook: a file name that is not hidden.
+pub fn is_notebook_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('.')
+        && !name.contains(std::path::is_separator)
+}
+
+/// The notebook name of `data_file`, which is its file name without the
+/// extension.
+pub fn notebook_name(data_file: &Path) -> String {
+    data_file
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Names of the notebooks in `dir`, one for every `<name>.json`, `.jsonl`
+/// or `.db` file, sorted.
+pub fn notebooks(dir: &Path) -> io::Result<Vec<String>> {
+    let mut names = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let is_notebook = path
+            .extension()
+            .is_some_and(|e| Backend::ALL.iter().any(|b| e == b.extension()));
+        if is_notebook {
+            names.push(notebook_name(&path));
+        }
+    }
+    names.sort();
+    names.dedup();
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Note;
+
+    /// A notebook in a directory of its own, and two feeds of it, as two
+    /// processes would have it open.
+    fn open_twice(name: &str) -> (PathBuf, Feed, Feed) {
+        let dir = std::env::temp_dir().join(format!(
+            "feednotes-store-{}-{}",
+            std::process::id(),
+            name
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("notes.json");
+        let a = load(&path, None).unwrap();
+        let b = load(&path, None).unwrap();
+        (path, a, b)
+    }
+
+    fn texts(path: &Path) -> Vec<String> {
+        let feed = read(path, None).unwrap();
+        let mut texts: Vec<String> =
+            feed.notes.iter().map(|n| n.text.clone()).collect();
+        texts.sort();
+        texts
+    }
+
+    fn clean_up(path: &Path) {
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn notes_given_the_same_id_are_both_kept() {
+        let (path, mut a, mut b) = open_twice("ids");
+        let id = a.push(Note::new("from a".into()));
+        save(&path, &mut a, None).unwrap();
+        assert_eq!(b.push(Note::new("from b".into())), id);
+        save(&path, &mut b, None).unwrap();
+
+        assert_eq!(b.conflicts, 0);
+        assert_eq!(texts(&path), ["from a", "from b"]);
+        let feed = read(&path, None).unwrap();
+        let from_b = feed.notes.iter().find(|n| n.text == "from b").unwrap();
+        assert_ne!(from_b.id, id);
+        clean_up(&path);
+    }
+
+    #[test]
+    fn notes_changed_on_both_sides_are_kept_twice() {
+        let (path, mut a, _) = open_twice("conflict");
+        a.push(Note::new("note".into()));
+        save(&path, &mut a, None).unwrap();
+        let mut b = load(&path, None).unwrap();
+        a.notes[0].set_text("from a".into());
+        a.touch(0);
+        save(&path, &mut a, None).unwrap();
+        b.notes[0].set_text("from b".into());
+        b.touch(0);
+        save(&path, &mut b, None).unwrap();
+
+        assert_eq!(b.conflicts, 1);
+        let copy = format!("from b\n\n#{}", CONFLICT_TAG);
+        assert_eq!(texts(&path), ["from a", copy.as_str()]);
+        let feed = read(&path, None).unwrap();
+        let from_a = feed.notes.iter().find(|n| n.text == "from a").unwrap();
+        assert_eq!(from_a.id, a.notes[0].id);
+        clean_up(&path);
+    }
+
+    #[test]
+    fn replies_follow_a_parent_given_another_id() {
+        let (path, mut a, mut b) = open_twice("replies");
+        a.push(Note::new("from a".into()));
+        save(&path, &mut a, None).unwrap();
+        let parent = b.push(Note::new("parent".into()));
+        let mut reply = Note::new("reply".into());
+        reply.reply_to = Some(parent);
+        b.push(reply);
+        save(&path, &mut b, None).unwrap();
+
+        let feed = read(&path, None).unwrap();
+        let find = |text: &str| feed.notes.iter().find(|n| n.text == text);
+        let parent = find("parent").unwrap();
+        assert_ne!(parent.id, find("from a").unwrap().id);
+        assert_eq!(find("reply").unwrap().reply_to, Some(parent.id));
+        clean_up(&path);
+    }
+
+    #[test]
+    fn a_note_removed_here_leaves_one_given_its_id_elsewhere() {
+        let (path, mut a, mut b) = open_twice("removed");
+        let id = b.push(Note::new("from b".into()));
+        save(&path, &mut b, None).unwrap();
+        assert_eq!(a.push(Note::new("from a".into())), id);
+        a.remove(0);
+        save(&path, &mut a, None).unwrap();
+
+        assert_eq!(texts(&path), ["from b"]);
+        clean_up(&path);
+    }
+
+    #[test]
+    fn notes_deleted_on_either_side_stay_deleted() {
+        let (path, mut a, _) = open_twice("deletes");
+        for text in ["kept", "deleted by a", "deleted by b"] {
+            a.push(Note::new(text.into()));
+        }
+        save(&path, &mut a, None).unwrap();
+        let mut b = load(&path, None).unwrap();
+        let position =
+            |feed: &Feed, text| feed.notes.iter().position(|n| n.text == text);
+        a.remove(position(&a, "deleted by a").unwrap());
+        save(&path, &mut a, None).unwrap();
+        b.remove(position(&b, "deleted by b").unwrap());
+        save(&path, &mut b, None).unwrap();
+
+        assert_eq!(texts(&path), ["kept"]);
+        assert_eq!(b.notes.len(), 1);
+        clean_up(&path);
+    }
+}
@@ -0,0 +1,99 @@
+//! Sync through git. When the directory holding the notebooks is the root of
+//! a git repository, every save is committed, and the feed can pull and push
+//! to the repository's upstream.
+
+use std::{
+    path::Path,
+    process::{Command, Output, Stdio},
+};
+
+/// Runs `git` in `dir`, failing with git's own message when it exits
+/// unsuccessfully. Git must not prompt for credentials, since the terminal
+/// belongs to the TUI while it runs.
+fn git(
+    dir: &Path,
+    args: &[&str],
+) -> Result<Output, Box<dyn std::error::Error>> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdin(Stdio::null())
+        .output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("git {}: {}", args[0], stderr.trim()).into());
+    }
+    Ok(output)
+}
+
+/// Whether `dir` is the top level of a git work tree. A notes directory that
+/// merely sits somewhere inside another repository does not count.
+pub fn is_repo(dir: &Path) -> bool {
+    let Ok(output) = git(dir, &["rev-parse", "--show-toplevel"]) else {
+        return false;
+    };
+    let toplevel = String::from_utf8_lossy(&output.stdout);
+    match (Path::new(toplevel.trim()).canonicalize(), dir.canonicalize()) {
+        (Ok(toplevel), Ok(dir)) => toplevel == dir,
+        _ => false,
+    }
+}
+
+/// Commits `files`, the first of which names the commit, if they changed
+/// since the last commit. Files that are gone are committed as deleted, or
+/// left out if git never knew them.
+pub fn commit(
+    dir: &Path,
+    files: &[&Path],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut names = Vec::new();
+    for file in files {
+        let name = file.file_name().ok_or("notes file has no name")?;
+        let name = name.to_string_lossy().into_owned();
+        if file.exists()
+            || git(dir, &["ls-files", "--error-unmatch", "--", &name]).is_ok()
+        {
+            names.push(name);
+        }
+    }
+    let Some(first) = names.first() else {
+        return Ok(());
+    };
+    let message = format!("Update {}", first);
+    let names: Vec<&str> = names.iter().map(String::as_str).collect();
+
+    git(dir, &[&["add", "--all", "--"], &names[..]].concat())?;
+    // exits with 1 when there are staged changes
+    if git(dir, &[&["diff", "--cached", "--quiet", "--"], &names[..]].concat())
+        .is_ok()
+    {
+        return Ok(());
+    }
+    git(
+        dir,
+        &[&["commit", "--quiet", "-m", &message, "--"], &names[..]].concat(),
+    )?;
+    Ok(())
+}
+
+/// Pulls from and then pushes to the upstream of the current branch. A pull
+/// that does not merge cleanly is aborted, leaving the local history as it
+/// was, so the conflict can be resolved by hand.
+pub fn sync(dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    if let Err(e) = git(dir, &["pull", "--no-rebase", "--no-edit", "--quiet"]) {
+        if git(dir, &["rev-parse", "--verify", "--quiet", "MERGE_HEAD"]).is_ok()
+        {
+            git(dir, &["merge", "--abort"])?;
+            return Err(format!(
+                "conflict with the remote, merge it by hand in {}",
+                dir.display()
+            )
+            .into());
+        }
+        return Err(e);
+    }
+    git(dir, &["push", "--quiet"])?;
+    Ok(())
+}
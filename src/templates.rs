@@ -0,0 +1,55 @@
+//! Templates new notes can start from: any file in `templates/` in the
+//! config directory, e.g. `~/.config/feednotes/templates/`, named after the file without its
+//! extension. `{{date}}`, `{{time}}`, `{{datetime}}` and `{{weekday}}` in a
+//! template are filled in when it is used.
+
+use std::{fs, io, path::PathBuf};
+
+use chrono::{DateTime, Local};
+
+use crate::config;
+
+pub fn dir() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    Ok(config::dir()?.join("templates"))
+}
+
+/// The names of the templates, sorted. None when the directory is missing.
+pub fn list() -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let entries = match fs::read_dir(dir()?) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut names = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        let Some(name) = path.file_stem().map(|s| s.to_string_lossy()) else {
+            continue;
+        };
+        if path.is_file() && !name.starts_with('.') {
+            names.push(name.into_owned());
+        }
+    }
+    names.sort();
+    names.dedup();
+    Ok(names)
+}
+
+/// The text of template `name` with its placeholders filled in for now.
+pub fn load(name: &str) -> Result<String, Box<dyn std::error::Error>> {
+    for entry in fs::read_dir(dir()?)? {
+        let path = entry?.path();
+        if path.file_stem().is_some_and(|s| s == name) && path.is_file() {
+            return Ok(expand(&fs::read_to_string(path)?, Local::now()));
+        }
+    }
+    Err(format!("no template named {}", name).into())
+}
+
+/// `text` with the placeholders of a template filled in for `now`.
+pub fn expand(text: &str, now: DateTime<Local>) -> String {
+    text.replace("{{datetime}}", &now.format("%Y-%m-%d %H:%M").to_string())
+        .replace("{{date}}", &now.format("%Y-%m-%d").to_string())
+        .replace("{{time}}", &now.format("%H:%M").to_string())
+        .replace("{{weekday}}", &now.format("%A").to_string())
+}
@@ -0,0 +1,89 @@
+//! Formatting note timestamps for display.
+
+use chrono::{DateTime, Datelike, Local};
+use serde::Deserialize;
+
+/// How times of day are written.
+#[derive(Clone, Copy, Default, Deserialize)]
+pub enum Clock {
+    #[default]
+    #[serde(rename = "24h")]
+    H24,
+    #[serde(rename = "12h")]
+    H12,
+}
+
+impl Clock {
+    /// A time of day, `14:20` or `2:20 PM`.
+    pub fn time_format(self) -> &'static str {
+        match self {
+            Clock::H24 => "%H:%M",
+            Clock::H12 => "%-I:%M %p",
+        }
+    }
+
+    /// The date and time, used when no date format is set.
+    pub fn date_format(self) -> &'static str {
+        match self {
+            Clock::H24 => "%Y-%m-%d %H:%M:%S",
+            Clock::H12 => "%Y-%m-%d %-I:%M:%S %p",
+        }
+    }
+}
+
+/// Formats `date` with `date_format`, or relative to `now` ("5 minutes ago",
+/// "yesterday 14:20") when `relative` is set, with times of day on `clock`.
+pub fn label(
+    date: DateTime<Local>,
+    now: DateTime<Local>,
+    relative: bool,
+    date_format: &str,
+    clock: Clock,
+) -> String {
+    if relative {
+        if let Some(label) = humanize(date, now, clock) {
+            return label;
+        }
+    }
+    date.format(date_format).to_string()
+}
+
+/// `None` for dates in the future, which only happen when the clock was
+/// changed and are shown as they are.
+fn humanize(
+    date: DateTime<Local>,
+    now: DateTime<Local>,
+    clock: Clock,
+) -> Option<String> {
+    let time = clock.time_format();
+    let elapsed = now.signed_duration_since(date);
+    if elapsed.num_seconds() < 0 {
+        return None;
+    }
+
+    let days = (now.date_naive() - date.date_naive()).num_days();
+    let label = if elapsed.num_minutes() < 1 {
+        "just now".to_string()
+    } else if elapsed.num_hours() < 1 {
+        plural(elapsed.num_minutes(), "minute")
+    } else if days == 0 {
+        plural(elapsed.num_hours(), "hour")
+    } else if days == 1 {
+        date.format(&format!("yesterday {}", time)).to_string()
+    } else if days < 7 {
+        date.format(&format!("%A {}", time)).to_string()
+    } else if date.year() == now.year() {
+        date.format(&format!("%b %-d {}", time)).to_string()
+    } else {
+        date.format("%b %-d %Y").to_string()
+    };
+    Some(label)
+}
+
+fn plural(n: i64, unit: &str) -> String {
+    if n == 1 {
+        format!("1 {} ago", unit)
+    } else {
+        format!("{} {}s ago", n, unit)
+    }
+}
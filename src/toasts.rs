@@ -0,0 +1,105 @@
+//! Messages about what happened, shown for a few seconds in a corner of the
+//! screen and kept to be read again with `:messages`.
+
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Local};
+
+/// How many messages are kept for `:messages`.
+const HISTORY: usize = 200;
+
+/// How long a message is shown. Errors stay twice as long.
+const SHOWN_FOR: Duration = Duration::from_secs(4);
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Info,
+    Error,
+}
+
+pub struct Toast {
+    pub text: String,
+    pub level: Level,
+    /// When it was last said.
+    pub at: DateTime<Local>,
+    shown: Instant,
+}
+
+impl Toast {
+    /// When it stops being shown.
+    fn until(&self) -> Instant {
+        match self.level {
+            Level::Info => self.shown + SHOWN_FOR,
+            Level::Error => self.shown + SHOWN_FOR * 2,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Toasts {
+    /// Oldest first.
+    history: VecDeque<Toast>,
+}
+
+impl Toasts {
+    pub fn info(&mut self, text: impl Into<String>) {
+        self.push(text.into(), Level::Info);
+    }
+
+    pub fn error(&mut self, text: impl Into<String>) {
+        self.push(text.into(), Level::Error);
+    }
+
+    fn push(&mut self, text: String, level: Level) {
+        if text.is_empty() {
+            return;
+        }
+        let now = (Local::now(), Instant::now());
+        // said again right away, it is shown for longer instead of twice
+        if let Some(last) = self
+            .history
+            .back_mut()
+            .filter(|t| t.text == text && t.level == level)
+        {
+            (last.at, last.shown) = now;
+            return;
+        }
+        if self.history.len() == HISTORY {
+            self.history.pop_front();
+        }
+        self.history.push_back(Toast { text, level, at: now.0, shown: now.1 });
+    }
+
+    /// The latest `max` messages still shown, oldest first.
+    pub fn shown(&self, max: usize) -> Vec<&Toast> {
+        let now = Instant::now();
+        let mut shown: Vec<&Toast> = self
+            .history
+            .iter()
+            .rev()
+            .filter(|t| t.until() > now)
+            .take(max)
+            .collect();
+        shown.reverse();
+        shown
+    }
+
+    /// How long until the next message shown goes away.
+    pub fn deadline(&self) -> Option<Duration> {
+        let now = Instant::now();
+        self.history
+            .iter()
+            .map(Toast::until)
+            .filter(|&until| until > now)
+            .min()
+            .map(|until| until - now)
+    }
+
+    /// Every message kept, oldest first.
+    pub fn history(&self) -> &VecDeque<Toast> {
+        &self.history
+    }
+}
@@ -0,0 +1,122 @@
+//! The words and tags used most in the notes of a week or a month, as a
+//! rough picture of what was on one's mind.
+
+use std::collections::HashMap;
+
+use chrono::{Datelike, Days, Months, NaiveDate, Weekday};
+
+use crate::{model::Feed, spell};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Period {
+    Week,
+    Month,
+}
+
+impl Period {
+    /// The first day of the period `day` is in, weeks starting on
+    /// `week_start`.
+    pub fn start(self, day: NaiveDate, week_start: Weekday) -> NaiveDate {
+        match self {
+            Period::Week => {
+                day - Days::new(day.weekday().days_since(week_start) as u64)
+            }
+            Period::Month => day.with_day(1).unwrap(),
+        }
+    }
+
+    /// The first day of the period after the one starting on `start`.
+    pub fn next(self, start: NaiveDate) -> NaiveDate {
+        match self {
+            Period::Week => start + Days::new(7),
+            Period::Month => start + Months::new(1),
+        }
+    }
+
+    /// The first day of the period before the one starting on `start`.
+    pub fn previous(self, start: NaiveDate) -> NaiveDate {
+        match self {
+            Period::Week => start - Days::new(7),
+            Period::Month => start - Months::new(1),
+        }
+    }
+
+    /// What the period starting on `start` is called, e.g. "October 2024".
+    pub fn name(self, start: NaiveDate) -> String {
+        match self {
+            Period::Week => start.format("week of %-d %B %Y").to_string(),
+            Period::Month => start.format("%B %Y").to_string(),
+        }
+    }
+}
+
+pub struct Topics {
+    /// Notes written in the period.
+    pub notes: usize,
+    /// How often each word other than the stopwords was written, most often
+    /// first.
+    pub words: Vec<(String, usize)>,
+    /// How many notes had each tag, most first.
+    pub tags: Vec<(String, usize)>,
+}
+
+impl Topics {
+    /// Counts the words and tags of the notes outside the trash written from
+    /// `from` up to but not including `to`. Case is ignored, and words
+    /// shorter than three letters are left out along with the stopwords.
+    pub fn new(feed: &Feed, from: NaiveDate, to: NaiveDate) -> Topics {
+        let mut notes = 0;
+        let mut words: HashMap<String, usize> = HashMap::new();
+        let mut tags: HashMap<String, usize> = HashMap::new();
+        for note in &feed.notes {
+            let day = note.date.date_naive();
+            if note.deleted_at.is_some() || day < from || day >= to {
+                continue;
+            }
+            notes += 1;
+            for line in note.text.lines() {
+                for (_, word) in spell::words(line) {
+                    let word = word.to_lowercase();
+                    let word = word
+                        .strip_suffix("'s")
+                        .or_else(|| word.strip_suffix("’s"))
+                        .unwrap_or(&word);
+                    if word.chars().count() < 3 || is_stopword(word) {
+                        continue;
+                    }
+                    *words.entry(word.to_string()).or_insert(0) += 1;
+                }
+            }
+            for tag in &note.tags {
+                *tags.entry(tag.to_lowercase()).or_insert(0) += 1;
+            }
+        }
+        Topics { notes, words: ranked(words), tags: ranked(tags) }
+    }
+}
+
+fn is_stopword(word: &str) -> bool {
+    STOPWORDS.split_whitespace().any(|w| w == word)
+}
+
+/// `counts` with the highest first, and equal ones in alphabetical order.
+fn ranked(counts: HashMap<String, usize>) -> Vec<(String, usize)> {
+    let mut ranked: Vec<(String, usize)> = counts.into_iter().collect();
+    ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    ranked
+}
+
+/// Common English words that say nothing about what a note is about.
+const STOPWORDS: &str =
+    "about above after again against all also and any are aren't because been \
+    before being below between both but can can't cannot could couldn't did \
+    didn't does doesn't doing don't down during each even ever few for from \
+    further get gets got had hadn't has hasn't have haven't having her here \
+    hers herself him himself his how i'd i'll i'm i've into isn't it's its \
+    itself just let's like more most much must mustn't myself never nor not \
+    now off once one only other ought our ours ourselves out over own really \
+    same shan't she should shouldn't some still such than that the their \
+    theirs them themselves then there these they thing things this those \
+    through too under until very was wasn't way well were weren't what when \
+    where which while who whom why will with won't would wouldn't yet you \
+    you'd you'll you're you've your yours yourself yourselves";
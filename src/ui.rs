@@ -0,0 +1,7121 @@
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    fs, io,
+    ops::Range,
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use chrono::{Datelike, Days, Local, Months, NaiveDate, Weekday};
+use ratatui::{
+    self,
+    buffer::Buffer,
+    crossterm::{
+        event::{
+            self, DisableBracketedPaste, DisableMouseCapture,
+            EnableBracketedPaste, EnableMouseCapture, Event, KeyCode, KeyEvent,
+            KeyEventKind, KeyModifiers, MouseButton, MouseEvent,
+            MouseEventKind,
+        },
+        execute,
+    },
+    layout::{Constraint, Layout, Position, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span, Text},
+    widgets::{
+        Block, Clear, Padding, Paragraph, Scrollbar, ScrollbarOrientation,
+        ScrollbarState, Widget, Wrap,
+    },
+    Frame,
+};
+use regex::Regex;
+use tui_textarea::{CursorMove, Input, Key, TextArea};
+use tui_widget_list::{ListBuilder, ListState, ListView};
+
+use crate::{
+    attachments,
+    chord::{Chord, Chords, Step},
+    cli, clipboard,
+    complete::{self, Completion},
+    config::{Config, Keys, Theme},
+    crypt::Cipher,
+    diff::{self, Change},
+    export,
+    filter::{DayHeader, FeedView, Query, Sort},
+    fuzzy, geo,
+    i18n::{Language, Strings},
+    mastodon,
+    model::{
+        self, checkbox, extract_tags, links, urls, Feed, Note, NoteColor,
+        NoteId, Revision, TRASH_RETENTION_DAYS,
+    },
+    saver::{self, Saver},
+    signals::Signals,
+    snippets,
+    spell::{self, Speller},
+    stats::{self, Stats},
+    store::{self, Session},
+    templates, timefmt,
+    toasts::{Level, Toasts},
+    topics::{Period, Topics},
+    watch::Watch,
+    webhooks,
+};
+
+/// The value of `result`, or else shows its error in `toasts` and returns,
+/// leaving the rest of what a key does undone: the notes are still there to
+/// keep working on, or to save, after a file could not be read or written.
+macro_rules! recover {
+    ($toasts:ident, $result:expr) => {
+        match $result {
+            Ok(value) => value,
+            Err(e) => {
+                $toasts.error(e.to_string());
+                return;
+            }
+        }
+    };
+}
+
+#[derive(PartialEq, Eq)]
+enum Focus {
+    NewNote,
+    Feed,
+    Filter,
+    Tags,
+    Trash,
+    JumpDate,
+    ConfirmDelete,
+    Notebooks,
+    NewNotebook,
+    /// The selected note on its own, read only.
+    Read,
+    /// The messages shown since feednotes started, with `:messages`.
+    Messages,
+    /// Picking part of the note being read to copy, with the composer's
+    /// motions.
+    SelectText,
+    /// Asking for a file to attach to the selected note.
+    Attach,
+    /// The files attached to the selected note.
+    Attachments,
+    /// Picking a checkbox of the selected note to toggle.
+    Checkboxes,
+    /// Picking the line to split the selected note before.
+    SplitNote,
+    /// Picking a template to start a new note from.
+    Templates,
+    /// Asking for a tag to add to the marked notes.
+    TagNotes,
+    /// Asking for a file to export the marked notes to.
+    ExportNotes,
+    /// A command typed after `:`, like `:w` to save.
+    Command,
+    /// Asking where the selected or marked notes were written.
+    Locate,
+    /// How much and how regularly notes are written.
+    Stats,
+    /// The words and tags used most in a week or a month.
+    Topics,
+    /// Picking a color for the selected or marked notes.
+    Colors,
+    /// Picking a replacement for the misspelled word under the composer's
+    /// cursor.
+    Spelling,
+    /// What the note being edited would be saved with, word by word.
+    ReviewEdit,
+    /// Asking whether a new note written again is merged into the note
+    /// written before, shown word by word against it.
+    Duplicate,
+    /// The earlier versions of the selected note.
+    Revisions,
+    /// Looking for a note by its title to go to.
+    Switcher,
+    /// Picking a web address of the selected note to open.
+    Urls,
+    /// A month with how many notes were written each day, to pick a day to
+    /// show the notes of.
+    Calendar,
+    /// The scratch pad, text kept apart from the notes.
+    Scratch,
+    /// Picking which notes to review.
+    ReviewPeriod,
+    /// Going through notes one at a time to keep, archive, tag or delete
+    /// each.
+    Review,
+    /// The lock screen, hiding the notes until the passphrase is typed.
+    Lock,
+}
+
+/// What the main loop reacts to: a terminal event, or a tick when none came
+/// for [`TICK`], for what changes with time alone.
+enum Message {
+    Event(Event),
+    Tick,
+}
+
+const TICK: Duration = Duration::from_secs(1);
+
+/// How many messages are shown at once in the corner.
+const TOASTS_SHOWN: usize = 3;
+
+/// The widest a message shown in the corner gets before it wraps.
+const TOAST_WIDTH: u16 = 60;
+
+/// The next event, or a tick if none comes within `timeout`.
+fn next_message(timeout: Duration) -> io::Result<Message> {
+    if event::poll(timeout)? {
+        match event::read()? {
+            // reported on Windows, where input methods send a press and a
+            // release for each character they commit
+            Event::Key(key) if key.kind == KeyEventKind::Release => {
+                Ok(Message::Tick)
+            }
+            event => Ok(Message::Event(event)),
+        }
+    } else {
+        Ok(Message::Tick)
+    }
+}
+
+enum InputMode {
+    Normal,
+    Insert,
+    View,
+    /// Visual mode selecting whole lines, entered with `V`.
+    ViewLine,
+}
+
+enum FeedEditingMode {
+    New,
+    Edit(usize),
+    /// Writing a reply to the note with this id.
+    Reply(NoteId),
+}
+
+/// What picking a notebook in the notebook list does.
+enum NotebookAction {
+    Switch,
+    /// Move the selected or marked notes into the picked notebook.
+    Move,
+}
+
+/// A feed mutation that can be reverted with `u`, holding what is needed to
+/// put the feed back the way it was.
+enum Undo {
+    Delete {
+        indices: Vec<usize>,
+    },
+    Edit {
+        notes: Vec<(usize, Note)>,
+    },
+    /// A note split in two, kept as it was before, and the index of the note
+    /// split off it, which goes to the trash.
+    Split {
+        note: (usize, Box<Note>),
+        added: usize,
+    },
+}
+
+/// Runs the terminal UI until the user quits, saving `feed` to `data_file`
+/// after every change, encrypted with `cipher` if there is one. Other
+/// notebooks are looked up next to `data_file`. When `readonly`, the keys that
+/// would change the notes are turned off and nothing is written.
+pub fn run(
+    mut feed: Feed,
+    mut data_file: PathBuf,
+    config: &Config,
+    cipher: Option<&Cipher>,
+    readonly: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        run_loop(&mut feed, &mut data_file, config, cipher, readonly)
+    }));
+    if let Ok(Ok(())) = result {
+        return Ok(());
+    }
+    if readonly {
+        let _ =
+            execute!(io::stdout(), DisableMouseCapture, DisableBracketedPaste);
+        ratatui::restore();
+        return match result {
+            Ok(result) => result,
+            Err(panic) => panic::resume_unwind(panic),
+        };
+    }
+
+    // the terminal is still in raw mode and the last changes may not have
+    // made it to disk
+    let _ = execute!(io::stdout(), DisableMouseCapture, DisableBracketedPaste);
+    ratatui::restore();
+    match store::save_recovery(&data_file, &feed, cipher) {
+        Ok(path) => eprintln!("notes saved to {}", path.display()),
+        Err(e) => eprintln!("could not save notes: {}", e),
+    }
+    match result {
+        Ok(result) => result,
+        Err(panic) => panic::resume_unwind(panic),
+    }
+}
+
+/// Runs a composer filling the terminal and nothing else, for `feednotes
+/// capture`. The note is added to `data_file` when saved with `ctrl-s` or the
+/// save keys, merging with what another feednotes has written in the
+/// meantime, and nothing is added when the composer is closed with `ctrl-c`
+/// or by leaving normal mode with backspace. Closing the terminal, or
+/// SIGTERM, adds it as saving would. A note that writes a recent one again is
+/// added next to it without asking, the composer being gone, and a note that
+/// cannot be added is printed instead, once the terminal is restored.
+pub fn capture(
+    data_file: &Path,
+    config: &Config,
+    cipher: Option<&Cipher>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let strings = config.language.unwrap_or_else(Language::from_env).strings();
+    let signals = Signals::catch()?;
+    let mut terminal = ratatui::init();
+    let text = execute!(io::stdout(), EnableBracketedPaste)
+        .map_err(Into::into)
+        .and_then(|()| compose(&mut terminal, config, strings, &signals));
+    let restored = execute!(io::stdout(), DisableBracketedPaste);
+    ratatui::restore();
+    let text = text?;
+    if !signals.caught() {
+        restored?;
+    }
+
+    if text.trim().is_empty() {
+        return Ok(());
+    }
+    // the feed is loaded only now, so the note goes after whatever was added
+    // while it was being written
+    let added =
+        cli::add_note(data_file, text.clone(), &config.webhooks, cipher, false);
+    if added.is_err() {
+        // the note is in no file yet, so it goes where it can be copied from
+        println!("{}", text);
+    }
+    added
+}
+
+/// The composer of [`capture`], until it is closed. Returns the text written,
+/// or nothing when it is thrown away.
+fn compose(
+    terminal: &mut ratatui::DefaultTerminal,
+    config: &Config,
+    strings: &Strings,
+    signals: &Signals,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let mut textarea = TextArea::default();
+    let mut inputmode = InputMode::Insert;
+    let mut vim = Vim::new(config);
+    loop {
+        // the note is added as if saved, as the terminal may be gone
+        if signals.caught() {
+            return Ok(textarea.lines().join("\n"));
+        }
+        vim.expire();
+        terminal.draw(|f| {
+            let mut block = Block::bordered()
+                .border_type(config.theme.border_type)
+                .title(format!(
+                    "{} ({})",
+                    strings.titles.capture,
+                    mode_name(strings, &inputmode)
+                ))
+                .title_bottom(format!(" {} ", strings.hints.capture));
+            if let Some(query) = &vim.search {
+                block = block.title_bottom(format!("/{}", query));
+            }
+            textarea.set_block(block);
+            textarea.set_cursor_line_style(Style::default());
+            textarea.set_search_style(
+                Style::default().add_modifier(Modifier::REVERSED),
+            );
+            f.render_widget(&textarea, f.area());
+            vim.width = f.area().width.saturating_sub(2) as usize;
+            if matches!(inputmode, InputMode::Insert) {
+                if let Some(at) = cursor_position(&textarea, f.area()) {
+                    f.set_cursor_position(at);
+                }
+            }
+        })?;
+
+        // woken up early when the keys of a command time out
+        let timeout = vim.deadline().unwrap_or(TICK);
+        let event = match next_message(timeout) {
+            Ok(Message::Event(event)) => event,
+            Ok(Message::Tick) => continue,
+            Err(_) if signals.caught() => continue,
+            Err(e) => return Err(e.into()),
+        };
+        if let Event::Paste(pasted) = &event {
+            textarea
+                .insert_str(pasted.replace("\r\n", "\n").replace('\r', "\n"));
+            continue;
+        }
+        match event.clone().into() {
+            Input { key: Key::Char('s'), ctrl: true, .. } => {
+                return Ok(textarea.lines().join("\n"));
+            }
+            Input { key: Key::Char('c'), ctrl: true, .. } => {
+                return Ok(String::new());
+            }
+            _ => match inputmode {
+                InputMode::Insert => {
+                    insert_event(event, &mut textarea, &mut inputmode, &mut vim)
+                }
+                _ => {
+                    let inputs: Vec<Input> = match event {
+                        Event::Key(key)
+                            if matches!(inputmode, InputMode::Normal)
+                                && vim.takes_keys() =>
+                        {
+                            match vim.keys.key(key, false) {
+                                Step::Run(chord)
+                                    if chord.is(&config.keys.save) =>
+                                {
+                                    return Ok(textarea.lines().join("\n"));
+                                }
+                                Step::Run(chord) => chord
+                                    .keys()
+                                    .into_iter()
+                                    .map(Input::from)
+                                    .collect(),
+                                Step::Pending | Step::Ignored => continue,
+                            }
+                        }
+                        event => vec![event.into()],
+                    };
+                    let mut focus = Focus::NewNote;
+                    for input in inputs {
+                        textarea_event(
+                            input,
+                            &mut textarea,
+                            &mut focus,
+                            &mut inputmode,
+                            &mut vim,
+                        )?;
+                    }
+                    if focus != Focus::NewNote {
+                        return Ok(String::new());
+                    }
+                }
+            },
+        }
+    }
+}
+
+fn run_loop(
+    feed: &mut Feed,
+    data_file: &mut PathBuf,
+    config: &Config,
+    cipher: Option<&Cipher>,
+    readonly: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut app = App::new(feed, data_file, config, cipher, readonly)?;
+    let watch = match Watch::new(&app.notebook_dir) {
+        Ok(watch) => Some(watch),
+        Err(e) => {
+            let message = app.strings.status.not_watching;
+            app.toasts.error(format!("{}: {}", message, e));
+            None
+        }
+    };
+    let signals = Signals::catch()?;
+    let mut terminal = ratatui::init();
+    execute!(io::stdout(), EnableMouseCapture, EnableBracketedPaste)?;
+
+    loop {
+        app.sync(watch.as_ref());
+        // quits like `q`, as the terminal may be gone and nothing more can
+        // be drawn
+        if signals.caught() {
+            app.keep_composed();
+            break;
+        }
+        app.chords.expire();
+        app.vim.expire();
+        app.toasts.info(std::mem::take(&mut app.status));
+
+        terminal.draw(|f| app.draw(f))?;
+
+        // woken up early when changes are due to be saved, when the keys of a
+        // command time out, or when a message is to be hidden
+        let timeout = [
+            app.saver.due_in(),
+            app.chords.deadline(),
+            app.vim.deadline(),
+            app.toasts.deadline(),
+        ]
+        .into_iter()
+        .flatten()
+        .min()
+        .unwrap_or(TICK);
+        // keys typed by a mapping go before the terminal's
+        let (message, mapped) = match app.chords.replay() {
+            Some(key) => (Ok(Message::Event(Event::Key(key))), true),
+            None => (next_message(timeout), false),
+        };
+        let message = match message {
+            // back to the top, which keeps what the composer holds
+            Err(_) if signals.caught() => continue,
+            message => message?,
+        };
+        match message {
+            Message::Event(Event::Resize(..)) => {
+                // the next draw lays everything out again for the new size,
+                // popups included, on a screen cleared of the old layout
+                terminal.clear()?;
+                app.card_heights.get_mut().clear();
+            }
+            Message::Event(event) => {
+                app.last_input = Instant::now();
+                app.handle(event, mapped);
+                if app.quit {
+                    break;
+                }
+            }
+            // and redrawn, so relative dates stay current
+            Message::Tick => app.tick(),
+        }
+    }
+
+    let restored =
+        execute!(io::stdout(), DisableMouseCapture, DisableBracketedPaste);
+    ratatui::restore();
+    if !readonly && !app.discard {
+        app.saver.flush(app.data_file, app.feed)?;
+        leave(
+            app.data_file,
+            app.feed,
+            &app.feed_view,
+            &app.state,
+            app.read_position,
+        )?;
+    }
+    // a terminal that was closed cannot be restored, nor needs to be
+    if !signals.caught() {
+        restored?;
+    }
+    Ok(())
+}
+
+/// Everything the UI shows and the keys act on, apart from the terminal,
+/// so that it can be driven without one.
+struct App<'a> {
+    feed: &'a mut Feed,
+    data_file: &'a mut PathBuf,
+    config: &'a Config,
+    cipher: Option<&'a Cipher>,
+    readonly: bool,
+    strings: &'static Strings,
+    notebook_dir: PathBuf,
+    sort: Sort,
+    journal: Journal,
+    feed_view: FeedView,
+    trash_view: FeedView,
+    focus: Focus,
+    state: ListState,
+    trash_state: ListState,
+    textarea: TextArea<'static>,
+    scratch: TextArea<'static>,
+    selection: TextArea<'static>,
+    filter: String,
+    inputmode: InputMode,
+    feed_editing_mode: FeedEditingMode,
+    tags: Vec<(String, usize)>,
+    tag_state: ListState,
+    undo_stack: Vec<Undo>,
+    /// What the last key did, shown in `toasts` before the next draw.
+    status: String,
+    toasts: Toasts,
+    messages_scroll: u16,
+    speller: Option<Speller>,
+    /// The suggestions for the word picked with z=, with its line and columns.
+    suggestions: Vec<String>,
+    suggestion_state: ListState,
+    /// What the word being typed in the composer can be completed to.
+    completion: Option<Completion>,
+    misspelled_at: (usize, Range<usize>),
+    notebooks: Vec<String>,
+    notebook_state: ListState,
+    notebook_action: NotebookAction,
+    picked_notebook: Option<String>,
+    attachment_state: ListState,
+    /// Images pasted into the composer, attached to the note saved with them.
+    pasted: Vec<PathBuf>,
+    url_state: ListState,
+    calendar_day: NaiveDate,
+    topics_period: Period,
+    topics_start: NaiveDate,
+    review_period_state: ListState,
+    review: Review,
+    checkbox_state: ListState,
+    split_line_state: ListState,
+    vim: Vim,
+    /// The commands of more than one key in the feed and the trash.
+    chords: Chords,
+    template_names: Vec<String>,
+    template_state: ListState,
+    color_state: ListState,
+    revision_state: ListState,
+    /// The notes whose titles match what is typed in the quick switcher.
+    switcher: Vec<usize>,
+    switcher_state: ListState,
+    /// Notes marked with `v`, which bulk actions apply to.
+    marked: HashSet<NoteId>,
+    /// Notes selected before jumps, most recent last, gone back to with Ctrl-o.
+    jumps: Vec<NoteId>,
+    relative_dates: bool,
+    /// The feed as a list of titles beside the selected note.
+    split: bool,
+    /// A line for each note instead of a card.
+    compact: bool,
+    /// Where each visible card was drawn, for mapping clicks to notes.
+    cards: Vec<(usize, Rect)>,
+    /// The height of each card measured, by note and width, so the cards
+    /// out of sight are not wrapped again on every draw. Cleared when the
+    /// terminal is resized or the notes change.
+    card_heights: RefCell<HashMap<(NoteId, u16), u16>>,
+    /// [`Feed::edits`] when `card_heights` were measured.
+    card_heights_edits: u64,
+    last_click: Option<(usize, Instant)>,
+    read_scroll: u16,
+    /// The link of the note read, or the note linking to it, picked with Tab.
+    read_link: Option<usize>,
+    read_page: u16,
+    review_scroll: u16,
+    /// The composer fills the screen, toggled with `gz`.
+    zen: bool,
+    /// The note a new one being saved writes again.
+    duplicate: usize,
+    /// The notebook was written to and is reloaded once back in the feed,
+    /// where nothing holds on to a note by its index.
+    disk_changed: bool,
+    /// The note to keep selected through a reload.
+    reselect: Option<NoteId>,
+    saver: Saver,
+    /// Quitting with `:q!`, leaving unsaved changes unsaved.
+    discard: bool,
+    /// Where the note last read was left.
+    read_position: Option<ReadPosition>,
+    /// The notes added since the last session, marked in the feed.
+    unread: HashSet<NoteId>,
+    lock: Lock,
+    last_input: Instant,
+    /// Set by the keys that quit, for the main loop to stop.
+    quit: bool,
+}
+
+impl<'a> App<'a> {
+    fn new(
+        feed: &'a mut Feed,
+        data_file: &'a mut PathBuf,
+        config: &'a Config,
+        cipher: Option<&'a Cipher>,
+        readonly: bool,
+    ) -> Result<App<'a>, Box<dyn std::error::Error>> {
+        let strings =
+            config.language.unwrap_or_else(Language::from_env).strings();
+        let journal = Journal { on: config.journal, collapsed: HashSet::new() };
+        let feed_view = build_view(feed, "", config.sort, &journal);
+        let mut state = ListState::default();
+        let scratch = TextArea::new(
+            store::load_scratch(data_file, cipher)?
+                .lines()
+                .map(|l| l.to_string())
+                .collect(),
+        );
+        let mut toasts = Toasts::default();
+        let mut speller = None;
+        if config.spell_check {
+            match Speller::start(&config.spell_language) {
+                Ok(s) => speller = Some(s),
+                Err(e) => toasts
+                    .error(format!("{}: {}", strings.status.spelling_off, e)),
+            }
+        }
+        let autosave = config.autosave.then(|| {
+            config.autosave_after.map_or(saver::DEBOUNCE, Duration::from_secs)
+        });
+        let (read_position, unread) =
+            resume(data_file, feed, &feed_view, &mut state)?;
+        let mut focus = Focus::Feed;
+        let mut lock = Lock::default();
+        if config.lock_after.is_some() && cipher.is_none() {
+            // nothing to unlock with yet
+            lock.choose_only = true;
+            lock.from = Some(std::mem::replace(&mut focus, Focus::Lock));
+        }
+        let keys = &config.keys;
+        let chords = Chords::new(
+            [&keys.delete, "yy", "yp", "gg", "gd", "gs", "gw", "gr", "gx"]
+                .map(String::from)
+                .to_vec(),
+            keys.timeout(),
+        )
+        .with_counts()
+        .with_mappings(&keys.map);
+        let today = Local::now().date_naive();
+        Ok(App {
+            notebook_dir: store::notebook_dir(data_file),
+            sort: config.sort,
+            trash_view: FeedView::trash(feed),
+            feed_view,
+            journal,
+            focus,
+            state,
+            trash_state: ListState::default(),
+            textarea: TextArea::default(),
+            scratch,
+            selection: TextArea::default(),
+            filter: String::new(),
+            inputmode: InputMode::Normal,
+            feed_editing_mode: FeedEditingMode::New,
+            tags: Vec::new(),
+            tag_state: ListState::default(),
+            undo_stack: Vec::new(),
+            status: String::new(),
+            toasts,
+            messages_scroll: 0,
+            speller,
+            suggestions: Vec::new(),
+            suggestion_state: ListState::default(),
+            completion: None,
+            misspelled_at: (0, 0..0),
+            notebooks: Vec::new(),
+            notebook_state: ListState::default(),
+            notebook_action: NotebookAction::Switch,
+            picked_notebook: None,
+            attachment_state: ListState::default(),
+            pasted: Vec::new(),
+            url_state: ListState::default(),
+            calendar_day: today,
+            topics_period: Period::Week,
+            topics_start: today,
+            review_period_state: ListState::default(),
+            review: Review::default(),
+            checkbox_state: ListState::default(),
+            split_line_state: ListState::default(),
+            vim: Vim::new(config),
+            chords,
+            template_names: Vec::new(),
+            template_state: ListState::default(),
+            color_state: ListState::default(),
+            revision_state: ListState::default(),
+            switcher: Vec::new(),
+            switcher_state: ListState::default(),
+            marked: HashSet::new(),
+            jumps: Vec::new(),
+            relative_dates: config.theme.relative_dates,
+            split: config.split,
+            compact: config.compact,
+            cards: Vec::new(),
+            card_heights: RefCell::default(),
+            card_heights_edits: 0,
+            last_click: None,
+            read_scroll: 0,
+            read_link: None,
+            read_page: 1,
+            review_scroll: 0,
+            zen: false,
+            duplicate: 0,
+            disk_changed: false,
+            reselect: None,
+            saver: Saver::start(cipher.cloned(), autosave),
+            discard: false,
+            read_position,
+            unread,
+            lock,
+            last_input: Instant::now(),
+            quit: false,
+            feed,
+            data_file,
+            config,
+            cipher,
+            readonly,
+            strings,
+        })
+    }
+
+    /// Saves and reloads the notes as due, and carries out what the last key
+    /// left to do between draws: switching notebooks, attaching pasted images
+    /// and checking the spelling.
+    fn sync(&mut self, watch: Option<&Watch>) {
+        let config = self.config;
+        let cipher = self.cipher;
+        let readonly = self.readonly;
+        let strings = self.strings;
+        let App {
+            feed,
+            data_file,
+            notebook_dir,
+            sort,
+            journal,
+            feed_view,
+            trash_view,
+            focus,
+            state,
+            trash_state,
+            textarea,
+            filter,
+            feed_editing_mode,
+            undo_stack,
+            status,
+            toasts,
+            speller,
+            notebook_action,
+            picked_notebook,
+            pasted,
+            marked,
+            card_heights,
+            disk_changed,
+            reselect,
+            saver,
+            read_position,
+            unread,
+            ..
+        } = self;
+        match saver.poll(data_file, feed) {
+            Ok(merged) => *disk_changed |= merged,
+            Err(e) => {
+                toasts.error(format!("{}: {}", strings.status.saving_failed, e))
+            }
+        }
+        if let Some(watch) = &watch {
+            *disk_changed |= watch.changed(data_file);
+        }
+        // not while saving, as the files are only known to be as saved
+        // once the save is done
+        if *disk_changed
+            && !saver.busy()
+            && matches!(focus, Focus::Feed | Focus::Trash)
+        {
+            *disk_changed = false;
+            let selected =
+                state.selected.map(|k| feed.notes[feed_view.refs[k]].id);
+            let reloaded = if !readonly {
+                store::reload(data_file, feed, cipher)
+            } else if store::changed_on_disk(data_file, feed) {
+                store::read(data_file, cipher).map(|f| {
+                    **feed = f;
+                    feed.merged = true;
+                })
+            } else {
+                Ok(())
+            };
+            match reloaded {
+                Ok(()) if feed.merged => *reselect = selected,
+                Ok(()) => {}
+                Err(e) => toasts
+                    .error(format!("{}: {}", strings.status.cannot_reload, e)),
+            }
+        }
+        if std::mem::take(&mut feed.merged) {
+            undo_stack.clear();
+            card_heights.get_mut().clear();
+            if *focus != Focus::NewNote {
+                *feed_editing_mode = FeedEditingMode::New;
+            }
+            *feed_view = build_view(feed, filter, *sort, journal);
+            *trash_view = FeedView::trash(feed);
+            if let Some(id) = reselect.take() {
+                if let Some(k) =
+                    feed_view.refs.iter().position(|&i| feed.notes[i].id == id)
+                {
+                    state.select(Some(k));
+                }
+            }
+            clamp(state, feed_view.refs.len());
+            clamp(trash_state, trash_view.refs.len());
+            *status =
+                (strings.status.merged_in)(std::mem::take(&mut feed.conflicts));
+        }
+        if let Some(name) = picked_notebook.take() {
+            // the other notebook's notes have ids of their own
+            card_heights.get_mut().clear();
+            let path =
+                store::notebook_path(notebook_dir, &name, config.storage);
+            match notebook_action {
+                NotebookAction::Switch if readonly => {
+                    **feed = recover!(toasts, store::read(&path, cipher));
+                    **data_file = path;
+                    filter.clear();
+                    *state = ListState::default();
+                    marked.clear();
+                    *status = (strings.status.switched)(&name);
+                    let view = build_view(feed, filter, *sort, journal);
+                    (*read_position, *unread) = resume(
+                        data_file, feed, &view, state,
+                    )
+                    .unwrap_or_else(|e| {
+                        toasts.error(e.to_string());
+                        (None, HashSet::new())
+                    });
+                }
+                NotebookAction::Switch => {
+                    recover!(toasts, saver.flush(data_file, feed));
+                    recover!(
+                        toasts,
+                        leave(
+                            data_file,
+                            feed,
+                            feed_view,
+                            state,
+                            *read_position
+                        )
+                    );
+                    // the notes open stay the ones saved to `data_file` until
+                    // the other notebook is ready
+                    let mut other =
+                        recover!(toasts, store::load(&path, cipher));
+                    recover!(
+                        toasts,
+                        store::backup(&path, &other, cipher, config.backups)
+                    );
+                    let purged = other.purge_trash(TRASH_RETENTION_DAYS);
+                    recover!(
+                        toasts,
+                        attachments::remove_orphans(&path, &other, &purged)
+                    );
+                    **feed = other;
+                    **data_file = path;
+                    saver.changed();
+                    filter.clear();
+                    *state = ListState::default();
+                    undo_stack.clear();
+                    marked.clear();
+                    *status = (strings.status.switched)(&name);
+                    let view = build_view(feed, filter, *sort, journal);
+                    (*read_position, *unread) = resume(
+                        data_file, feed, &view, state,
+                    )
+                    .unwrap_or_else(|e| {
+                        toasts.error(e.to_string());
+                        (None, HashSet::new())
+                    });
+                }
+                NotebookAction::Move if path == **data_file => {
+                    *status = (strings.status.already_in)(&name);
+                }
+                NotebookAction::Move => {
+                    let targets = targets(
+                        feed,
+                        marked,
+                        state.selected.map(|k| feed_view.refs[k]),
+                    );
+                    let mut other =
+                        recover!(toasts, store::load(&path, cipher));
+                    for &i in &targets {
+                        other.insert(feed.notes[i].clone());
+                    }
+                    // the notes only leave this notebook once they are safe
+                    // in the other
+                    recover!(toasts, store::save(&path, &mut other, cipher));
+                    // from the back, so the indices still to go stay valid
+                    for &i in targets.iter().rev() {
+                        feed.remove(i);
+                    }
+                    // removing the notes shifts the indices the undo entries
+                    // refer to
+                    undo_stack.clear();
+                    marked.clear();
+                    saver.changed();
+                    state.previous();
+                    *status = (strings.status.moved)(targets.len(), &name);
+                }
+            }
+            *feed_view = build_view(feed, filter, *sort, journal);
+            *trash_view = FeedView::trash(feed);
+            clamp(state, feed_view.refs.len());
+        }
+
+        if !pasted.is_empty()
+            && !matches!(
+                focus,
+                Focus::NewNote
+                    | Focus::ReviewEdit
+                    | Focus::Duplicate
+                    | Focus::Spelling
+                    | Focus::Lock
+            )
+        {
+            for name in std::mem::take(pasted) {
+                let reference = attachments::image_reference(&name);
+                match feed.notes.iter().position(|n| {
+                    n.deleted_at.is_none() && n.text.contains(&reference)
+                }) {
+                    Some(i) if feed.notes[i].attachments.contains(&name) => {}
+                    Some(i) => {
+                        feed.notes[i].attachments.push(name);
+                        feed.touch(i);
+                    }
+                    // not saved, or taken out of the text again
+                    None => {
+                        let _ = fs::remove_file(
+                            attachments::dir(data_file).join(name),
+                        );
+                    }
+                }
+            }
+            saver.changed();
+        }
+
+        if let (Focus::NewNote, Some(s)) = (&focus, speller.as_mut()) {
+            if let Err(e) = s.check(textarea.lines()) {
+                toasts.error(format!("{}: {}", strings.status.spelling_off, e));
+                *speller = None;
+            }
+        }
+    }
+
+    /// Saves what the composer holds as with the save key, rather than lose it
+    /// with the terminal.
+    fn keep_composed(&mut self) {
+        let config = self.config;
+        let readonly = self.readonly;
+        let App {
+            feed,
+            focus,
+            textarea,
+            feed_editing_mode,
+            undo_stack,
+            toasts,
+            saver,
+            ..
+        } = self;
+        // what the composer holds is saved as with the save key rather
+        // than lost with the terminal
+        let composing = matches!(
+            focus,
+            Focus::NewNote
+                | Focus::Spelling
+                | Focus::Duplicate
+                | Focus::ReviewEdit
+        );
+        let text = textarea.lines().join("\n");
+        if composing && !readonly && !text.trim().is_empty() {
+            match feed_editing_mode {
+                FeedEditingMode::Edit(i) if feed.notes[*i].text == text => {}
+                FeedEditingMode::Edit(i) => {
+                    edit_note(feed, *i, text, undo_stack)
+                }
+                _ => push_note(
+                    feed,
+                    text,
+                    feed_editing_mode,
+                    config,
+                    undo_stack,
+                    toasts,
+                ),
+            }
+            saver.changed();
+        }
+    }
+
+    /// Locks the screen after `lock_after` minutes without input.
+    fn tick(&mut self) {
+        let idle = self
+            .config
+            .lock_after
+            .is_some_and(|m| self.last_input.elapsed().as_secs() >= m * 60);
+        if idle
+            && self.focus != Focus::Lock
+            && (self.cipher.is_some() || self.lock.passphrase.is_some())
+        {
+            self.lock.from =
+                Some(std::mem::replace(&mut self.focus, Focus::Lock));
+        }
+    }
+}
+
+// drawing, a method for each focus
+impl App<'_> {
+    /// Draws what is in focus, and the status bar and the messages over it.
+    fn draw(&mut self, f: &mut Frame) {
+        // the zen composer hides the status bar too
+        let zen =
+            self.zen && matches!(self.focus, Focus::NewNote | Focus::Spelling);
+        let [area, bar_area] = Layout::vertical([
+            Constraint::Min(0),
+            Constraint::Length(if zen { 0 } else { 1 }),
+        ])
+        .areas(f.area());
+        match self.focus {
+            Focus::Feed | Focus::Trash | Focus::ConfirmDelete => {
+                self.draw_feed(f, area)
+            }
+            Focus::NewNote | Focus::Spelling => self.draw_composer(f),
+            Focus::Filter
+            | Focus::JumpDate
+            | Focus::NewNotebook
+            | Focus::Attach
+            | Focus::TagNotes
+            | Focus::ExportNotes
+            | Focus::Command
+            | Focus::Locate => self.draw_prompt(f),
+            Focus::Switcher => self.draw_switcher(f),
+            Focus::Read => self.draw_read(f, area),
+            Focus::SelectText => self.draw_select_text(f, area),
+            Focus::Messages => self.draw_messages(f, area),
+            Focus::ReviewEdit => self.draw_review_edit(f),
+            Focus::Duplicate => self.draw_duplicate(f),
+            Focus::Tags => self.draw_tags(f),
+            Focus::Notebooks => self.draw_notebooks(f),
+            Focus::Attachments => self.draw_attachments(f),
+            Focus::Revisions => self.draw_revisions(f),
+            Focus::Lock => {
+                self.draw_lock(f);
+                // nothing else, the status bar included
+                return;
+            }
+            Focus::Scratch => self.draw_scratch(f),
+            Focus::ReviewPeriod => self.draw_review_period(f),
+            Focus::Review => self.draw_review(f, area),
+            Focus::Calendar => self.draw_calendar(f),
+            Focus::Urls => self.draw_urls(f),
+            Focus::Colors => self.draw_colors(f),
+            Focus::Templates => self.draw_templates(f),
+            Focus::Stats => self.draw_stats(f),
+            Focus::Topics => self.draw_topics(f),
+            Focus::SplitNote => self.draw_split_note(f),
+            Focus::Checkboxes => self.draw_checkboxes(f),
+        }
+        self.draw_bar(f, bar_area);
+        self.draw_toasts(f, area);
+    }
+
+    fn draw_feed(&mut self, f: &mut Frame, area: Rect) {
+        let config = self.config;
+        let strings = self.strings;
+        let theme = &self.config.theme;
+        let App {
+            feed,
+            feed_view,
+            trash_view,
+            focus,
+            state,
+            trash_state,
+            filter,
+            marked,
+            relative_dates,
+            split,
+            compact,
+            cards,
+            card_heights,
+            card_heights_edits,
+            unread,
+            ..
+        } = self;
+        let (view, list_state) = if *focus == Focus::Trash {
+            (&trash_view, trash_state)
+        } else {
+            (&feed_view, state)
+        };
+
+        // borrowed, as the list only builds the cards in sight
+        let notes = &feed.notes;
+        let refs = &view.refs;
+        let depths = &view.depths;
+        let headers = &view.headers;
+        let highlighter = if *focus == Focus::Trash {
+            None
+        } else {
+            Query::parse(filter).ok().and_then(|q| q.highlighter())
+        };
+        let now = Local::now();
+        let relative = relative_dates;
+        let marked = &marked;
+        let unread =
+            if *focus == Focus::Trash { &HashSet::new() } else { unread };
+        if *split {
+            let [list_area, preview_area] = Layout::horizontal([
+                Constraint::Percentage(40),
+                Constraint::Min(0),
+            ])
+            .areas(area);
+            let date = |d| {
+                timefmt::label(
+                    d,
+                    now,
+                    *relative,
+                    theme.date_format(),
+                    theme.clock,
+                )
+            };
+            let builder = ListBuilder::new(|context| {
+                let note = &notes[refs[context.index]];
+                let indent = depths
+                    .get(context.index)
+                    .map_or(0, |&d| (d as u16 * 2).min(20));
+                let mut item = Paragraph::new(compact_line(
+                    note,
+                    date(note.date),
+                    marked.contains(&note.id),
+                ));
+                if context.is_selected {
+                    item = item.style(Style::default().bg(theme.selection_bg));
+                }
+                (Card { indent, header: Vec::new(), body: item }, 1)
+            });
+            let block = Block::bordered().border_type(theme.border_type);
+            let inner = block.inner(list_area);
+            f.render_stateful_widget(
+                ListView::new(builder, refs.len())
+                    .block(block)
+                    .infinite_scrolling(false),
+                list_area,
+                list_state,
+            );
+            *cards = card_areas(inner, list_state, refs.len(), |_| 1);
+
+            let mut block = Block::bordered()
+                .border_type(theme.border_type)
+                .padding(Padding::horizontal(1));
+            let mut text = Text::default();
+            if let Some(k) = list_state.selected {
+                let note = &notes[refs[k]];
+                let mut title = date(note.date);
+                if let Some(modified) = note.modified {
+                    title += &(strings.edited)(&date(modified));
+                }
+                block = block.title(title);
+                if let Some(color) = note.color {
+                    block = block
+                        .border_style(Style::default().fg(color_of(color)));
+                }
+                if !note.tags.is_empty() {
+                    block = block.title_bottom(tag_line(&note.tags));
+                }
+                text = highlight(&note.text, highlighter.as_ref(), None);
+                if config.titles {
+                    text = bold_title(text, &note.text);
+                }
+            }
+            f.render_widget(
+                Paragraph::new(text).wrap(Wrap { trim: false }).block(block),
+                preview_area,
+            );
+        } else {
+            let [_, center_area, _] = Layout::horizontal([
+                Constraint::Min(0),
+                Constraint::Length(config.layout.feed_width),
+                Constraint::Min(0),
+            ])
+            .areas(area);
+            let [center_area, scrollbar_area] =
+                Layout::horizontal([Constraint::Min(0), Constraint::Length(1)])
+                    .areas(center_area);
+            f.render_stateful_widget(
+                Scrollbar::new(ScrollbarOrientation::VerticalRight),
+                scrollbar_area,
+                &mut ScrollbarState::new(view.refs.len())
+                    .position(list_state.selected.unwrap_or(0)),
+            );
+
+            if *card_heights_edits != feed.edits {
+                card_heights.get_mut().clear();
+                *card_heights_edits = feed.edits;
+            }
+            let indent = |index: usize| {
+                depths.get(index).map_or(0, |&d| (d as u16 * 4).min(40))
+            };
+            let header = |index: usize| {
+                let mut header = Vec::new();
+                // between the notes added since the last session
+                // and the others
+                let new = |k: usize| unread.contains(&notes[refs[k]].id);
+                if index > 0 && new(index) != new(index - 1) {
+                    header.push(unread_divider(
+                        new(index - 1),
+                        center_area.width,
+                    ));
+                }
+                header.extend(
+                    headers
+                        .get(index)
+                        .cloned()
+                        .flatten()
+                        .map(|h| day_header(&h)),
+                );
+                header
+            };
+            let card = |index: usize, selected: bool| {
+                let note = &notes[refs[index]];
+                let indent = indent(index);
+                let date = |d| {
+                    timefmt::label(
+                        d,
+                        now,
+                        *relative,
+                        theme.date_format(),
+                        theme.clock,
+                    )
+                };
+                let header = header(index);
+                if *compact {
+                    let mut item = Paragraph::new(compact_line(
+                        note,
+                        date(note.date),
+                        marked.contains(&note.id),
+                    ));
+                    if selected {
+                        item =
+                            item.style(Style::default().bg(theme.selection_bg));
+                    }
+                    let height = 1 + header.len() as u16;
+                    return (Card { indent, header, body: item }, height);
+                }
+                let mut title = String::new();
+                if marked.contains(&note.id) {
+                    title += "● ";
+                }
+                title += &date(note.date);
+                if let Some(modified) = note.modified {
+                    title += &(strings.edited)(&date(modified));
+                }
+                if !note.attachments.is_empty() {
+                    title += &format!(" 📎{}", note.attachments.len());
+                }
+                if note.attachments.iter().any(|a| attachments::is_image(a)) {
+                    title += " 🖼";
+                }
+                if note.posted.is_some() {
+                    title += " ↗";
+                }
+                if note.starred {
+                    title += " ★";
+                }
+                if let Some(deleted_at) = note.deleted_at {
+                    title += &format!(" (deleted {})", date(deleted_at));
+                }
+                let mut block = Block::bordered()
+                    .border_type(theme.border_type)
+                    .title(title)
+                    .padding(Padding::uniform(config.layout.card_padding));
+                if let Some(color) = note.color {
+                    block = block
+                        .border_style(Style::default().fg(color_of(color)));
+                }
+                if !note.tags.is_empty() {
+                    block = block.title_bottom(tag_line(&note.tags));
+                }
+                // explicit line breaks are kept and long lines wrap,
+                // the same as in the read view
+                let mut text =
+                    highlight(&note.text, highlighter.as_ref(), None);
+                if config.titles {
+                    text = bold_title(text, &note.text);
+                }
+                let body = Paragraph::new(text).wrap(Wrap { trim: false });
+                let width = center_area.width.saturating_sub(indent);
+                let height = *card_heights
+                    .borrow_mut()
+                    .entry((note.id, width))
+                    .or_insert_with(|| card_height(&body, &block, width));
+                let mut item = body.block(block);
+                if selected {
+                    item = item.style(Style::default().bg(theme.selection_bg));
+                }
+                let height = height + header.len() as u16;
+                (Card { indent, header, body: item }, height)
+            };
+            let builder = ListBuilder::new(|context| {
+                card(context.index, context.is_selected)
+            });
+
+            f.render_stateful_widget(
+                ListView::new(builder, view.refs.len())
+                    .block(Block::default())
+                    .infinite_scrolling(false),
+                center_area,
+                list_state,
+            );
+            // the cards drawn are measured by now, so only ones never in
+            // sight are built again to be
+            *cards =
+                card_areas(center_area, list_state, view.refs.len(), |i| {
+                    let header = header(i).len() as u16;
+                    if *compact {
+                        return 1 + header;
+                    }
+                    let width = center_area.width.saturating_sub(indent(i));
+                    let key = (notes[refs[i]].id, width);
+                    let height = card_heights.borrow().get(&key).copied();
+                    height.map_or_else(|| card(i, false).1, |h| h + header)
+                });
+        }
+
+        if *focus == Focus::ConfirmDelete {
+            let area = popup_area(f.area(), 40, 3, config.layout.popup_top);
+            let question =
+                (strings.confirm_delete)(targets(feed, marked, None).len());
+            f.render_widget(Clear, area);
+            f.render_widget(
+                Paragraph::new(question)
+                    .centered()
+                    .block(Block::bordered().border_type(theme.border_type)),
+                area,
+            );
+        }
+    }
+
+    fn draw_composer(&mut self, f: &mut Frame) {
+        let config = self.config;
+        let strings = self.strings;
+        let theme = &self.config.theme;
+        let App {
+            focus,
+            textarea,
+            inputmode,
+            feed_editing_mode,
+            speller,
+            suggestions,
+            suggestion_state,
+            completion,
+            vim,
+            zen,
+            ..
+        } = self;
+        let area = if *zen {
+            zen_area(f.area(), vim.text_width)
+        } else {
+            popup_area(
+                f.area(),
+                config.layout.popup_width,
+                config.layout.composer_height,
+                config.layout.popup_top,
+            )
+        };
+
+        let words: usize =
+            textarea.lines().iter().map(|l| l.split_whitespace().count()).sum();
+        let chars =
+            textarea.lines().iter().map(|l| l.chars().count()).sum::<usize>()
+                + textarea.lines().len()
+                - 1;
+        let counter = match config.char_limit {
+            Some(limit) => format!(
+                "{} {} / {}/{} {}",
+                words, strings.words, chars, limit, strings.chars
+            ),
+            None => format!(
+                "{} {} / {} {}",
+                words, strings.words, chars, strings.chars
+            ),
+        };
+        let counter_style = match config.char_limit {
+            Some(limit) if chars > limit => Style::default().fg(Color::Red),
+            _ => Style::default(),
+        };
+        let mut block = Block::bordered().border_type(theme.border_type);
+        if let Some(query) = &vim.search {
+            block = block.title_bottom(format!("/{}", query));
+        }
+        if *zen {
+            textarea.set_block(Block::new());
+        } else {
+            textarea.set_block(block.title(Line::from(vec![
+                Span::raw(format!(
+                    "{} ({}) — ",
+                    match feed_editing_mode {
+                        FeedEditingMode::Reply(_) => {
+                            strings.titles.reply
+                        }
+                        _ => strings.titles.new_note,
+                    },
+                    mode_name(strings, inputmode)
+                )),
+                Span::styled(counter, counter_style),
+            ])));
+        }
+        textarea.set_cursor_line_style(Style::default());
+        textarea.set_search_style(
+            Style::default().add_modifier(Modifier::REVERSED),
+        );
+        let cursor = if *zen && config.typewriter {
+            draw_typewriter(textarea, area, f.buffer_mut())
+        } else {
+            f.render_widget(&*textarea, area);
+            cursor_position(textarea, area)
+        };
+        let inner = textarea.block().map_or(area, |block| block.inner(area));
+        vim.width = inner.width as usize;
+        if matches!(inputmode, InputMode::Insert) {
+            if let Some(at) = cursor {
+                f.set_cursor_position(at);
+            }
+        }
+        if let Some(speller) = &speller {
+            underline_misspelled(f.buffer_mut(), inner, speller);
+        }
+        if let (Some(completion), InputMode::Insert) = (&completion, &inputmode)
+        {
+            if let Some(at) = cursor {
+                let screen = f.area();
+                draw_completion(f.buffer_mut(), screen, completion, at, theme);
+            }
+        }
+
+        if *focus == Focus::Spelling {
+            let area = popup_area(
+                f.area(),
+                config.layout.popup_width,
+                (suggestions.len() as u16 + 2).min(10),
+                config.layout.popup_top + config.layout.composer_height,
+            );
+            let items = &suggestions;
+            let builder = ListBuilder::new(|context| {
+                let mut item = Paragraph::new(items[context.index].as_str());
+                if context.is_selected {
+                    item = item.style(Style::default().bg(theme.selection_bg));
+                }
+                (item, 1)
+            });
+            f.render_widget(Clear, area);
+            f.render_stateful_widget(
+                ListView::new(builder, suggestions.len())
+                    .block(
+                        Block::bordered()
+                            .border_type(theme.border_type)
+                            .title(strings.titles.suggestions),
+                    )
+                    .infinite_scrolling(false),
+                area,
+                suggestion_state,
+            );
+        }
+    }
+
+    fn draw_prompt(&mut self, f: &mut Frame) {
+        let config = self.config;
+        let strings = self.strings;
+        let theme = &self.config.theme;
+        let App { focus, textarea, inputmode, .. } = self;
+        let area = popup_area(
+            f.area(),
+            config.layout.popup_width,
+            3,
+            config.layout.popup_top,
+        );
+
+        let title = match focus {
+            Focus::Filter => strings.titles.filtering,
+            Focus::JumpDate => strings.titles.go_to_date,
+            Focus::Attach => strings.titles.attach_file,
+            Focus::TagNotes => strings.titles.add_tag,
+            Focus::ExportNotes => strings.titles.export_to_file,
+            Focus::Command => strings.titles.command,
+            Focus::Locate => strings.titles.location,
+            _ => strings.titles.new_notebook,
+        };
+        let mut block = Block::bordered()
+            .border_type(theme.border_type)
+            .title(format!("{} ({})", title, mode_name(strings, inputmode)));
+        if *focus == Focus::Filter {
+            if let Err(e) = Query::parse(&textarea.lines().concat()) {
+                block = block.title_bottom(Span::styled(
+                    format!(" {} ", e),
+                    Style::default().fg(Color::Red),
+                ));
+            }
+        }
+        textarea.set_block(block);
+        textarea.set_cursor_line_style(Style::default());
+        f.render_widget(&*textarea, area);
+    }
+
+    fn draw_switcher(&mut self, f: &mut Frame) {
+        let config = self.config;
+        let strings = self.strings;
+        let theme = &self.config.theme;
+        let App { feed, textarea, switcher, switcher_state, .. } = self;
+        let list_height = (switcher.len() as u16).clamp(1, 10) + 2;
+        let area = popup_area(
+            f.area(),
+            config.layout.popup_width,
+            3 + list_height,
+            config.layout.popup_top,
+        );
+        let [input_area, list_area] =
+            Layout::vertical([Constraint::Length(3), Constraint::Min(0)])
+                .areas(area);
+
+        textarea.set_block(
+            Block::bordered()
+                .border_type(theme.border_type)
+                .title(strings.titles.go_to_note),
+        );
+        textarea.set_cursor_line_style(Style::default());
+        f.render_widget(&*textarea, input_area);
+
+        let notes = &feed.notes;
+        let items = &switcher;
+        let builder = ListBuilder::new(|context| {
+            let mut item = Paragraph::new(notes[items[context.index]].title());
+            if context.is_selected {
+                item = item.style(Style::default().bg(theme.selection_bg));
+            }
+            (item, 1)
+        });
+        f.render_stateful_widget(
+            ListView::new(builder, switcher.len())
+                .block(Block::bordered().border_type(theme.border_type))
+                .infinite_scrolling(false),
+            list_area,
+            switcher_state,
+        );
+    }
+
+    fn draw_read(&mut self, f: &mut Frame, area: Rect) {
+        let strings = self.strings;
+        let theme = &self.config.theme;
+        let App {
+            feed,
+            feed_view,
+            state,
+            filter,
+            relative_dates,
+            read_scroll,
+            read_link,
+            read_page,
+            ..
+        } = self;
+        let i = feed_view.refs[state.selected.unwrap()];
+        let note = &feed.notes[i];
+        let date = |d| {
+            timefmt::label(
+                d,
+                Local::now(),
+                *relative_dates,
+                theme.date_format(),
+                theme.clock,
+            )
+        };
+        let mut header = date(note.date);
+        if let Some(modified) = note.modified {
+            header += &(strings.edited)(&date(modified));
+        }
+        if let Some(writer) =
+            note.last_writer.as_deref().filter(|&w| w != model::machine())
+        {
+            header += &(strings.changed_on)(&writer);
+        }
+        let mut text = Text::from(header);
+        if !note.tags.is_empty() {
+            text.push_line(tag_line(&note.tags));
+        }
+        if let Some(location) = &note.location {
+            text.push_line(Line::styled(
+                format!("📍 {}", geo::describe(location)),
+                Style::default().fg(Color::DarkGray),
+            ));
+        }
+        text.push_line("");
+        let highlighter =
+            Query::parse(filter).ok().and_then(|q| q.highlighter());
+        let link_count = links(&note.text).len();
+        for line in highlight(
+            &note.text,
+            highlighter.as_ref(),
+            read_link.filter(|&k| k < link_count),
+        ) {
+            text.push_line(line);
+        }
+        let backlinks = feed.backlinks(i);
+        if !backlinks.is_empty() {
+            text.push_line("");
+            text.push_line(Line::styled(
+                strings.linked_from,
+                Style::default().add_modifier(Modifier::BOLD),
+            ));
+        }
+        for (k, &j) in backlinks.iter().enumerate() {
+            let mut style = link_style();
+            if *read_link == Some(link_count + k) {
+                style = style.add_modifier(Modifier::REVERSED);
+            }
+            text.push_line(Line::styled(
+                format!("← {}", feed.notes[j].title()),
+                style,
+            ));
+        }
+
+        let mut block = Block::bordered()
+            .border_type(theme.border_type)
+            .padding(Padding::horizontal(1));
+        if link_count + backlinks.len() > 0 {
+            block = block.title_bottom(format!(" {} ", strings.hints.links));
+        }
+        let inner = block.inner(area);
+        let paragraph = Paragraph::new(text).wrap(Wrap { trim: false });
+        let max_scroll = (paragraph.line_count(inner.width) as u16)
+            .saturating_sub(inner.height);
+        *read_scroll = (*read_scroll).min(max_scroll);
+        *read_page = inner.height.max(1);
+        f.render_widget(paragraph.block(block).scroll((*read_scroll, 0)), area);
+    }
+
+    fn draw_select_text(&mut self, f: &mut Frame, area: Rect) {
+        let strings = self.strings;
+        let theme = &self.config.theme;
+        let App { selection, vim, .. } = self;
+        selection.set_block(
+            Block::bordered()
+                .border_type(theme.border_type)
+                .title(format!(" {} ", strings.titles.select))
+                .title_bottom(format!(" {} ", strings.hints.select)),
+        );
+        selection.set_cursor_line_style(Style::default());
+        f.render_widget(&*selection, area);
+        vim.width = area.width.saturating_sub(2) as usize;
+    }
+
+    fn draw_messages(&mut self, f: &mut Frame, area: Rect) {
+        let strings = self.strings;
+        let theme = &self.config.theme;
+        let App { toasts, messages_scroll, .. } = self;
+        let mut text = Text::default();
+        for toast in toasts.history() {
+            let style = match toast.level {
+                Level::Info => Style::default(),
+                Level::Error => Style::default().fg(Color::Red),
+            };
+            text.push_line(Line::from(vec![
+                Span::styled(
+                    format!("{} ", toast.at.format(theme.clock.time_format())),
+                    Style::default().fg(Color::DarkGray),
+                ),
+                Span::styled(toast.text.clone(), style),
+            ]));
+        }
+        let block = Block::bordered()
+            .border_type(theme.border_type)
+            .title(format!(" {} ", strings.titles.messages))
+            .padding(Padding::horizontal(1));
+        let inner = block.inner(area);
+        let paragraph = Paragraph::new(text).wrap(Wrap { trim: false });
+        let max_scroll = (paragraph.line_count(inner.width) as u16)
+            .saturating_sub(inner.height);
+        *messages_scroll = (*messages_scroll).min(max_scroll);
+        f.render_widget(
+            paragraph.block(block).scroll((*messages_scroll, 0)),
+            area,
+        );
+    }
+
+    fn draw_review_edit(&mut self, f: &mut Frame) {
+        let config = self.config;
+        let strings = self.strings;
+        let theme = &self.config.theme;
+        let App { feed, textarea, feed_editing_mode, review_scroll, .. } = self;
+        let FeedEditingMode::Edit(i) = feed_editing_mode else {
+            return;
+        };
+        let area = popup_area(
+            f.area(),
+            config.layout.popup_width,
+            config.layout.composer_height,
+            config.layout.popup_top,
+        );
+        let block = Block::bordered()
+            .border_type(theme.border_type)
+            .title(strings.titles.save_changes)
+            .title_bottom(format!(" {} ", strings.hints.save_changes));
+        let inner = block.inner(area);
+        let paragraph = Paragraph::new(diff_text(
+            &feed.notes[*i].text,
+            &textarea.lines().join("\n"),
+        ))
+        .wrap(Wrap { trim: false });
+        let max_scroll = (paragraph.line_count(inner.width) as u16)
+            .saturating_sub(inner.height);
+        *review_scroll = (*review_scroll).min(max_scroll);
+        f.render_widget(
+            paragraph.block(block).scroll((*review_scroll, 0)),
+            area,
+        );
+    }
+
+    fn draw_duplicate(&mut self, f: &mut Frame) {
+        let config = self.config;
+        let strings = self.strings;
+        let theme = &self.config.theme;
+        let App {
+            feed,
+            textarea,
+            relative_dates,
+            review_scroll,
+            duplicate,
+            ..
+        } = self;
+        let Some(note) = feed.notes.get(*duplicate) else {
+            return;
+        };
+        let area = popup_area(
+            f.area(),
+            config.layout.popup_width,
+            config.layout.composer_height,
+            config.layout.popup_top,
+        );
+        let block = Block::bordered()
+            .border_type(theme.border_type)
+            .title(format!(
+                "{} ({})",
+                strings.titles.duplicate,
+                timefmt::label(
+                    note.date,
+                    Local::now(),
+                    *relative_dates,
+                    theme.date_format(),
+                    theme.clock,
+                )
+            ))
+            .title_bottom(format!(" {} ", strings.hints.duplicate));
+        let inner = block.inner(area);
+        let paragraph =
+            Paragraph::new(diff_text(&note.text, &textarea.lines().join("\n")))
+                .wrap(Wrap { trim: false });
+        let max_scroll = (paragraph.line_count(inner.width) as u16)
+            .saturating_sub(inner.height);
+        *review_scroll = (*review_scroll).min(max_scroll);
+        f.render_widget(
+            paragraph.block(block).scroll((*review_scroll, 0)),
+            area,
+        );
+    }
+
+    fn draw_tags(&mut self, f: &mut Frame) {
+        let config = self.config;
+        let strings = self.strings;
+        let theme = &self.config.theme;
+        let App { tags, tag_state, .. } = self;
+        let height = (tags.len() as u16 + 2).min(15);
+        let area = popup_area(
+            f.area(),
+            config.layout.popup_width,
+            height,
+            config.layout.popup_top,
+        );
+
+        let items = tags.clone();
+        let builder = ListBuilder::new(move |context| {
+            let (tag, count): &(String, usize) = &items[context.index];
+            let mut item = Paragraph::new(Line::from(vec![
+                Span::styled(format!("#{}", tag), tag_style()),
+                Span::raw(format!(" ({})", count)),
+            ]));
+            if context.is_selected {
+                item = item.style(Style::default().bg(theme.selection_bg));
+            }
+            (item, 1)
+        });
+
+        f.render_stateful_widget(
+            ListView::new(builder, tags.len())
+                .block(
+                    Block::bordered()
+                        .border_type(theme.border_type)
+                        .title(strings.titles.tags),
+                )
+                .infinite_scrolling(false),
+            area,
+            tag_state,
+        );
+    }
+
+    fn draw_notebooks(&mut self, f: &mut Frame) {
+        let config = self.config;
+        let strings = self.strings;
+        let theme = &self.config.theme;
+        let App { notebooks, notebook_state, notebook_action, marked, .. } =
+            self;
+        let height = (notebooks.len() as u16 + 2).min(15);
+        let area = popup_area(
+            f.area(),
+            config.layout.popup_width,
+            height,
+            config.layout.popup_top,
+        );
+
+        let items = notebooks.clone();
+        let builder = ListBuilder::new(move |context| {
+            let mut item = Paragraph::new(items[context.index].clone());
+            if context.is_selected {
+                item = item.style(Style::default().bg(theme.selection_bg));
+            }
+            (item, 1)
+        });
+
+        f.render_stateful_widget(
+            ListView::new(builder, notebooks.len())
+                .block(
+                    Block::bordered()
+                        .border_type(theme.border_type)
+                        .title(match notebook_action {
+                            NotebookAction::Switch => strings.titles.notebooks,
+                            NotebookAction::Move if !marked.is_empty() => {
+                                strings.titles.move_notes_to
+                            }
+                            NotebookAction::Move => strings.titles.move_note_to,
+                        })
+                        .title_bottom(format!(" {} ", strings.hints.notebooks)),
+                )
+                .infinite_scrolling(false),
+            area,
+            notebook_state,
+        );
+    }
+
+    fn draw_attachments(&mut self, f: &mut Frame) {
+        let config = self.config;
+        let strings = self.strings;
+        let theme = &self.config.theme;
+        let App { feed, feed_view, state, attachment_state, .. } = self;
+        let note = &feed.notes[feed_view.refs[state.selected.unwrap()]];
+        let height = (note.attachments.len() as u16 + 2).min(15);
+        let area = popup_area(
+            f.area(),
+            config.layout.popup_width,
+            height,
+            config.layout.popup_top,
+        );
+
+        let items = note.attachments.clone();
+        let builder = ListBuilder::new(move |context| {
+            let mut item = Paragraph::new(
+                items[context.index].to_string_lossy().to_string(),
+            );
+            if context.is_selected {
+                item = item.style(Style::default().bg(theme.selection_bg));
+            }
+            (item, 1)
+        });
+
+        f.render_stateful_widget(
+            ListView::new(builder, note.attachments.len())
+                .block(
+                    Block::bordered()
+                        .border_type(theme.border_type)
+                        .title(strings.titles.attachments)
+                        .title_bottom(format!(
+                            " {} ",
+                            strings.hints.attachments
+                        )),
+                )
+                .infinite_scrolling(false),
+            area,
+            attachment_state,
+        );
+    }
+
+    fn draw_revisions(&mut self, f: &mut Frame) {
+        let config = self.config;
+        let strings = self.strings;
+        let theme = &self.config.theme;
+        let App {
+            feed, feed_view, state, revision_state, relative_dates, ..
+        } = self;
+        let note = &feed.notes[feed_view.refs[state.selected.unwrap()]];
+        // newest first
+        let revisions: Vec<&Revision> = note.revisions.iter().rev().collect();
+        let list_height = (revisions.len() as u16).min(8) + 2;
+        let area = popup_area(
+            f.area(),
+            config.layout.popup_width,
+            list_height + config.layout.composer_height,
+            config.layout.popup_top,
+        );
+        let [list_area, preview_area] = Layout::vertical([
+            Constraint::Length(list_height),
+            Constraint::Min(0),
+        ])
+        .areas(area);
+
+        let now = Local::now();
+        let items = &revisions;
+        let builder = ListBuilder::new(|context| {
+            let revision = items[context.index];
+            let mut item = Paragraph::new(format!(
+                "{}  {}",
+                timefmt::label(
+                    revision.date,
+                    now,
+                    *relative_dates,
+                    theme.date_format(),
+                    theme.clock,
+                ),
+                revision.title()
+            ));
+            if context.is_selected {
+                item = item.style(Style::default().bg(theme.selection_bg));
+            }
+            (item, 1)
+        });
+        f.render_stateful_widget(
+            ListView::new(builder, revisions.len())
+                .block(
+                    Block::bordered()
+                        .border_type(theme.border_type)
+                        .title(strings.titles.versions)
+                        .title_bottom(format!(" {} ", strings.hints.versions)),
+                )
+                .infinite_scrolling(false),
+            list_area,
+            revision_state,
+        );
+        if let Some(k) = revision_state.selected {
+            f.render_widget(
+                Paragraph::new(revisions[k].text.as_str())
+                    .wrap(Wrap { trim: false })
+                    .block(
+                        Block::bordered()
+                            .border_type(theme.border_type)
+                            .padding(Padding::horizontal(1)),
+                    ),
+                preview_area,
+            );
+        }
+    }
+
+    fn draw_lock(&mut self, f: &mut Frame) {
+        let config = self.config;
+        let cipher = self.cipher;
+        let strings = self.strings;
+        let theme = &self.config.theme;
+        let App { lock, .. } = self;
+        let choosing = cipher.is_none() && lock.passphrase.is_none();
+        let prompt = if !choosing {
+            strings.passphrase
+        } else if lock.first.is_none() {
+            strings.choose_passphrase
+        } else {
+            strings.repeat_passphrase
+        };
+        let area = popup_area(
+            f.area(),
+            config.layout.popup_width,
+            3,
+            config.layout.popup_top,
+        );
+        let mut block =
+            Block::bordered().border_type(theme.border_type).title(format!(
+                " {} ",
+                if choosing {
+                    strings.titles.lock
+                } else {
+                    strings.titles.locked
+                }
+            ));
+        if !lock.message.is_empty() {
+            block = block.title_bottom(format!(" {} ", lock.message));
+        }
+        f.render_widget(
+            Paragraph::new(format!(
+                "{}: {}",
+                prompt,
+                "•".repeat(lock.typed.chars().count())
+            ))
+            .block(block),
+            area,
+        );
+    }
+
+    fn draw_scratch(&mut self, f: &mut Frame) {
+        let config = self.config;
+        let strings = self.strings;
+        let theme = &self.config.theme;
+        let App { scratch, vim, .. } = self;
+        let area = popup_area(
+            f.area(),
+            config.layout.popup_width,
+            config.layout.composer_height,
+            config.layout.popup_top,
+        );
+        scratch.set_block(
+            Block::bordered()
+                .border_type(theme.border_type)
+                .title(format!(" {} ", strings.titles.scratch))
+                .title_bottom(format!(" {} ", strings.hints.scratch)),
+        );
+        scratch.set_cursor_line_style(Style::default());
+        f.render_widget(&*scratch, area);
+        vim.width = area.width.saturating_sub(2) as usize;
+    }
+
+    fn draw_review_period(&mut self, f: &mut Frame) {
+        let config = self.config;
+        let strings = self.strings;
+        let theme = &self.config.theme;
+        let App { review_period_state, .. } = self;
+        let area = popup_area(
+            f.area(),
+            config.layout.popup_width,
+            REVIEW_PERIODS.len() as u16 + 2,
+            config.layout.popup_top,
+        );
+        let builder = ListBuilder::new(|context| {
+            let mut item = Paragraph::new(REVIEW_PERIODS[context.index].0);
+            if context.is_selected {
+                item = item.style(Style::default().bg(theme.selection_bg));
+            }
+            (item, 1)
+        });
+        f.render_stateful_widget(
+            ListView::new(builder, REVIEW_PERIODS.len())
+                .block(
+                    Block::bordered()
+                        .border_type(theme.border_type)
+                        .title(strings.titles.review),
+                )
+                .infinite_scrolling(false),
+            area,
+            review_period_state,
+        );
+    }
+
+    fn draw_review(&mut self, f: &mut Frame, area: Rect) {
+        let strings = self.strings;
+        let theme = &self.config.theme;
+        let App { feed, review, relative_dates, .. } = self;
+        let Some(i) = review.current(feed) else {
+            return;
+        };
+        let note = &feed.notes[i];
+        let date = |d| {
+            timefmt::label(
+                d,
+                Local::now(),
+                *relative_dates,
+                theme.date_format(),
+                theme.clock,
+            )
+        };
+        let mut header = date(note.date);
+        if let Some(modified) = note.modified {
+            header += &(strings.edited)(&date(modified));
+        }
+        let mut text = Text::from(header);
+        if !note.tags.is_empty() {
+            text.push_line(tag_line(&note.tags));
+        }
+        text.push_line("");
+        for line in highlight(&note.text, None, None) {
+            text.push_line(line);
+        }
+        let keys = match &review.tag {
+            Some(tag) => format!(" tag: #{}▏ ", tag),
+            None => format!(" {} ", strings.hints.review),
+        };
+        let block = Block::bordered()
+            .border_type(theme.border_type)
+            .title(format!(
+                " {} {}/{} ",
+                strings.titles.review,
+                review.at + 1,
+                review.notes.len()
+            ))
+            .title_bottom(keys)
+            .padding(Padding::horizontal(1));
+        f.render_widget(
+            Paragraph::new(text).wrap(Wrap { trim: false }).block(block),
+            area,
+        );
+    }
+
+    fn draw_calendar(&mut self, f: &mut Frame) {
+        let config = self.config;
+        let strings = self.strings;
+        let theme = &self.config.theme;
+        let App { feed, calendar_day, .. } = self;
+        let text = calendar(
+            *calendar_day,
+            Local::now().date_naive(),
+            config.week_start,
+            &stats::per_day(feed),
+            theme.selection_bg,
+        );
+        let area = popup_area(
+            f.area(),
+            39,
+            text.lines.len() as u16 + 2,
+            config.layout.popup_top,
+        );
+        let block = Block::bordered()
+            .border_type(theme.border_type)
+            .title(calendar_day.format(" %B %Y ").to_string())
+            .title_bottom(format!(" {} ", strings.hints.calendar))
+            .padding(Padding::horizontal(1));
+        f.render_widget(Clear, area);
+        f.render_widget(Paragraph::new(text).block(block), area);
+    }
+
+    fn draw_urls(&mut self, f: &mut Frame) {
+        let config = self.config;
+        let strings = self.strings;
+        let theme = &self.config.theme;
+        let App { feed, feed_view, state, url_state, .. } = self;
+        let text = &feed.notes[feed_view.refs[state.selected.unwrap()]].text;
+        let items: Vec<&str> =
+            urls(text).into_iter().map(|r| &text[r]).collect();
+        let height = (items.len() as u16 + 2).min(15);
+        let area = popup_area(
+            f.area(),
+            config.layout.popup_width,
+            height,
+            config.layout.popup_top,
+        );
+
+        let count = items.len();
+        let builder = ListBuilder::new(move |context| {
+            let mut item = Paragraph::new(items[context.index]);
+            if context.is_selected {
+                item = item.style(Style::default().bg(theme.selection_bg));
+            }
+            (item, 1)
+        });
+        f.render_stateful_widget(
+            ListView::new(builder, count)
+                .block(
+                    Block::bordered()
+                        .border_type(theme.border_type)
+                        .title(strings.titles.links),
+                )
+                .infinite_scrolling(false),
+            area,
+            url_state,
+        );
+    }
+
+    fn draw_colors(&mut self, f: &mut Frame) {
+        let config = self.config;
+        let strings = self.strings;
+        let theme = &self.config.theme;
+        let App { color_state, .. } = self;
+        let area = popup_area(
+            f.area(),
+            config.layout.popup_width,
+            NoteColor::ALL.len() as u16 + 3,
+            config.layout.popup_top,
+        );
+
+        let builder = ListBuilder::new(move |context| {
+            // the first entry takes the color off
+            let mut item = match context.index {
+                0 => Paragraph::new(strings.no_color),
+                k => {
+                    let color = NoteColor::ALL[k - 1];
+                    Paragraph::new(Span::styled(
+                        format!("■ {}", color.name()),
+                        Style::default().fg(color_of(color)),
+                    ))
+                }
+            };
+            if context.is_selected {
+                item = item.style(Style::default().bg(theme.selection_bg));
+            }
+            (item, 1)
+        });
+
+        f.render_stateful_widget(
+            ListView::new(builder, NoteColor::ALL.len() + 1)
+                .block(
+                    Block::bordered()
+                        .border_type(theme.border_type)
+                        .title(strings.titles.color),
+                )
+                .infinite_scrolling(false),
+            area,
+            color_state,
+        );
+    }
+
+    fn draw_templates(&mut self, f: &mut Frame) {
+        let config = self.config;
+        let strings = self.strings;
+        let theme = &self.config.theme;
+        let App { template_names, template_state, .. } = self;
+        let height = (template_names.len() as u16 + 2).min(15);
+        let area = popup_area(
+            f.area(),
+            config.layout.popup_width,
+            height,
+            config.layout.popup_top,
+        );
+
+        let items = template_names.clone();
+        let builder = ListBuilder::new(move |context| {
+            let mut item = Paragraph::new(format!(
+                "{} {}",
+                context.index + 1,
+                items[context.index]
+            ));
+            if context.is_selected {
+                item = item.style(Style::default().bg(theme.selection_bg));
+            }
+            (item, 1)
+        });
+
+        f.render_stateful_widget(
+            ListView::new(builder, template_names.len())
+                .block(
+                    Block::bordered()
+                        .border_type(theme.border_type)
+                        .title(strings.titles.templates),
+                )
+                .infinite_scrolling(false),
+            area,
+            template_state,
+        );
+    }
+
+    fn draw_stats(&mut self, f: &mut Frame) {
+        let config = self.config;
+        let strings = self.strings;
+        let theme = &self.config.theme;
+        let App { feed, .. } = self;
+        let area = popup_area(
+            f.area(),
+            config.layout.popup_width,
+            14,
+            config.layout.popup_top,
+        );
+        let block = Block::bordered()
+            .border_type(theme.border_type)
+            .title(strings.titles.stats)
+            .padding(Padding::horizontal(1));
+        // a column per week, after the weekday names
+        let weeks = block.inner(area).width.saturating_sub(4);
+
+        let today = Local::now().date_naive();
+        let stats = Stats::new(feed, today);
+        let mut text = Text::default();
+        for line in stats.summary() {
+            text.push_line(line);
+        }
+        text.push_line("");
+        for row in stats.heatmap(today, weeks as usize, config.week_start) {
+            text.push_line(Line::styled(
+                row,
+                Style::default().fg(Color::Green),
+            ));
+        }
+        f.render_widget(Clear, area);
+        f.render_widget(Paragraph::new(text).block(block), area);
+    }
+
+    fn draw_topics(&mut self, f: &mut Frame) {
+        let config = self.config;
+        let strings = self.strings;
+        let theme = &self.config.theme;
+        let App { feed, topics_period, topics_start, .. } = self;
+        let area = popup_area(
+            f.area(),
+            config.layout.popup_width,
+            16,
+            config.layout.popup_top,
+        );
+        let block = Block::bordered()
+            .border_type(theme.border_type)
+            .title(format!(" {} ", topics_period.name(*topics_start)))
+            .title_bottom(format!(" {} ", strings.hints.topics))
+            .padding(Padding::horizontal(1));
+        let inner = block.inner(area);
+        f.render_widget(Clear, area);
+        f.render_widget(block, area);
+
+        let topics =
+            Topics::new(feed, *topics_start, topics_period.next(*topics_start));
+        let [count_area, _, columns] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Min(0),
+        ])
+        .areas(inner);
+        f.render_widget(
+            Paragraph::new((strings.notes)(topics.notes)),
+            count_area,
+        );
+        let [words_area, tags_area] = Layout::horizontal([
+            Constraint::Percentage(60),
+            Constraint::Percentage(40),
+        ])
+        .spacing(2)
+        .areas(columns);
+        let column = |title: &str, counts: &[(String, usize)], area: Rect| {
+            let width = area.width as usize;
+            let mut text = Text::from(Line::styled(
+                title.to_string(),
+                Style::default().add_modifier(Modifier::BOLD),
+            ));
+            for (name, n) in
+                counts.iter().take((area.height as usize).saturating_sub(1))
+            {
+                let n = n.to_string();
+                let name: String = name
+                    .chars()
+                    .take(width.saturating_sub(n.len() + 1))
+                    .collect();
+                text.push_line(format!(
+                    "{}{:>w$}",
+                    name,
+                    n,
+                    w = width.saturating_sub(name.chars().count())
+                ));
+            }
+            Paragraph::new(text)
+        };
+        f.render_widget(
+            column(strings.topic_words, &topics.words, words_area),
+            words_area,
+        );
+        f.render_widget(
+            column(strings.topic_tags, &topics.tags, tags_area),
+            tags_area,
+        );
+    }
+
+    fn draw_split_note(&mut self, f: &mut Frame) {
+        let config = self.config;
+        let strings = self.strings;
+        let theme = &self.config.theme;
+        let App { feed, feed_view, state, split_line_state, .. } = self;
+        let note = &feed.notes[feed_view.refs[state.selected.unwrap()]];
+        let lines: Vec<&str> = note.text.lines().collect();
+        let area = popup_area(
+            f.area(),
+            config.layout.popup_width,
+            (lines.len() as u16 + 2).min(15),
+            config.layout.popup_top,
+        );
+
+        let count = lines.len();
+        let builder = ListBuilder::new(|context| {
+            let mut item =
+                Paragraph::new(highlight(lines[context.index], None, None));
+            if context.is_selected {
+                item = item.style(Style::default().bg(theme.selection_bg));
+            }
+            (item, 1)
+        });
+
+        f.render_stateful_widget(
+            ListView::new(builder, count)
+                .block(
+                    Block::bordered()
+                        .border_type(theme.border_type)
+                        .title(strings.titles.split)
+                        .title_bottom(format!(" {} ", strings.hints.split)),
+                )
+                .infinite_scrolling(false),
+            area,
+            split_line_state,
+        );
+    }
+
+    fn draw_checkboxes(&mut self, f: &mut Frame) {
+        let config = self.config;
+        let strings = self.strings;
+        let theme = &self.config.theme;
+        let App { feed, feed_view, state, checkbox_state, .. } = self;
+        let note = &feed.notes[feed_view.refs[state.selected.unwrap()]];
+        let lines: Vec<String> =
+            note.text.lines().map(str::to_string).collect();
+        let items = note.checkboxes();
+        let height = (items.len() as u16 + 2).min(15);
+        let area = popup_area(
+            f.area(),
+            config.layout.popup_width,
+            height,
+            config.layout.popup_top,
+        );
+
+        let count = items.len();
+        let builder = ListBuilder::new(move |context| {
+            let line = &lines[items[context.index]];
+            let mut item =
+                Paragraph::new(highlight(line.trim_start(), None, None));
+            if context.is_selected {
+                item = item.style(Style::default().bg(theme.selection_bg));
+            }
+            (item, 1)
+        });
+
+        f.render_stateful_widget(
+            ListView::new(builder, count)
+                .block(
+                    Block::bordered()
+                        .border_type(theme.border_type)
+                        .title(strings.titles.toggle)
+                        .title_bottom(format!(" {} ", strings.hints.toggle)),
+                )
+                .infinite_scrolling(false),
+            area,
+            checkbox_state,
+        );
+    }
+
+    /// The status bar.
+    fn draw_bar(&mut self, f: &mut Frame, bar_area: Rect) {
+        let readonly = self.readonly;
+        let strings = self.strings;
+        let App {
+            feed,
+            data_file,
+            feed_view,
+            trash_view,
+            focus,
+            state,
+            trash_state,
+            textarea,
+            filter,
+            inputmode,
+            feed_editing_mode,
+            toasts,
+            vim,
+            chords,
+            marked,
+            saver,
+            ..
+        } = self;
+        let mut left = vec![Span::styled(
+            format!(" {} ", mode_label(strings, focus, inputmode)),
+            Style::default().add_modifier(Modifier::REVERSED),
+        )];
+        if readonly {
+            left.push(Span::styled(
+                format!(" {} ", strings.labels.read_only),
+                Style::default().fg(Color::Black).bg(Color::Yellow),
+            ));
+        }
+        if !filter.is_empty() {
+            left.push(Span::raw(format!(" /{}", filter)));
+        }
+        if !marked.is_empty() {
+            left.push(Span::raw(format!(
+                " {}",
+                (strings.marked)(marked.len())
+            )));
+        }
+        if *focus == Focus::Trash && toasts.shown(1).is_empty() {
+            left.push(Span::raw(format!(" {}", strings.hints.trash)));
+        }
+        f.render_widget(Paragraph::new(Line::from(left)), bar_area);
+
+        let (len, selected) = if *focus == Focus::Trash {
+            (trash_view.refs.len(), trash_state.selected)
+        } else {
+            (feed_view.refs.len(), state.selected)
+        };
+        let position = match selected {
+            Some(i) => (i + 1).to_string(),
+            None => "-".into(),
+        };
+        let total =
+            feed.notes.iter().filter(|n| n.deleted_at.is_none()).count();
+        let original = match feed_editing_mode {
+            FeedEditingMode::New | FeedEditingMode::Reply(_) => "",
+            FeedEditingMode::Edit(i) => feed.notes[*i].text.as_str(),
+        };
+        let saved = !saver.unsaved()
+            && (*focus != Focus::NewNote
+                || textarea.lines().join("\n") == original);
+        // the keys of a command typed so far
+        let mut typed = chords.typed() + &vim.typed();
+        if !typed.is_empty() {
+            typed += "   ";
+        }
+        f.render_widget(
+            Paragraph::new(format!(
+                "{}{}/{} · {} [{}] {} ",
+                typed,
+                position,
+                len,
+                (strings.notes)(total),
+                store::notebook_name(data_file),
+                if saved { strings.status.saved } else { "[+]" },
+            ))
+            .right_aligned(),
+            bar_area,
+        );
+    }
+
+    /// The latest messages, stacked up from the bottom right corner.
+    fn draw_toasts(&mut self, f: &mut Frame, area: Rect) {
+        let theme = &self.config.theme;
+        let App { toasts, .. } = self;
+        // the latest messages, stacked up from the bottom right corner
+        let mut bottom = area.bottom();
+        for toast in toasts.shown(TOASTS_SHOWN).into_iter().rev() {
+            let width = (Span::raw(toast.text.as_str()).width() as u16 + 4)
+                .min(TOAST_WIDTH)
+                .min(area.width);
+            let mut block = Block::bordered()
+                .border_type(theme.border_type)
+                .padding(Padding::horizontal(1));
+            if toast.level == Level::Error {
+                block = block.border_style(Style::default().fg(Color::Red));
+            }
+            let body =
+                Paragraph::new(toast.text.as_str()).wrap(Wrap { trim: true });
+            let height = card_height(&body, &block, width);
+            if height > bottom - area.y {
+                break;
+            }
+            bottom -= height;
+            let toast_area =
+                Rect { x: area.right() - width, y: bottom, width, height };
+            f.render_widget(Clear, toast_area);
+            f.render_widget(body.block(block), toast_area);
+        }
+    }
+}
+
+// what keys, the mouse and pasting do, a method for each focus
+impl App<'_> {
+    /// Does what `event` does in the focus, `mapped` when it is a key typed by
+    /// a mapping.
+    fn handle(&mut self, event: Event, mapped: bool) {
+        // pasted text and what an input method commits in one go are taken
+        // whole, never as keys that could run commands
+        if let Event::Paste(text) = &event {
+            return self.paste(text);
+        }
+        match self.focus {
+            Focus::Feed => self.feed_event(event, mapped),
+            Focus::Stats => self.stats_event(event),
+            Focus::Topics => self.topics_event(event),
+            Focus::ConfirmDelete => self.confirm_delete_event(event),
+            Focus::Trash => self.trash_event(event, mapped),
+            Focus::Read => self.read_event(event),
+            Focus::Messages => self.messages_event(event),
+            Focus::SelectText => self.select_text_event(event),
+            Focus::Tags => self.tags_event(event),
+            Focus::Notebooks => self.notebooks_event(event),
+            Focus::Switcher => self.switcher_event(event),
+            Focus::Revisions => self.revisions_event(event),
+            Focus::ReviewPeriod => self.review_period_event(event),
+            Focus::Lock => self.lock_event(event),
+            Focus::Review => self.review_event(event),
+            Focus::Calendar => self.calendar_event(event),
+            Focus::Urls => self.urls_event(event),
+            Focus::Colors => self.colors_event(event),
+            Focus::ReviewEdit => self.review_edit_event(event),
+            Focus::Duplicate => self.duplicate_event(event),
+            Focus::Spelling => self.spelling_event(event),
+            Focus::Templates => self.templates_event(event),
+            Focus::SplitNote => self.split_note_event(event),
+            Focus::Checkboxes => self.checkboxes_event(event),
+            Focus::Attachments => self.attachments_event(event),
+            Focus::Scratch => self.scratch_event(event),
+            Focus::NewNote => self.new_note_event(event),
+            Focus::Filter
+            | Focus::JumpDate
+            | Focus::NewNotebook
+            | Focus::Attach
+            | Focus::TagNotes
+            | Focus::ExportNotes
+            | Focus::Command
+            | Focus::Locate => self.prompt_event(event),
+        }
+    }
+
+    fn paste(&mut self, text: &str) {
+        let App {
+            feed,
+            focus,
+            textarea,
+            scratch,
+            switcher,
+            switcher_state,
+            ..
+        } = self;
+        let pasted = text.replace("\r\n", "\n").replace('\r', "\n");
+        match focus {
+            Focus::NewNote => {
+                textarea.insert_str(pasted);
+            }
+            Focus::Scratch => {
+                scratch.insert_str(pasted);
+            }
+            Focus::Filter
+            | Focus::JumpDate
+            | Focus::NewNotebook
+            | Focus::Attach
+            | Focus::TagNotes
+            | Focus::ExportNotes
+            | Focus::Command
+            | Focus::Locate => {
+                textarea.insert_str(pasted.replace('\n', " "));
+            }
+            Focus::Switcher => {
+                textarea.insert_str(pasted.replace('\n', " "));
+                *switcher = switcher_matches(feed, &textarea.lines().concat());
+                switcher_state.select(Some(0));
+            }
+            _ => {}
+        }
+    }
+
+    fn feed_event(&mut self, event: Event, mapped: bool) {
+        let config = self.config;
+        let cipher = self.cipher;
+        let readonly = self.readonly;
+        let strings = self.strings;
+        let theme = &self.config.theme;
+        let keys = &self.config.keys;
+        let App {
+            feed,
+            data_file,
+            notebook_dir,
+            sort,
+            journal,
+            feed_view,
+            trash_view,
+            focus,
+            state,
+            trash_state,
+            textarea,
+            filter,
+            inputmode,
+            feed_editing_mode,
+            tags,
+            tag_state,
+            undo_stack,
+            status,
+            toasts,
+            notebooks,
+            notebook_state,
+            notebook_action,
+            attachment_state,
+            url_state,
+            calendar_day,
+            topics_period,
+            topics_start,
+            review_period_state,
+            checkbox_state,
+            split_line_state,
+            chords,
+            template_names,
+            template_state,
+            color_state,
+            revision_state,
+            switcher,
+            switcher_state,
+            marked,
+            jumps,
+            relative_dates,
+            split,
+            compact,
+            cards,
+            last_click,
+            read_scroll,
+            read_link,
+            saver,
+            read_position,
+            lock,
+            ..
+        } = self;
+        let chord = match event {
+            Event::Key(key) => match chords.key(key, mapped) {
+                Step::Run(chord) => chord,
+                Step::Pending | Step::Ignored => return,
+            },
+            Event::Mouse(mouse) => {
+                if !mouse_event(mouse, cards, state, last_click) {
+                    return;
+                }
+                chords.clear();
+                // double click
+                Chord::from(KeyEvent::from(KeyCode::Char('i')))
+            }
+            _ => return,
+        };
+        let key = chord.key;
+        status.clear();
+        if readonly && changes_notes(key, keys) {
+            *status = strings.status.read_only.into();
+            return;
+        }
+        // cards in sight, for paging
+        let page = cards.len().max(1);
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        // a count for the key after it: 3j moves down three notes,
+        // 12G goes to the twelfth and 2n starts a note from the
+        // second template
+        if let Some(count) = chord.count {
+            match key.code {
+                KeyCode::Char(c) if c == keys.next || c == keys.previous => {
+                    let by = count.min(isize::MAX as usize) as isize;
+                    let by = if c == keys.next { by } else { -by };
+                    move_by(state, feed_view.refs.len(), by);
+                }
+                KeyCode::Char('G') if !feed_view.refs.is_empty() => {
+                    let k = (count - 1).min(feed_view.refs.len() - 1);
+                    jump(state, k, feed, feed_view, jumps);
+                }
+                KeyCode::Char('n') => {
+                    let names = recover!(toasts, templates::list());
+                    let Some(name) = names.get(count - 1) else {
+                        *status = (strings.status.no_template)(count);
+                        return;
+                    };
+                    match template_textarea(name) {
+                        Ok(t) => {
+                            *textarea = t;
+                            *focus = Focus::NewNote;
+                            *feed_editing_mode = FeedEditingMode::New;
+                        }
+                        Err(e) => *status = e.to_string(),
+                    }
+                }
+                _ => {}
+            }
+            return;
+        }
+        match key.code {
+            KeyCode::Char('q') => self.quit = true,
+            KeyCode::Char(':') => {
+                *focus = Focus::Command;
+                *textarea = TextArea::default();
+                *inputmode = InputMode::Insert;
+            }
+
+            KeyCode::Char('d') | KeyCode::Char('u') if ctrl => {
+                let by = (page / 2).max(1) as isize;
+                let by = if key.code == KeyCode::Char('d') { by } else { -by };
+                move_by(state, feed_view.refs.len(), by);
+            }
+            KeyCode::Char('f') | KeyCode::Char('b') if ctrl => {
+                let by = (page - 1).max(1) as isize;
+                let by = if key.code == KeyCode::Char('f') { by } else { -by };
+                move_by(state, feed_view.refs.len(), by);
+            }
+            KeyCode::Char('p') if ctrl => {
+                *focus = Focus::Switcher;
+                *textarea = TextArea::default();
+                *switcher = switcher_matches(feed, "");
+                *switcher_state = ListState::default();
+                switcher_state.select(Some(0));
+            }
+            KeyCode::Char('o') if ctrl => {
+                // notes since filtered out or trashed are skipped
+                let back = std::iter::from_fn(|| jumps.pop()).find_map(|id| {
+                    feed_view.refs.iter().position(|&i| feed.notes[i].id == id)
+                });
+                match back {
+                    Some(k) => state.select(Some(k)),
+                    None => *status = strings.status.no_earlier_position.into(),
+                }
+            }
+            KeyCode::Char('G') if !feed_view.refs.is_empty() => {
+                let last = feed_view.refs.len() - 1;
+                jump(state, last, feed, feed_view, jumps);
+            }
+
+            KeyCode::Char(c) if c == keys.next => state.next(),
+            KeyCode::Char(c) if c == keys.previous => state.previous(),
+            KeyCode::Char(c) if keys.delete.starts_with(c) => {
+                if state.selected.is_none() || !chord.is(&keys.delete) {
+                    return;
+                }
+                if config.confirm_delete {
+                    *focus = Focus::ConfirmDelete;
+                    return;
+                }
+                *status = trash_notes(
+                    feed,
+                    targets(
+                        feed,
+                        marked,
+                        state.selected.map(|k| feed_view.refs[k]),
+                    ),
+                    undo_stack,
+                );
+                marked.clear();
+                saver.changed();
+                *feed_view = build_view(feed, filter, *sort, journal);
+                *trash_view = FeedView::trash(feed);
+                state.previous();
+                clamp(state, feed_view.refs.len());
+            }
+            KeyCode::Char('u') => {
+                match undo_stack.pop() {
+                    Some(Undo::Delete { indices }) => {
+                        for &i in &indices {
+                            feed.notes[i].deleted_at = None;
+                            feed.touch(i);
+                        }
+                        *status =
+                            (strings.status.restored_deleted)(indices.len());
+                    }
+                    Some(Undo::Edit { notes }) => {
+                        for (i, note) in notes {
+                            feed.restore(i, note);
+                        }
+                        *status = strings.status.reverted_edit.into();
+                    }
+                    Some(Undo::Split { note: (i, note), added }) => {
+                        feed.restore(i, *note);
+                        feed.notes[added].deleted_at = Some(Local::now());
+                        feed.touch(added);
+                        *status = strings.status.joined_split.into();
+                    }
+                    None => {
+                        *status = strings.status.nothing_to_undo.into();
+                        return;
+                    }
+                }
+                saver.changed();
+                *feed_view = build_view(feed, filter, *sort, journal);
+                *trash_view = FeedView::trash(feed);
+            }
+            KeyCode::Char('T') => {
+                *focus = Focus::Trash;
+                *trash_view = FeedView::trash(feed);
+                *trash_state = ListState::default();
+            }
+
+            KeyCode::Char('n') => {
+                *focus = Focus::NewNote;
+                *textarea = TextArea::default();
+                *feed_editing_mode = FeedEditingMode::New;
+            }
+            KeyCode::Char('N') => {
+                *template_names = recover!(toasts, templates::list());
+                if template_names.is_empty() {
+                    *status = (strings.status.no_templates)(
+                        &recover!(toasts, templates::dir()).display(),
+                    );
+                    return;
+                }
+                *focus = Focus::Templates;
+                *template_state = ListState::default();
+                template_state.select(Some(0));
+            }
+            KeyCode::Char('r') => {
+                let Some(selected) = state.selected else {
+                    return;
+                };
+                *focus = Focus::NewNote;
+                *textarea = TextArea::default();
+                let i = feed_view.refs[selected];
+                *feed_editing_mode = FeedEditingMode::Reply(feed.notes[i].id);
+            }
+            KeyCode::Enter | KeyCode::Char('l') => {
+                let Some(selected) = state.selected else {
+                    return;
+                };
+                *focus = Focus::Read;
+                // back where the note was left if it was the last
+                // one read
+                let id = feed.notes[feed_view.refs[selected]].id;
+                *read_scroll = match read_position {
+                    Some((read, scroll)) if *read == id => *scroll,
+                    _ => 0,
+                };
+                *read_link = None;
+            }
+            KeyCode::Char('i') => {
+                if state.selected.is_none() {
+                    return;
+                }
+                *focus = Focus::NewNote;
+                let i = feed_view.refs[state.selected.unwrap()];
+                *feed_editing_mode = FeedEditingMode::Edit(i);
+                *textarea = TextArea::new(
+                    feed.notes[i].text.lines().map(|l| l.to_string()).collect(),
+                );
+            }
+            // edit with a dated line added at the end to write under
+            KeyCode::Char('a') => {
+                let Some(selected) = state.selected else {
+                    return;
+                };
+                let i = feed_view.refs[selected];
+                let mut lines: Vec<String> =
+                    feed.notes[i].text.lines().map(|l| l.to_string()).collect();
+                let now = Local::now().format(theme.date_format());
+                lines.extend([
+                    String::new(),
+                    format!("--- {}", now),
+                    String::new(),
+                ]);
+                *focus = Focus::NewNote;
+                *feed_editing_mode = FeedEditingMode::Edit(i);
+                *textarea = TextArea::new(lines);
+                textarea.move_cursor(CursorMove::Bottom);
+                *inputmode = InputMode::Insert;
+            }
+            KeyCode::Char('`') => {
+                *focus = Focus::Scratch;
+                *inputmode = InputMode::Normal;
+            }
+            KeyCode::Char('/') => {
+                *focus = Focus::Filter;
+                *textarea = TextArea::new(vec![filter.clone()]);
+                textarea.move_cursor(CursorMove::End);
+                *inputmode = InputMode::Insert;
+            }
+            KeyCode::Char('y') => {
+                let Some(selected) = state.selected else {
+                    return;
+                };
+                match chord.rest.as_str() {
+                    "y" => {
+                        let targets = targets(
+                            feed,
+                            marked,
+                            Some(feed_view.refs[selected]),
+                        );
+                        let texts: Vec<&str> = targets
+                            .iter()
+                            .map(|&i| feed.notes[i].text.as_str())
+                            .collect();
+                        recover!(toasts, clipboard::copy(&texts.join("\n\n")));
+                        *status = (strings.status.copied_notes)(targets.len());
+                    }
+                    // a new note starting as a copy of this one
+                    "p" if readonly => {
+                        *status = strings.status.read_only.into();
+                    }
+                    "p" => {
+                        let i = feed_view.refs[selected];
+                        *textarea = TextArea::new(
+                            feed.notes[i]
+                                .text
+                                .lines()
+                                .map(|l| l.to_string())
+                                .collect(),
+                        );
+                        textarea.move_cursor(CursorMove::Bottom);
+                        textarea.move_cursor(CursorMove::End);
+                        *focus = Focus::NewNote;
+                        *feed_editing_mode = FeedEditingMode::New;
+                    }
+                    _ => {}
+                }
+            }
+            KeyCode::Char('g') => match chord.rest.as_str() {
+                "g" if !feed_view.refs.is_empty() => {
+                    jump(state, 0, feed, feed_view, jumps);
+                }
+                "d" => {
+                    *focus = Focus::JumpDate;
+                    *textarea = TextArea::default();
+                    textarea.set_placeholder_text("YYYY-MM-DD");
+                    *inputmode = InputMode::Insert;
+                }
+                "s" => *focus = Focus::Stats,
+                "w" => {
+                    *focus = Focus::Topics;
+                    *topics_period = Period::Week;
+                    *topics_start = topics_period
+                        .start(Local::now().date_naive(), config.week_start);
+                }
+                "r" if readonly => {
+                    *status = strings.status.read_only.into();
+                }
+                "r" => {
+                    *focus = Focus::ReviewPeriod;
+                    *review_period_state = ListState::default();
+                    review_period_state.select(Some(0));
+                }
+                "x" => {
+                    let Some(selected) = state.selected else {
+                        return;
+                    };
+                    let text = &feed.notes[feed_view.refs[selected]].text;
+                    match urls(text)[..] {
+                        [] => *status = strings.status.no_links.into(),
+                        [ref url] => {
+                            let url = &text[url.clone()];
+                            *status = match attachments::launch(url) {
+                                Ok(()) => (strings.status.opened)(&url),
+                                Err(e) => {
+                                    (strings.status.cannot_open)(&url, &e)
+                                }
+                            };
+                        }
+                        _ => {
+                            *focus = Focus::Urls;
+                            *url_state = ListState::default();
+                            url_state.select(Some(0));
+                        }
+                    }
+                }
+                _ => {}
+            },
+            KeyCode::Char('R') => *relative_dates = !*relative_dates,
+            KeyCode::Char('J') | KeyCode::Char('z') => {
+                let selected = state.selected.map(|k| feed_view.refs[k]);
+                if key.code == KeyCode::Char('J') {
+                    journal.on = !journal.on;
+                } else if let (true, Some(i)) = (journal.on, selected) {
+                    let day = feed.notes[i].date.date_naive();
+                    if !journal.collapsed.remove(&day) {
+                        journal.collapsed.insert(day);
+                    }
+                } else {
+                    return;
+                }
+                *feed_view = build_view(feed, filter, *sort, journal);
+                // keep the same note selected, or the first note of
+                // its day once that is collapsed
+                state.select(selected.and_then(|i| {
+                    let day = feed.notes[i].date.date_naive();
+                    feed_view.refs.iter().position(|&j| j == i).or_else(|| {
+                        feed_view.refs.iter().position(|&j| {
+                            feed.notes[j].date.date_naive() == day
+                        })
+                    })
+                }));
+            }
+            KeyCode::Char('{') | KeyCode::Char('}') => {
+                let Some(selected) = state.selected else {
+                    return;
+                };
+                let forward = key.code == KeyCode::Char('}');
+                if let Some(k) = feed_view.day_start(feed, selected, forward) {
+                    state.select(Some(k));
+                }
+            }
+            KeyCode::Char('s') => {
+                *sort = sort.next();
+                *feed_view = build_view(feed, filter, *sort, journal);
+                state.select(if feed_view.refs.is_empty() {
+                    None
+                } else {
+                    Some(0)
+                });
+                let saved =
+                    if readonly { Ok(()) } else { Config::save_sort(*sort) };
+                *status = match saved {
+                    Ok(()) => (strings.status.sorted)(&sort.describe()),
+                    Err(e) => {
+                        (strings.status.sort_not_saved)(&sort.describe(), &e)
+                    }
+                };
+            }
+            KeyCode::Char('D') => *compact = !*compact,
+            // stars the notes unless they all are already
+            KeyCode::Char('*') => {
+                let targets = targets(
+                    feed,
+                    marked,
+                    state.selected.map(|k| feed_view.refs[k]),
+                );
+                if targets.is_empty() {
+                    return;
+                }
+                let star = targets.iter().any(|&i| !feed.notes[i].starred);
+                let mut before = Vec::new();
+                for &i in &targets {
+                    if feed.notes[i].starred == star {
+                        continue;
+                    }
+                    before.push((i, feed.notes[i].clone()));
+                    feed.notes[i].starred = star;
+                    feed.touch(i);
+                }
+                undo_stack.push(Undo::Edit { notes: before });
+                marked.clear();
+                saver.changed();
+                *feed_view = build_view(feed, filter, *sort, journal);
+                clamp(state, feed_view.refs.len());
+                *status = (strings.status.starred)(targets.len(), star);
+            }
+            KeyCode::Char('\'') => {
+                *filter = if filter == "is:starred" {
+                    String::new()
+                } else {
+                    "is:starred".into()
+                };
+                *feed_view = build_view(feed, filter, *sort, journal);
+                if feed_view.refs.is_empty() {
+                    *status = strings.status.no_starred.into();
+                    state.select(None);
+                } else {
+                    state.select(Some(0));
+                }
+            }
+            KeyCode::Char('L') => {
+                lock.choose_only = false;
+                lock.from = Some(std::mem::replace(focus, Focus::Lock));
+            }
+            KeyCode::Char('P') => {
+                *split = !*split;
+                let saved =
+                    if readonly { Ok(()) } else { Config::save_split(*split) };
+                if let Err(e) = saved {
+                    toasts.error(format!(
+                        "{}: {}",
+                        strings.status.saving_layout_failed, e
+                    ));
+                }
+            }
+            KeyCode::Char('S') => {
+                recover!(toasts, saver.flush(data_file, feed));
+                match cli::sync_notebook(data_file, &config.webdav, cipher) {
+                    Ok(conflicts) => {
+                        **feed =
+                            recover!(toasts, store::load(data_file, cipher));
+                        let purged = feed.purge_trash(TRASH_RETENTION_DAYS);
+                        if let Err(e) = attachments::remove_orphans(
+                            data_file, feed, &purged,
+                        ) {
+                            toasts.error(e.to_string());
+                        }
+                        // the pulled feed may order notes differently
+                        undo_stack.clear();
+                        marked.clear();
+                        *feed_view = build_view(feed, filter, *sort, journal);
+                        *trash_view = FeedView::trash(feed);
+                        state.select(if feed_view.refs.is_empty() {
+                            None
+                        } else {
+                            Some(0)
+                        });
+                        *status = (strings.status.synced)(conflicts);
+                    }
+                    Err(e) => toasts.error(e.to_string()),
+                }
+            }
+            KeyCode::Char('b') | KeyCode::Char('m') => {
+                *notebook_action = if key.code == KeyCode::Char('b') {
+                    NotebookAction::Switch
+                } else if state.selected.is_some() {
+                    NotebookAction::Move
+                } else {
+                    return;
+                };
+                let current = store::notebook_name(data_file);
+                *notebooks = recover!(toasts, store::notebooks(notebook_dir));
+                if !notebooks.contains(&current) {
+                    notebooks.push(current.clone());
+                    notebooks.sort();
+                }
+                *focus = Focus::Notebooks;
+                *notebook_state = ListState::default();
+                notebook_state
+                    .select(notebooks.iter().position(|n| *n == current));
+            }
+            KeyCode::Char('v') => {
+                let Some(selected) = state.selected else {
+                    return;
+                };
+                let id = feed.notes[feed_view.refs[selected]].id;
+                if !marked.remove(&id) {
+                    marked.insert(id);
+                }
+                state.next();
+            }
+            KeyCode::Esc => marked.clear(),
+            KeyCode::Char('X') => {
+                let Some(selected) = state.selected else {
+                    return;
+                };
+                if feed.notes[feed_view.refs[selected]].text.lines().count() < 2
+                {
+                    *status = strings.status.single_line.into();
+                    return;
+                }
+                *focus = Focus::SplitNote;
+                *split_line_state = ListState::default();
+                split_line_state.select(Some(1));
+            }
+            KeyCode::Char('M') => {
+                let targets = targets(feed, marked, None);
+                if targets.len() < 2 {
+                    *status = strings.status.mark_to_merge.into();
+                    return;
+                }
+                undo_stack.push(Undo::Edit {
+                    notes: targets
+                        .iter()
+                        .map(|&i| (i, feed.notes[i].clone()))
+                        .collect(),
+                });
+                feed.merge(&targets);
+                marked.clear();
+                saver.changed();
+                *feed_view = build_view(feed, filter, *sort, journal);
+                *trash_view = FeedView::trash(feed);
+                clamp(state, feed_view.refs.len());
+                *status = (strings.status.merged)(targets.len());
+            }
+            KeyCode::Char('@') => {
+                let Some(selected) = state.selected else {
+                    return;
+                };
+                let location = &feed.notes[feed_view.refs[selected]].location;
+                let current = match location {
+                    Some(l) => l
+                        .name
+                        .clone()
+                        .unwrap_or_else(|| format!("{}, {}", l.lat, l.lon)),
+                    None => String::new(),
+                };
+                *focus = Focus::Locate;
+                *textarea = TextArea::new(vec![current]);
+                textarea.move_cursor(CursorMove::End);
+                textarea.set_placeholder_text(
+                    "a place, lat, lon or here; empty to remove",
+                );
+                *inputmode = InputMode::Insert;
+            }
+            KeyCode::Char('#') | KeyCode::Char('E') => {
+                if state.selected.is_none() {
+                    return;
+                }
+                *focus = if key.code == KeyCode::Char('#') {
+                    Focus::TagNotes
+                } else {
+                    Focus::ExportNotes
+                };
+                *textarea = TextArea::default();
+                *inputmode = InputMode::Insert;
+                // a single note is exported to a file named after
+                // its title
+                if *focus == Focus::ExportNotes
+                    && config.titles
+                    && marked.is_empty()
+                {
+                    let i = feed_view.refs[state.selected.unwrap()];
+                    textarea.insert_str(format!(
+                        "{}.json",
+                        export::file_stem(&feed.notes[i])
+                    ));
+                }
+            }
+            KeyCode::Char('p') => {
+                let Some(selected) = state.selected else {
+                    return;
+                };
+                let i = feed_view.refs[selected];
+                if let Some(url) = &feed.notes[i].posted {
+                    *status = (strings.status.already_posted)(url);
+                    return;
+                }
+                *status = match mastodon::post(
+                    &config.mastodon,
+                    &store::notebook_name(data_file),
+                    &feed.notes[i],
+                ) {
+                    Ok(url) => {
+                        let message = (strings.status.posted)(&url);
+                        feed.notes[i].posted = Some(url);
+                        feed.touch(i);
+                        saver.changed();
+                        message
+                    }
+                    Err(e) => {
+                        format!("{}: {}", strings.status.posting_failed, e)
+                    }
+                };
+            }
+            KeyCode::Char('C') => {
+                *calendar_day = match state.selected {
+                    Some(k) => feed.notes[feed_view.refs[k]].date.date_naive(),
+                    None => Local::now().date_naive(),
+                };
+                *focus = Focus::Calendar;
+            }
+            KeyCode::Char('H') => {
+                let Some(selected) = state.selected else {
+                    return;
+                };
+                let note = &feed.notes[feed_view.refs[selected]];
+                if note.revisions.is_empty() {
+                    *status = strings.status.never_edited.into();
+                    return;
+                }
+                *focus = Focus::Revisions;
+                *revision_state = ListState::default();
+                revision_state.select(Some(0));
+            }
+            KeyCode::Char('c') => {
+                let Some(selected) = state.selected else {
+                    return;
+                };
+                let color = feed.notes[feed_view.refs[selected]].color;
+                *focus = Focus::Colors;
+                *color_state = ListState::default();
+                color_state.select(Some(
+                    NoteColor::ALL
+                        .iter()
+                        .position(|&c| Some(c) == color)
+                        .map_or(0, |k| k + 1),
+                ));
+            }
+            KeyCode::Char('A') => {
+                if state.selected.is_none() {
+                    return;
+                }
+                *focus = Focus::Attach;
+                *textarea = TextArea::default();
+                *inputmode = InputMode::Insert;
+            }
+            KeyCode::Char('o') => {
+                let Some(selected) = state.selected else {
+                    return;
+                };
+                let note = &feed.notes[feed_view.refs[selected]];
+                match note.attachments.len() {
+                    0 => *status = strings.status.no_attachments.into(),
+                    1 => {
+                        recover!(
+                            toasts,
+                            attachments::open(data_file, &note.attachments[0],)
+                        );
+                    }
+                    _ => {
+                        *focus = Focus::Attachments;
+                        *attachment_state = ListState::default();
+                        attachment_state.select(Some(0));
+                    }
+                }
+            }
+            KeyCode::Char(' ') => {
+                let Some(selected) = state.selected else {
+                    return;
+                };
+                let i = feed_view.refs[selected];
+                match feed.notes[i].checkboxes()[..] {
+                    [] => *status = strings.status.no_checkboxes.into(),
+                    [line] => {
+                        undo_stack.push(Undo::Edit {
+                            notes: vec![(i, feed.notes[i].clone())],
+                        });
+                        feed.notes[i].toggle_checkbox(line);
+                        feed.notes[i].modified = Some(Local::now());
+                        feed.touch(i);
+                        saver.changed();
+                        *feed_view = build_view(feed, filter, *sort, journal);
+                        // ticked off the last item under todo:open
+                        if selected >= feed_view.refs.len() {
+                            state.previous();
+                        }
+                    }
+                    _ => {
+                        *focus = Focus::Checkboxes;
+                        *checkbox_state = ListState::default();
+                        checkbox_state.select(Some(0));
+                    }
+                }
+            }
+            KeyCode::Char('t') => {
+                *tags = feed.tags();
+                if tags.is_empty() {
+                    return;
+                }
+                *focus = Focus::Tags;
+                *tag_state = ListState::default();
+                tag_state.select(Some(0));
+            }
+            _ => {}
+        }
+    }
+
+    fn stats_event(&mut self, event: Event) {
+        let App { focus, .. } = self;
+        if let Event::Key(_) = event {
+            *focus = Focus::Feed;
+        }
+    }
+
+    fn topics_event(&mut self, event: Event) {
+        let config = self.config;
+        let App {
+            feed,
+            sort,
+            journal,
+            feed_view,
+            focus,
+            state,
+            filter,
+            topics_period,
+            topics_start,
+            ..
+        } = self;
+        let Event::Key(key) = event else {
+            return;
+        };
+        let today = Local::now().date_naive();
+        match key.code {
+            KeyCode::Char('l') | KeyCode::Right => {
+                *topics_start = topics_period.next(*topics_start)
+            }
+            KeyCode::Char('h') | KeyCode::Left => {
+                *topics_start = topics_period.previous(*topics_start)
+            }
+            KeyCode::Char('w') => *topics_period = Period::Week,
+            KeyCode::Char('m') => *topics_period = Period::Month,
+            KeyCode::Char('t') => *topics_start = today,
+            KeyCode::Enter => {
+                let last = topics_period.next(*topics_start).pred_opt();
+                *filter = format!(
+                    "date:{}..{}",
+                    topics_start,
+                    last.unwrap_or(*topics_start)
+                );
+                *feed_view = build_view(feed, filter, *sort, journal);
+                state.select((!feed_view.refs.is_empty()).then_some(0));
+                *focus = Focus::Feed;
+            }
+            KeyCode::Esc | KeyCode::Backspace | KeyCode::Char('q') => {
+                *focus = Focus::Feed
+            }
+            _ => {}
+        }
+        // switching between a week and a month keeps the day it
+        // starts on in the period
+        *topics_start = topics_period.start(*topics_start, config.week_start);
+    }
+
+    fn confirm_delete_event(&mut self, event: Event) {
+        let App {
+            feed,
+            sort,
+            journal,
+            feed_view,
+            trash_view,
+            focus,
+            state,
+            filter,
+            undo_stack,
+            status,
+            marked,
+            saver,
+            ..
+        } = self;
+        let Event::Key(key) = event else {
+            return;
+        };
+        *focus = Focus::Feed;
+        if !matches!(key.code, KeyCode::Char('y') | KeyCode::Char('Y')) {
+            return;
+        }
+        *status = trash_notes(
+            feed,
+            targets(feed, marked, state.selected.map(|k| feed_view.refs[k])),
+            undo_stack,
+        );
+        marked.clear();
+        saver.changed();
+        *feed_view = build_view(feed, filter, *sort, journal);
+        *trash_view = FeedView::trash(feed);
+        state.previous();
+        clamp(state, feed_view.refs.len());
+    }
+
+    fn trash_event(&mut self, event: Event, mapped: bool) {
+        let readonly = self.readonly;
+        let strings = self.strings;
+        let keys = &self.config.keys;
+        let App {
+            feed,
+            data_file,
+            sort,
+            journal,
+            feed_view,
+            trash_view,
+            focus,
+            trash_state,
+            filter,
+            undo_stack,
+            status,
+            toasts,
+            chords,
+            cards,
+            last_click,
+            saver,
+            ..
+        } = self;
+        let chord = match event {
+            Event::Key(key) => match chords.key(key, mapped) {
+                Step::Run(chord) => chord,
+                Step::Pending | Step::Ignored => return,
+            },
+            Event::Mouse(mouse) => {
+                mouse_event(mouse, cards, trash_state, last_click);
+                return;
+            }
+            _ => return,
+        };
+        status.clear();
+        match chord.key.code {
+            KeyCode::Char(c) if c == keys.next => trash_state.next(),
+            KeyCode::Char(c) if c == keys.previous => trash_state.previous(),
+            KeyCode::Char(c)
+                if readonly && (c == 'r' || keys.delete.starts_with(c)) =>
+            {
+                *status = strings.status.read_only.into()
+            }
+            KeyCode::Char('r') => {
+                let Some(selected) = trash_state.selected else {
+                    return;
+                };
+                let i = trash_view.refs[selected];
+                feed.notes[i].deleted_at = None;
+                feed.touch(i);
+                saver.changed();
+                *feed_view = build_view(feed, filter, *sort, journal);
+                *trash_view = FeedView::trash(feed);
+                trash_state.previous();
+                *status = strings.status.restored.into();
+            }
+            KeyCode::Char(c) if keys.delete.starts_with(c) => {
+                let Some(selected) = trash_state.selected else {
+                    return;
+                };
+                if chord.is(&keys.delete) {
+                    let note = feed.remove(trash_view.refs[selected]);
+                    if let Err(e) =
+                        attachments::remove_orphans(data_file, feed, &[note])
+                    {
+                        toasts.error(e.to_string());
+                    }
+                    // purging shifts the indices the undo entries
+                    // refer to
+                    undo_stack.clear();
+                    saver.changed();
+                    *feed_view = build_view(feed, filter, *sort, journal);
+                    *trash_view = FeedView::trash(feed);
+                    trash_state.previous();
+                    *status = strings.status.deleted_forever.into();
+                }
+            }
+            KeyCode::Esc
+            | KeyCode::Backspace
+            | KeyCode::Char('q')
+            | KeyCode::Char('T') => *focus = Focus::Feed,
+            _ => {}
+        }
+    }
+
+    fn read_event(&mut self, event: Event) {
+        let strings = self.strings;
+        let keys = &self.config.keys;
+        let App {
+            feed,
+            sort,
+            journal,
+            feed_view,
+            focus,
+            state,
+            selection,
+            filter,
+            inputmode,
+            status,
+            jumps,
+            read_scroll,
+            read_link,
+            read_page,
+            read_position,
+            ..
+        } = self;
+        let Event::Key(key) = event else {
+            return;
+        };
+        match key.code {
+            KeyCode::Char(c) if c == keys.next => {
+                *read_scroll = read_scroll.saturating_add(1)
+            }
+            KeyCode::Down => *read_scroll = read_scroll.saturating_add(1),
+            KeyCode::Char(c) if c == keys.previous => {
+                *read_scroll = read_scroll.saturating_sub(1)
+            }
+            KeyCode::Up => *read_scroll = read_scroll.saturating_sub(1),
+            KeyCode::Char(' ') | KeyCode::PageDown => {
+                *read_scroll = read_scroll.saturating_add(*read_page)
+            }
+            KeyCode::PageUp => {
+                *read_scroll = read_scroll.saturating_sub(*read_page)
+            }
+            KeyCode::Char('g') => *read_scroll = 0,
+            // clamped to the end of the note when drawn
+            KeyCode::Char('G') => *read_scroll = u16::MAX,
+            KeyCode::Char('v') => {
+                let i = feed_view.refs[state.selected.unwrap()];
+                *selection = TextArea::new(
+                    feed.notes[i].text.lines().map(|l| l.to_string()).collect(),
+                );
+                *focus = Focus::SelectText;
+                *inputmode = InputMode::Normal;
+            }
+            KeyCode::Tab | KeyCode::BackTab => {
+                let i = feed_view.refs[state.selected.unwrap()];
+                let count =
+                    links(&feed.notes[i].text).len() + feed.backlinks(i).len();
+                if count == 0 {
+                    return;
+                }
+                *read_link = Some(match (*read_link, key.code) {
+                    (None, KeyCode::Tab) => 0,
+                    (None, _) => count - 1,
+                    (Some(k), KeyCode::Tab) => (k + 1) % count,
+                    (Some(k), _) => (k + count - 1) % count,
+                });
+            }
+            KeyCode::Enter => {
+                let Some(k) = read_link else {
+                    return;
+                };
+                let i = feed_view.refs[state.selected.unwrap()];
+                let links = links(&feed.notes[i].text);
+                let target = match links.get(*k) {
+                    Some((_, target)) => match feed.resolve(target) {
+                        Some(j) => j,
+                        None => {
+                            *status = (strings.status.no_note_called)(&target);
+                            return;
+                        }
+                    },
+                    None => feed.backlinks(i)[*k - links.len()],
+                };
+                remember(jumps, feed.notes[i].id);
+                if !feed_view.refs.contains(&target) {
+                    filter.clear();
+                    *feed_view = build_view(feed, filter, *sort, journal);
+                }
+                state.select(feed_view.refs.iter().position(|&j| j == target));
+                *read_scroll = 0;
+                *read_link = None;
+            }
+            KeyCode::Esc
+            | KeyCode::Backspace
+            | KeyCode::Char('q')
+            | KeyCode::Char('h') => {
+                let i = feed_view.refs[state.selected.unwrap()];
+                *read_position = Some((feed.notes[i].id, *read_scroll));
+                *focus = Focus::Feed
+            }
+            _ => {}
+        }
+    }
+
+    fn messages_event(&mut self, event: Event) {
+        let keys = &self.config.keys;
+        let App { focus, messages_scroll, .. } = self;
+        let Event::Key(key) = event else {
+            return;
+        };
+        match key.code {
+            KeyCode::Char(c) if c == keys.next => {
+                *messages_scroll = messages_scroll.saturating_add(1)
+            }
+            KeyCode::Down => {
+                *messages_scroll = messages_scroll.saturating_add(1)
+            }
+            KeyCode::Char(c) if c == keys.previous => {
+                *messages_scroll = messages_scroll.saturating_sub(1)
+            }
+            KeyCode::Up => *messages_scroll = messages_scroll.saturating_sub(1),
+            KeyCode::Char('g') => *messages_scroll = 0,
+            // clamped to the last message when drawn
+            KeyCode::Char('G') => *messages_scroll = u16::MAX,
+            KeyCode::Esc | KeyCode::Backspace | KeyCode::Char('q') => {
+                *focus = Focus::Feed
+            }
+            _ => {}
+        }
+    }
+
+    fn select_text_event(&mut self, event: Event) {
+        let strings = self.strings;
+        let App { focus, selection, inputmode, status, toasts, vim, .. } = self;
+        let input: Input = event.clone().into();
+        let normal = matches!(inputmode, InputMode::Normal);
+        if vim.search.is_none() {
+            match input.key {
+                Key::Esc | Key::Backspace | Key::Char('q')
+                    if normal && vim.idle() =>
+                {
+                    *focus = Focus::Read;
+                    return;
+                }
+                // the note is only read here, so the keys that would
+                // change it do nothing
+                Key::Char(
+                    'i' | 'a' | 'A' | 'o' | 'O' | 'p' | 'x' | '>' | '<' | 'd'
+                    | 'u' | 'r' | '.' | 'q',
+                ) => return,
+                _ => {}
+            }
+        }
+        recover!(
+            toasts,
+            textarea_event(event, selection, focus, inputmode, vim,)
+        );
+        if !normal
+            && input.key == Key::Char('y')
+            && matches!(inputmode, InputMode::Normal)
+        {
+            let copied = selection.yank_text();
+            recover!(toasts, clipboard::copy(&copied));
+            *status = (strings.status.copied_chars)(copied.chars().count());
+            *focus = Focus::Read;
+        }
+    }
+
+    fn tags_event(&mut self, event: Event) {
+        let keys = &self.config.keys;
+        let App {
+            feed,
+            sort,
+            journal,
+            feed_view,
+            focus,
+            filter,
+            tags,
+            tag_state,
+            ..
+        } = self;
+        let Event::Key(key) = event else {
+            return;
+        };
+        match key.code {
+            KeyCode::Char(c) if c == keys.next => tag_state.next(),
+            KeyCode::Char(c) if c == keys.previous => tag_state.previous(),
+            KeyCode::Enter => {
+                if let Some(i) = tag_state.selected {
+                    *filter = format!("#{}", tags[i].0);
+                    *feed_view = build_view(feed, filter, *sort, journal);
+                }
+                *focus = Focus::Feed;
+            }
+            KeyCode::Esc | KeyCode::Backspace | KeyCode::Char('q') => {
+                *focus = Focus::Feed
+            }
+            _ => {}
+        }
+    }
+
+    fn notebooks_event(&mut self, event: Event) {
+        let readonly = self.readonly;
+        let strings = self.strings;
+        let keys = &self.config.keys;
+        let App {
+            focus,
+            textarea,
+            inputmode,
+            status,
+            notebooks,
+            notebook_state,
+            picked_notebook,
+            ..
+        } = self;
+        let Event::Key(key) = event else {
+            return;
+        };
+        match key.code {
+            KeyCode::Char(c) if c == keys.next => notebook_state.next(),
+            KeyCode::Char(c) if c == keys.previous => notebook_state.previous(),
+            KeyCode::Enter => {
+                *picked_notebook =
+                    notebook_state.selected.map(|i| notebooks[i].clone());
+                *focus = Focus::Feed;
+            }
+            KeyCode::Char('n') if readonly => {
+                *focus = Focus::Feed;
+                *status = strings.status.read_only.into();
+            }
+            KeyCode::Char('n') => {
+                *focus = Focus::NewNotebook;
+                *textarea = TextArea::default();
+                *inputmode = InputMode::Insert;
+            }
+            KeyCode::Esc | KeyCode::Backspace | KeyCode::Char('q') => {
+                *focus = Focus::Feed
+            }
+            _ => {}
+        }
+    }
+
+    fn switcher_event(&mut self, event: Event) {
+        let App {
+            feed,
+            sort,
+            journal,
+            feed_view,
+            focus,
+            state,
+            textarea,
+            filter,
+            switcher,
+            switcher_state,
+            jumps,
+            ..
+        } = self;
+        let Event::Key(key) = event else {
+            return;
+        };
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Down | KeyCode::Tab => switcher_state.next(),
+            KeyCode::Char('n') if ctrl => switcher_state.next(),
+            KeyCode::Up | KeyCode::BackTab => switcher_state.previous(),
+            KeyCode::Char('p') if ctrl => switcher_state.previous(),
+            KeyCode::Enter => {
+                let Some(&i) =
+                    switcher_state.selected.and_then(|k| switcher.get(k))
+                else {
+                    return;
+                };
+                *focus = Focus::Feed;
+                // the switcher goes to notes the filter hides too
+                if !feed_view.refs.contains(&i) {
+                    filter.clear();
+                    *feed_view = build_view(feed, filter, *sort, journal);
+                }
+                if let Some(k) = feed_view.refs.iter().position(|&j| j == i) {
+                    jump(state, k, feed, feed_view, jumps);
+                }
+            }
+            KeyCode::Esc => *focus = Focus::Feed,
+            _ => {
+                textarea.input(key);
+                *switcher = switcher_matches(feed, &textarea.lines().concat());
+                switcher_state.select(Some(0));
+            }
+        }
+    }
+
+    fn revisions_event(&mut self, event: Event) {
+        let readonly = self.readonly;
+        let strings = self.strings;
+        let theme = &self.config.theme;
+        let keys = &self.config.keys;
+        let App {
+            feed,
+            sort,
+            journal,
+            feed_view,
+            focus,
+            state,
+            filter,
+            undo_stack,
+            status,
+            revision_state,
+            saver,
+            ..
+        } = self;
+        let Event::Key(key) = event else {
+            return;
+        };
+        match key.code {
+            KeyCode::Char(c) if c == keys.next => revision_state.next(),
+            KeyCode::Char(c) if c == keys.previous => revision_state.previous(),
+            KeyCode::Enter if readonly => {
+                *status = strings.status.read_only.into()
+            }
+            KeyCode::Enter => {
+                let Some(k) = revision_state.selected else {
+                    return;
+                };
+                let i = feed_view.refs[state.selected.unwrap()];
+                let revisions = &feed.notes[i].revisions;
+                let revision = &revisions[revisions.len() - 1 - k];
+                *status = (strings.status.restored_version)(
+                    &revision.date.format(theme.date_format()),
+                );
+                edit_note(feed, i, revision.text.clone(), undo_stack);
+                saver.changed();
+                *feed_view = build_view(feed, filter, *sort, journal);
+                clamp(state, feed_view.refs.len());
+                *focus = Focus::Feed;
+            }
+            KeyCode::Esc | KeyCode::Backspace | KeyCode::Char('q') => {
+                *focus = Focus::Feed
+            }
+            _ => {}
+        }
+    }
+
+    fn review_period_event(&mut self, event: Event) {
+        let strings = self.strings;
+        let keys = &self.config.keys;
+        let App {
+            feed,
+            feed_view,
+            focus,
+            status,
+            review_period_state,
+            review,
+            ..
+        } = self;
+        let Event::Key(key) = event else {
+            return;
+        };
+        match key.code {
+            KeyCode::Char(c) if c == keys.next => review_period_state.next(),
+            KeyCode::Char(c) if c == keys.previous => {
+                review_period_state.previous()
+            }
+            KeyCode::Enter => {
+                let Some(k) = review_period_state.selected else {
+                    return;
+                };
+                let notes: Vec<NoteId> = match REVIEW_PERIODS[k].1 {
+                    Some(days) => {
+                        let since = Local::now() - Days::new(days);
+                        let mut notes: Vec<&Note> = feed
+                            .notes
+                            .iter()
+                            .filter(|n| {
+                                n.deleted_at.is_none() && n.date >= since
+                            })
+                            .collect();
+                        notes.sort_by_key(|n| n.date);
+                        notes.iter().map(|n| n.id).collect()
+                    }
+                    None => feed_view
+                        .refs
+                        .iter()
+                        .map(|&i| feed.notes[i].id)
+                        .collect(),
+                };
+                if notes.is_empty() {
+                    *status =
+                        (strings.status.no_notes_from)(&REVIEW_PERIODS[k].0);
+                    *focus = Focus::Feed;
+                    return;
+                }
+                *review = Review { notes, ..Review::default() };
+                *focus = Focus::Review;
+            }
+            KeyCode::Esc | KeyCode::Backspace | KeyCode::Char('q') => {
+                *focus = Focus::Feed
+            }
+            _ => {}
+        }
+    }
+
+    fn lock_event(&mut self, event: Event) {
+        let cipher = self.cipher;
+        let strings = self.strings;
+        let App { focus, status, lock, .. } = self;
+        let Event::Key(key) = event else {
+            return;
+        };
+        let choosing = cipher.is_none() && lock.passphrase.is_none();
+        match key.code {
+            KeyCode::Char(c)
+                if !key.modifiers.contains(KeyModifiers::CONTROL) =>
+            {
+                lock.typed.push(c);
+            }
+            KeyCode::Backspace => {
+                lock.typed.pop();
+            }
+            KeyCode::Esc if choosing => {
+                lock.first = None;
+                lock.typed.clear();
+                lock.message = "";
+                *focus = lock.from.take().unwrap_or(Focus::Feed);
+                *status = strings.status.not_locking.into();
+            }
+            KeyCode::Esc => lock.typed.clear(),
+            KeyCode::Enter => {
+                let typed = std::mem::take(&mut lock.typed);
+                if choosing {
+                    match lock.first.take() {
+                        _ if typed.is_empty() => {
+                            lock.message = strings.status.empty_passphrase
+                        }
+                        None => {
+                            lock.first = Some(typed);
+                            lock.message = "";
+                        }
+                        Some(first) if first != typed => {
+                            lock.message = strings.status.passphrases_differ
+                        }
+                        Some(_) => {
+                            lock.passphrase = Some(typed);
+                            lock.message = "";
+                            if lock.choose_only {
+                                *focus =
+                                    lock.from.take().unwrap_or(Focus::Feed);
+                            }
+                        }
+                    }
+                } else if lock.unlocks(&typed, cipher) {
+                    lock.message = "";
+                    *focus = lock.from.take().unwrap_or(Focus::Feed);
+                } else {
+                    lock.message = strings.status.wrong_passphrase;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn review_event(&mut self, event: Event) {
+        let config = self.config;
+        let cipher = self.cipher;
+        let strings = self.strings;
+        let keys = &self.config.keys;
+        let App {
+            feed,
+            data_file,
+            notebook_dir,
+            sort,
+            journal,
+            feed_view,
+            trash_view,
+            focus,
+            state,
+            filter,
+            undo_stack,
+            status,
+            toasts,
+            review,
+            saver,
+            ..
+        } = self;
+        let Event::Key(key) = event else {
+            return;
+        };
+        let Some(i) = review.current(feed) else {
+            *focus = Focus::Feed;
+            return;
+        };
+        if let Some(tag) = &mut review.tag {
+            match key.code {
+                KeyCode::Char(c) => tag.push(c),
+                KeyCode::Backspace => {
+                    tag.pop();
+                }
+                KeyCode::Esc => review.tag = None,
+                KeyCode::Enter => {
+                    let tag = tag.trim().trim_start_matches('#');
+                    if extract_tags(&format!("#{}", tag)) != [tag] {
+                        *status = (strings.status.invalid_tag)(&tag);
+                        return;
+                    }
+                    if !feed.notes[i].tags.iter().any(|t| t == tag) {
+                        undo_stack.push(Undo::Edit {
+                            notes: vec![(i, feed.notes[i].clone())],
+                        });
+                        let note = &mut feed.notes[i];
+                        note.set_text(append_tag(&note.text, tag));
+                        feed.touch(i);
+                        saver.changed();
+                    }
+                    review.tag = None;
+                    review.tagged += 1;
+                    review.at += 1;
+                }
+                _ => {}
+            }
+        } else {
+            match key.code {
+                KeyCode::Char(' ') | KeyCode::Enter => {
+                    review.kept += 1;
+                    review.at += 1;
+                }
+                KeyCode::Char(c) if c == keys.previous => {
+                    review.at = review.at.saturating_sub(1);
+                }
+                KeyCode::Char('a') => {
+                    let path = store::notebook_path(
+                        notebook_dir,
+                        ARCHIVE,
+                        config.storage,
+                    );
+                    if path == **data_file {
+                        *status = strings.status.already_archived.into();
+                        return;
+                    }
+                    let mut archive =
+                        recover!(toasts, store::load(&path, cipher));
+                    archive.insert(feed.notes[i].clone());
+                    recover!(toasts, store::save(&path, &mut archive, cipher));
+                    feed.remove(i);
+                    // the indices the undo entries refer to moved
+                    undo_stack.clear();
+                    saver.changed();
+                    review.archived += 1;
+                    review.at += 1;
+                }
+                KeyCode::Char('t') => review.tag = Some(String::new()),
+                KeyCode::Char('d') => {
+                    trash_notes(feed, vec![i], undo_stack);
+                    saver.changed();
+                    review.deleted += 1;
+                    review.at += 1;
+                }
+                KeyCode::Esc | KeyCode::Char('q') => {
+                    review.at = review.notes.len()
+                }
+                _ => {}
+            }
+        }
+        if review.current(feed).is_none() {
+            *status = review.summary();
+            *feed_view = build_view(feed, filter, *sort, journal);
+            *trash_view = FeedView::trash(feed);
+            clamp(state, feed_view.refs.len());
+            *focus = Focus::Feed;
+        }
+    }
+
+    fn calendar_event(&mut self, event: Event) {
+        let strings = self.strings;
+        let keys = &self.config.keys;
+        let App {
+            feed,
+            sort,
+            journal,
+            feed_view,
+            focus,
+            state,
+            filter,
+            status,
+            calendar_day,
+            ..
+        } = self;
+        let Event::Key(key) = event else {
+            return;
+        };
+        let day = *calendar_day;
+        let moved = match key.code {
+            KeyCode::Char(c) if c == keys.next => {
+                day.checked_add_days(Days::new(7))
+            }
+            KeyCode::Char(c) if c == keys.previous => {
+                day.checked_sub_days(Days::new(7))
+            }
+            KeyCode::Char('l') | KeyCode::Right => {
+                day.checked_add_days(Days::new(1))
+            }
+            KeyCode::Char('h') | KeyCode::Left => {
+                day.checked_sub_days(Days::new(1))
+            }
+            KeyCode::Char('L') | KeyCode::PageDown => {
+                day.checked_add_months(Months::new(1))
+            }
+            KeyCode::Char('H') | KeyCode::PageUp => {
+                day.checked_sub_months(Months::new(1))
+            }
+            KeyCode::Char('t') => Some(Local::now().date_naive()),
+            KeyCode::Enter => {
+                *filter = format!("date:{}", day);
+                *feed_view = build_view(feed, filter, *sort, journal);
+                if feed_view.refs.is_empty() {
+                    *status = (strings.status.no_notes_on)(&day);
+                    state.select(None);
+                } else {
+                    state.select(Some(0));
+                }
+                *focus = Focus::Feed;
+                None
+            }
+            KeyCode::Esc | KeyCode::Backspace | KeyCode::Char('q') => {
+                *focus = Focus::Feed;
+                None
+            }
+            _ => None,
+        };
+        if let Some(day) = moved {
+            *calendar_day = day;
+        }
+    }
+
+    fn urls_event(&mut self, event: Event) {
+        let strings = self.strings;
+        let keys = &self.config.keys;
+        let App { feed, feed_view, focus, state, status, url_state, .. } = self;
+        let Event::Key(key) = event else {
+            return;
+        };
+        match key.code {
+            KeyCode::Char(c) if c == keys.next => url_state.next(),
+            KeyCode::Char(c) if c == keys.previous => url_state.previous(),
+            KeyCode::Enter => {
+                let Some(k) = url_state.selected else {
+                    return;
+                };
+                let text =
+                    &feed.notes[feed_view.refs[state.selected.unwrap()]].text;
+                let url = &text[urls(text)[k].clone()];
+                *status = match attachments::launch(url) {
+                    Ok(()) => (strings.status.opened)(&url),
+                    Err(e) => (strings.status.cannot_open)(&url, &e),
+                };
+                *focus = Focus::Feed;
+            }
+            KeyCode::Esc | KeyCode::Backspace | KeyCode::Char('q') => {
+                *focus = Focus::Feed
+            }
+            _ => {}
+        }
+    }
+
+    fn colors_event(&mut self, event: Event) {
+        let keys = &self.config.keys;
+        let App {
+            feed,
+            sort,
+            journal,
+            feed_view,
+            focus,
+            state,
+            filter,
+            undo_stack,
+            color_state,
+            marked,
+            saver,
+            ..
+        } = self;
+        let Event::Key(key) = event else {
+            return;
+        };
+        match key.code {
+            KeyCode::Char(c) if c == keys.next => color_state.next(),
+            KeyCode::Char(c) if c == keys.previous => color_state.previous(),
+            KeyCode::Enter => {
+                let Some(k) = color_state.selected else {
+                    return;
+                };
+                let color = k.checked_sub(1).map(|k| NoteColor::ALL[k]);
+                let targets = targets(
+                    feed,
+                    marked,
+                    state.selected.map(|k| feed_view.refs[k]),
+                );
+                let mut before = Vec::new();
+                for &i in &targets {
+                    if feed.notes[i].color == color {
+                        continue;
+                    }
+                    before.push((i, feed.notes[i].clone()));
+                    feed.notes[i].color = color;
+                    feed.touch(i);
+                }
+                if !before.is_empty() {
+                    undo_stack.push(Undo::Edit { notes: before });
+                }
+                marked.clear();
+                saver.changed();
+                *feed_view = build_view(feed, filter, *sort, journal);
+                clamp(state, feed_view.refs.len());
+                *focus = Focus::Feed;
+            }
+            KeyCode::Esc | KeyCode::Backspace | KeyCode::Char('q') => {
+                *focus = Focus::Feed
+            }
+            _ => {}
+        }
+    }
+
+    fn review_edit_event(&mut self, event: Event) {
+        let strings = self.strings;
+        let keys = &self.config.keys;
+        let App {
+            feed,
+            sort,
+            journal,
+            feed_view,
+            focus,
+            textarea,
+            filter,
+            feed_editing_mode,
+            undo_stack,
+            status,
+            review_scroll,
+            saver,
+            ..
+        } = self;
+        let Event::Key(key) = event else {
+            return;
+        };
+        let FeedEditingMode::Edit(i) = feed_editing_mode else {
+            return;
+        };
+        match key.code {
+            KeyCode::Char(c) if c == keys.next => {
+                *review_scroll = review_scroll.saturating_add(1)
+            }
+            KeyCode::Char(c) if c == keys.previous => {
+                *review_scroll = review_scroll.saturating_sub(1)
+            }
+            KeyCode::Char('y') | KeyCode::Enter => {
+                edit_note(feed, *i, textarea.lines().join("\n"), undo_stack);
+                saver.changed();
+                *feed_view = build_view(feed, filter, *sort, journal);
+                *focus = Focus::Feed;
+                *status = strings.status.edited.into();
+            }
+            KeyCode::Char('n') | KeyCode::Esc | KeyCode::Backspace => {
+                *focus = Focus::NewNote
+            }
+            KeyCode::Char('d') => {
+                *focus = Focus::Feed;
+                *status = strings.status.discarded_edit.into();
+            }
+            _ => {}
+        }
+    }
+
+    fn duplicate_event(&mut self, event: Event) {
+        let config = self.config;
+        let strings = self.strings;
+        let keys = &self.config.keys;
+        let App {
+            feed,
+            sort,
+            journal,
+            feed_view,
+            focus,
+            state,
+            textarea,
+            filter,
+            feed_editing_mode,
+            undo_stack,
+            status,
+            toasts,
+            review_scroll,
+            duplicate,
+            saver,
+            ..
+        } = self;
+        let Event::Key(key) = event else {
+            return;
+        };
+        match key.code {
+            KeyCode::Char(c) if c == keys.next => {
+                *review_scroll = review_scroll.saturating_add(1)
+            }
+            KeyCode::Char(c) if c == keys.previous => {
+                *review_scroll = review_scroll.saturating_sub(1)
+            }
+            KeyCode::Char('m') if *duplicate < feed.notes.len() => {
+                let text = textarea.lines().join("\n");
+                *status = if text == feed.notes[*duplicate].text {
+                    strings.status.written_before.into()
+                } else {
+                    edit_note(feed, *duplicate, text, undo_stack);
+                    strings.status.merged_into.into()
+                };
+                saver.changed();
+                *feed_view = build_view(feed, filter, *sort, journal);
+                state.select(
+                    feed_view.refs.iter().position(|&i| i == *duplicate),
+                );
+                *focus = Focus::Feed;
+            }
+            KeyCode::Char('b') | KeyCode::Enter => {
+                push_note(
+                    feed,
+                    textarea.lines().join("\n"),
+                    feed_editing_mode,
+                    config,
+                    undo_stack,
+                    toasts,
+                );
+                saver.changed();
+                *feed_view = build_view(feed, filter, *sort, journal);
+                state.select(feed_view.refs.iter().position(|&i| i == 0));
+                *focus = Focus::Feed;
+            }
+            KeyCode::Char('n') | KeyCode::Esc | KeyCode::Backspace => {
+                *focus = Focus::NewNote
+            }
+            _ => {}
+        }
+    }
+
+    fn spelling_event(&mut self, event: Event) {
+        let keys = &self.config.keys;
+        let App {
+            focus,
+            textarea,
+            suggestions,
+            suggestion_state,
+            misspelled_at,
+            ..
+        } = self;
+        let Event::Key(key) = event else {
+            return;
+        };
+        match key.code {
+            KeyCode::Char(c) if c == keys.next => suggestion_state.next(),
+            KeyCode::Char(c) if c == keys.previous => {
+                suggestion_state.previous()
+            }
+            KeyCode::Enter => {
+                let Some(k) = suggestion_state.selected else {
+                    return;
+                };
+                let (row, columns) = misspelled_at.clone();
+                textarea.move_cursor(CursorMove::Jump(
+                    row as u16,
+                    columns.start as u16,
+                ));
+                textarea.delete_str(columns.len());
+                textarea.insert_str(&suggestions[k]);
+                *focus = Focus::NewNote;
+            }
+            KeyCode::Esc | KeyCode::Backspace | KeyCode::Char('q') => {
+                *focus = Focus::NewNote
+            }
+            _ => {}
+        }
+    }
+
+    fn templates_event(&mut self, event: Event) {
+        let keys = &self.config.keys;
+        let App {
+            focus,
+            textarea,
+            feed_editing_mode,
+            status,
+            template_names,
+            template_state,
+            ..
+        } = self;
+        let Event::Key(key) = event else {
+            return;
+        };
+        match key.code {
+            KeyCode::Char(c) if c == keys.next => template_state.next(),
+            KeyCode::Char(c) if c == keys.previous => template_state.previous(),
+            KeyCode::Enter => {
+                let Some(i) = template_state.selected else {
+                    return;
+                };
+                match template_textarea(&template_names[i]) {
+                    Ok(t) => {
+                        *textarea = t;
+                        *focus = Focus::NewNote;
+                        *feed_editing_mode = FeedEditingMode::New;
+                    }
+                    Err(e) => {
+                        *status = e.to_string();
+                        *focus = Focus::Feed;
+                    }
+                }
+            }
+            KeyCode::Esc | KeyCode::Backspace | KeyCode::Char('q') => {
+                *focus = Focus::Feed
+            }
+            _ => {}
+        }
+    }
+
+    fn split_note_event(&mut self, event: Event) {
+        let strings = self.strings;
+        let keys = &self.config.keys;
+        let App {
+            feed,
+            sort,
+            journal,
+            feed_view,
+            focus,
+            state,
+            filter,
+            undo_stack,
+            status,
+            split_line_state,
+            saver,
+            ..
+        } = self;
+        let Event::Key(key) = event else {
+            return;
+        };
+        let i = feed_view.refs[state.selected.unwrap()];
+        match key.code {
+            KeyCode::Char(c) if c == keys.next => split_line_state.next(),
+            KeyCode::Char(c) if c == keys.previous => {
+                split_line_state.previous()
+            }
+            KeyCode::Enter => {
+                let Some(line) = split_line_state.selected else {
+                    return;
+                };
+                let before = feed.notes[i].clone();
+                if !feed.split(i, line) {
+                    *status = strings.status.nothing_to_split.into();
+                    return;
+                }
+                shift_undo(undo_stack);
+                undo_stack.push(Undo::Split {
+                    note: (i + 1, Box::new(before)),
+                    added: 0,
+                });
+                saver.changed();
+                *feed_view = build_view(feed, filter, *sort, journal);
+                state.select(feed_view.refs.iter().position(|&r| r == i + 1));
+                *focus = Focus::Feed;
+                *status = strings.status.split.into();
+            }
+            KeyCode::Esc | KeyCode::Backspace | KeyCode::Char('q') => {
+                *focus = Focus::Feed
+            }
+            _ => {}
+        }
+    }
+
+    fn checkboxes_event(&mut self, event: Event) {
+        let keys = &self.config.keys;
+        let App {
+            feed,
+            sort,
+            journal,
+            feed_view,
+            focus,
+            state,
+            filter,
+            undo_stack,
+            checkbox_state,
+            saver,
+            ..
+        } = self;
+        let Event::Key(key) = event else {
+            return;
+        };
+        let i = feed_view.refs[state.selected.unwrap()];
+        match key.code {
+            KeyCode::Char(c) if c == keys.next => checkbox_state.next(),
+            KeyCode::Char(c) if c == keys.previous => checkbox_state.previous(),
+            KeyCode::Char(' ') | KeyCode::Enter => {
+                let Some(c) = checkbox_state.selected else {
+                    return;
+                };
+                undo_stack.push(Undo::Edit {
+                    notes: vec![(i, feed.notes[i].clone())],
+                });
+                let line = feed.notes[i].checkboxes()[c];
+                feed.notes[i].toggle_checkbox(line);
+                feed.notes[i].modified = Some(Local::now());
+                feed.touch(i);
+                saver.changed();
+            }
+            KeyCode::Esc | KeyCode::Backspace | KeyCode::Char('q') => {
+                // rebuilt only now so a filter like todo:open does
+                // not drop the note while it is being ticked off
+                *feed_view = build_view(feed, filter, *sort, journal);
+                if state.selected.unwrap() >= feed_view.refs.len() {
+                    state.previous();
+                }
+                *focus = Focus::Feed
+            }
+            _ => {}
+        }
+    }
+
+    fn attachments_event(&mut self, event: Event) {
+        let readonly = self.readonly;
+        let strings = self.strings;
+        let keys = &self.config.keys;
+        let App {
+            feed,
+            data_file,
+            feed_view,
+            focus,
+            state,
+            status,
+            toasts,
+            attachment_state,
+            saver,
+            ..
+        } = self;
+        let Event::Key(key) = event else {
+            return;
+        };
+        let i = feed_view.refs[state.selected.unwrap()];
+        match key.code {
+            KeyCode::Char(c) if c == keys.next => attachment_state.next(),
+            KeyCode::Char(c) if c == keys.previous => {
+                attachment_state.previous()
+            }
+            KeyCode::Enter => {
+                if let Some(a) = attachment_state.selected {
+                    recover!(
+                        toasts,
+                        attachments::open(
+                            data_file,
+                            &feed.notes[i].attachments[a],
+                        )
+                    );
+                }
+            }
+            KeyCode::Char('x') if readonly => {
+                *focus = Focus::Feed;
+                *status = strings.status.read_only.into();
+            }
+            KeyCode::Char('x') => {
+                let Some(a) = attachment_state.selected else {
+                    return;
+                };
+                let mut note = feed.notes[i].clone();
+                note.attachments = vec![feed.notes[i].attachments.remove(a)];
+                feed.touch(i);
+                if let Err(e) =
+                    attachments::remove_orphans(data_file, feed, &[note])
+                {
+                    toasts.error(e.to_string());
+                }
+                saver.changed();
+                *status = strings.status.attachment_removed.into();
+                if feed.notes[i].attachments.is_empty() {
+                    *focus = Focus::Feed;
+                } else {
+                    attachment_state.select(Some(
+                        a.min(feed.notes[i].attachments.len() - 1),
+                    ));
+                }
+            }
+            KeyCode::Esc | KeyCode::Backspace | KeyCode::Char('q') => {
+                *focus = Focus::Feed
+            }
+            _ => {}
+        }
+    }
+
+    fn scratch_event(&mut self, event: Event) {
+        let cipher = self.cipher;
+        let readonly = self.readonly;
+        let strings = self.strings;
+        let App {
+            data_file,
+            focus,
+            textarea,
+            scratch,
+            inputmode,
+            feed_editing_mode,
+            status,
+            toasts,
+            vim,
+            ..
+        } = self;
+        match inputmode {
+            // the selected lines are taken out to start a note with
+            InputMode::ViewLine
+                if matches!(
+                    event.clone().into(),
+                    Input { key: Key::Char('n'), .. }
+                ) =>
+            {
+                if readonly {
+                    *status = strings.status.read_only.into();
+                    return;
+                }
+                let (from, to) = vim.line_range(scratch);
+                let lines = scratch.lines()[from..=to].to_vec();
+                select_whole_lines(scratch, from, to);
+                scratch.cut();
+                scratch.move_cursor(CursorMove::Head);
+                *textarea = TextArea::new(lines);
+                textarea.move_cursor(CursorMove::Bottom);
+                textarea.move_cursor(CursorMove::End);
+                *focus = Focus::NewNote;
+                *feed_editing_mode = FeedEditingMode::New;
+                *inputmode = InputMode::Normal;
+            }
+            InputMode::Insert => insert_event(event, scratch, inputmode, vim),
+            _ => recover!(
+                toasts,
+                textarea_event(event, scratch, focus, inputmode, vim,)
+            ),
+        }
+        if *focus != Focus::Scratch {
+            recover!(
+                toasts,
+                store::save_scratch(
+                    data_file,
+                    &scratch.lines().join("\n"),
+                    cipher,
+                )
+            );
+        }
+    }
+
+    fn new_note_event(&mut self, event: Event) {
+        let config = self.config;
+        let cipher = self.cipher;
+        let strings = self.strings;
+        let keys = &self.config.keys;
+        let App {
+            feed,
+            data_file,
+            sort,
+            journal,
+            feed_view,
+            focus,
+            state,
+            textarea,
+            filter,
+            inputmode,
+            feed_editing_mode,
+            undo_stack,
+            status,
+            toasts,
+            speller,
+            suggestions,
+            suggestion_state,
+            completion,
+            misspelled_at,
+            pasted,
+            vim,
+            review_scroll,
+            zen,
+            duplicate,
+            saver,
+            ..
+        } = self;
+        // the save keys and z=, typed in normal mode
+        let chord = match &event {
+            Event::Key(key)
+                if matches!(inputmode, InputMode::Normal)
+                    && vim.takes_keys() =>
+            {
+                match vim.keys.key(*key, false) {
+                    Step::Run(chord) => Some(chord),
+                    Step::Pending | Step::Ignored => return,
+                }
+            }
+            _ => None,
+        };
+        match inputmode {
+            _ if matches!(
+                event.clone().into(),
+                Input { key: Key::Char('v'), ctrl: true, .. }
+            ) =>
+            {
+                let added = clipboard::paste_image().and_then(|png| {
+                    png.map(|png| {
+                        attachments::add_pasted(data_file, &png, cipher)
+                    })
+                    .transpose()
+                });
+                match added {
+                    Ok(Some(name)) => {
+                        textarea
+                            .insert_str(attachments::image_reference(&name));
+                        *status = (strings.status.pasted)(&name.display());
+                        pasted.push(name);
+                    }
+                    Ok(None) => *status = strings.status.no_image.into(),
+                    Err(e) => toasts.error(format!(
+                        "{}: {}",
+                        strings.status.cannot_paste, e
+                    )),
+                }
+            }
+            InputMode::Normal if chord.as_ref().is_some_and(|c| c.is("gz")) => {
+                *zen = !*zen;
+            }
+            InputMode::Normal if chord.as_ref().is_some_and(|c| c.is("z=")) => {
+                let Some(speller) = &speller else {
+                    *status = strings.status.spelling_off.into();
+                    return;
+                };
+                let (row, col) = textarea.cursor();
+                let line = &textarea.lines()[row];
+                let at =
+                    line.char_indices().nth(col).map_or(line.len(), |(i, _)| i);
+                let Some((range, word)) = spell::words(line)
+                    .into_iter()
+                    .find(|(range, _)| range.contains(&at))
+                else {
+                    *status = strings.status.no_word.into();
+                    return;
+                };
+                match speller.suggestions(word) {
+                    None => *status = (strings.status.spelled_right)(&word),
+                    Some([]) => {
+                        *status = (strings.status.no_suggestions)(&word)
+                    }
+                    Some(found) => {
+                        *suggestions = found.to_vec();
+                        let start = line[..range.start].chars().count();
+                        *misspelled_at =
+                            (row, start..start + word.chars().count());
+                        *suggestion_state = ListState::default();
+                        suggestion_state.select(Some(0));
+                        *focus = Focus::Spelling;
+                    }
+                }
+            }
+            InputMode::Normal | InputMode::View | InputMode::ViewLine => {
+                if chord.as_ref().is_some_and(|c| c.is(&keys.save)) {
+                    match feed_editing_mode {
+                        FeedEditingMode::New | FeedEditingMode::Reply(_) => {
+                            let text = textarea.lines().join("\n");
+                            if let Some(i) = feed.duplicate_of(&text) {
+                                *duplicate = i;
+                                *review_scroll = 0;
+                                *focus = Focus::Duplicate;
+                                return;
+                            }
+                            push_note(
+                                feed,
+                                text,
+                                feed_editing_mode,
+                                config,
+                                undo_stack,
+                                toasts,
+                            );
+                            saver.changed();
+                            *feed_view =
+                                build_view(feed, filter, *sort, journal);
+                            *focus = Focus::Feed;
+                            if let FeedEditingMode::Reply(_) = feed_editing_mode
+                            {
+                                // the new note is first in the feed,
+                                // but its place in the view is under
+                                // its parent
+                                state.select(
+                                    feed_view.refs.iter().position(|&i| i == 0),
+                                );
+                            }
+                        }
+                        FeedEditingMode::Edit(i)
+                            if config.review_edits
+                                && textarea.lines().join("\n")
+                                    != feed.notes[*i].text =>
+                        {
+                            *focus = Focus::ReviewEdit;
+                            *review_scroll = 0;
+                        }
+                        FeedEditingMode::Edit(i) => {
+                            edit_note(
+                                feed,
+                                *i,
+                                textarea.lines().join("\n"),
+                                undo_stack,
+                            );
+                            saver.changed();
+                            *feed_view =
+                                build_view(feed, filter, *sort, journal);
+                            *focus = Focus::Feed;
+                            *status = strings.status.edited.into();
+                        }
+                    }
+                } else {
+                    let inputs: Vec<Input> = match chord {
+                        Some(chord) => {
+                            chord.keys().into_iter().map(Input::from).collect()
+                        }
+                        None => vec![event.into()],
+                    };
+                    for input in inputs {
+                        // the keys left are still typed
+                        if let Err(e) = textarea_event(
+                            input, textarea, focus, inputmode, vim,
+                        ) {
+                            toasts.error(e.to_string());
+                        }
+                    }
+                }
+            }
+            InputMode::Insert => {
+                let input: Input = event.into();
+                if let Some(shown) = completion {
+                    match input {
+                        Input { key: Key::Tab | Key::Enter, .. } => {
+                            if let Some(shown) = completion.take() {
+                                shown.accept(textarea);
+                            }
+                            return;
+                        }
+                        Input { key: Key::Down, .. }
+                        | Input { key: Key::Char('n'), ctrl: true, .. } => {
+                            shown.next();
+                            return;
+                        }
+                        Input { key: Key::Up, .. }
+                        | Input { key: Key::Char('p'), ctrl: true, .. } => {
+                            shown.previous();
+                            return;
+                        }
+                        _ => {}
+                    }
+                }
+                insert_event(input, textarea, inputmode, vim);
+                *completion = match inputmode {
+                    InputMode::Insert
+                        if !complete::replace_shortcode(textarea) =>
+                    {
+                        Completion::new(textarea)
+                    }
+                    _ => None,
+                };
+            }
+        }
+    }
+
+    fn prompt_event(&mut self, event: Event) {
+        let config = self.config;
+        let cipher = self.cipher;
+        let readonly = self.readonly;
+        let strings = self.strings;
+        let App {
+            feed,
+            data_file,
+            sort,
+            journal,
+            feed_view,
+            focus,
+            state,
+            textarea,
+            filter,
+            inputmode,
+            undo_stack,
+            status,
+            toasts,
+            messages_scroll,
+            picked_notebook,
+            vim,
+            marked,
+            jumps,
+            disk_changed,
+            saver,
+            discard,
+            ..
+        } = self;
+        if matches!(event.clone().into(), Input { key: Key::Enter, .. }) {
+            if *focus == Focus::TagNotes {
+                let input = textarea.lines().concat();
+                let tag = input.trim().trim_start_matches('#');
+                if extract_tags(&format!("#{}", tag)) != [tag] {
+                    *status = (strings.status.invalid_tag)(&input.trim());
+                } else {
+                    let targets = targets(
+                        feed,
+                        marked,
+                        state.selected.map(|k| feed_view.refs[k]),
+                    );
+                    let mut before = Vec::new();
+                    for &i in &targets {
+                        if feed.notes[i].tags.iter().any(|t| t == tag) {
+                            continue;
+                        }
+                        before.push((i, feed.notes[i].clone()));
+                        let note = &mut feed.notes[i];
+                        note.set_text(append_tag(&note.text, tag));
+                        feed.touch(i);
+                    }
+                    *status = (strings.status.tagged)(
+                        before.len(),
+                        targets.len(),
+                        &tag,
+                    );
+                    if !before.is_empty() {
+                        undo_stack.push(Undo::Edit { notes: before });
+                    }
+                    marked.clear();
+                    saver.changed();
+                    *feed_view = build_view(feed, filter, *sort, journal);
+                    clamp(state, feed_view.refs.len());
+                }
+            } else if *focus == Focus::Locate {
+                let input = textarea.lines().concat();
+                let location = match input.trim() {
+                    "" => None,
+                    "here" => match &config.location_command {
+                        Some(command) => match geo::locate(command) {
+                            Ok(location) => Some(location),
+                            Err(e) => {
+                                *status = e.to_string();
+                                return;
+                            }
+                        },
+                        None => {
+                            *status = strings.status.no_location_command.into();
+                            return;
+                        }
+                    },
+                    input => match geo::parse(input) {
+                        Some(location) => Some(location),
+                        None => {
+                            *status = (strings.status.unknown_place)(&input);
+                            return;
+                        }
+                    },
+                };
+                let targets = targets(
+                    feed,
+                    marked,
+                    state.selected.map(|k| feed_view.refs[k]),
+                );
+                let mut before = Vec::new();
+                for &i in &targets {
+                    before.push((i, feed.notes[i].clone()));
+                    feed.notes[i].location = location.clone();
+                    feed.touch(i);
+                }
+                *status = match &location {
+                    Some(l) => (strings.status.located)(
+                        targets.len(),
+                        &geo::describe(l),
+                    ),
+                    None => (strings.status.unlocated)(targets.len()),
+                };
+                undo_stack.push(Undo::Edit { notes: before });
+                marked.clear();
+                saver.changed();
+                *feed_view = build_view(feed, filter, *sort, journal);
+                clamp(state, feed_view.refs.len());
+            } else if *focus == Focus::ExportNotes {
+                let input = textarea.lines().concat();
+                let path = expand_home(input.trim());
+                let notes: Vec<&Note> = targets(
+                    feed,
+                    marked,
+                    state.selected.map(|k| feed_view.refs[k]),
+                )
+                .into_iter()
+                .map(|i| &feed.notes[i])
+                .collect();
+                *status = match fs::write(
+                    &path,
+                    recover!(toasts, serde_json::to_vec_pretty(&notes)),
+                ) {
+                    Ok(()) => {
+                        marked.clear();
+                        (strings.status.exported)(notes.len(), &path.display())
+                    }
+                    Err(e) => {
+                        (strings.status.cannot_export)(&path.display(), &e)
+                    }
+                };
+            } else if *focus == Focus::Attach {
+                let input = textarea.lines().concat();
+                let input = input.trim();
+                let source = expand_home(input);
+                let i = feed_view.refs[state.selected.unwrap()];
+                match attachments::add(
+                    data_file,
+                    feed.notes[i].id,
+                    &source,
+                    cipher,
+                ) {
+                    Ok(name) => {
+                        feed.notes[i].attachments.push(name);
+                        feed.touch(i);
+                        saver.changed();
+                        *status = (strings.status.attached)(&input);
+                    }
+                    Err(e) => {
+                        *status = (strings.status.cannot_attach)(&input, &e)
+                    }
+                }
+            } else if *focus == Focus::NewNotebook {
+                let name = textarea.lines().concat().trim().to_string();
+                if !store::is_notebook_name(&name) {
+                    *status = (strings.status.invalid_notebook)(&name);
+                } else {
+                    *picked_notebook = Some(name);
+                }
+            } else if *focus == Focus::Command {
+                let command = textarea.lines().concat();
+                match command.trim() {
+                    "w" if readonly => {
+                        *status = strings.status.read_only.into()
+                    }
+                    "w" => match saver.flush(data_file, feed) {
+                        Ok(merged) => {
+                            *disk_changed |= merged;
+                            *status = strings.status.saved.into();
+                        }
+                        Err(e) => toasts.error(format!(
+                            "{}: {}",
+                            strings.status.saving_failed, e
+                        )),
+                    },
+                    "q" if saver.unsaved() => {
+                        *status = strings.status.unsaved.into()
+                    }
+                    "q" | "wq" | "x" => {
+                        self.quit = true;
+                        return;
+                    }
+                    "q!" => {
+                        *discard = true;
+                        self.quit = true;
+                        return;
+                    }
+                    "messages" | "mes" => {
+                        *focus = Focus::Messages;
+                        // newest at the bottom, in sight
+                        *messages_scroll = u16::MAX;
+                        vim.inserting = false;
+                        return;
+                    }
+                    "" => {}
+                    command => {
+                        *status = (strings.status.unknown_command)(&command)
+                    }
+                }
+            } else if *focus == Focus::Filter {
+                let input = textarea.lines().concat();
+                // the error is shown in the popup until it is fixed
+                let Ok(query) = Query::parse(&input) else {
+                    return;
+                };
+                if query.in_archive() {
+                    *status = strings.status.archive_search.into();
+                    return;
+                }
+                *filter = input;
+                *feed_view = build_view(feed, filter, *sort, journal);
+            } else {
+                let day = textarea.lines().concat();
+                match NaiveDate::parse_from_str(day.trim(), "%Y-%m-%d") {
+                    Ok(day) => {
+                        // the feed runs forward in time when sorted
+                        // oldest first
+                        let forward = *sort == Sort::Oldest;
+                        match feed_view.refs.iter().position(|i| {
+                            let d = feed.notes[*i].date.date_naive();
+                            if forward {
+                                d >= day
+                            } else {
+                                d <= day
+                            }
+                        }) {
+                            Some(k) => jump(state, k, feed, feed_view, jumps),
+                            None => {
+                                *status = (strings.status.no_notes_around)(
+                                    &day, forward,
+                                )
+                            }
+                        }
+                    }
+                    Err(_) => *status = (strings.status.invalid_date)(&day),
+                }
+            }
+            *focus = Focus::Feed;
+            // whatever was being typed is not a change to repeat
+            vim.inserting = false;
+            return;
+        }
+        match inputmode {
+            InputMode::Insert => insert_event(event, textarea, inputmode, vim),
+            _ => recover!(
+                toasts,
+                textarea_event(event, textarea, focus, inputmode, vim,)
+            ),
+        }
+    }
+}
+
+/// A note in the read view and how far down it is scrolled.
+type ReadPosition = (NoteId, u16);
+
+/// Picks up where the last session in the notebook at `data_file` left off,
+/// selecting the note that was selected. Returns where the note last read
+/// was left, and the notes added since.
+fn resume(
+    data_file: &Path,
+    feed: &Feed,
+    feed_view: &FeedView,
+    state: &mut ListState,
+) -> Result<(Option<ReadPosition>, HashSet<NoteId>), Box<dyn std::error::Error>>
+{
+    let Some(session) = store::load_session(data_file)? else {
+        return Ok((None, HashSet::new()));
+    };
+    if let Some(id) = session.selected {
+        if let Some(k) =
+            feed_view.refs.iter().position(|&i| feed.notes[i].id == id)
+        {
+            state.select(Some(k));
+        }
+    }
+    let unread = feed
+        .notes
+        .iter()
+        .filter(|n| n.id > session.last_id && n.deleted_at.is_none())
+        .map(|n| n.id)
+        .collect();
+    Ok((session.read, unread))
+}
+
+/// Keeps where the UI is in the notebook at `data_file` for [`resume`].
+fn leave(
+    data_file: &Path,
+    feed: &Feed,
+    feed_view: &FeedView,
+    state: &ListState,
+    read: Option<ReadPosition>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let newest = feed.notes.iter().map(|n| n.id).max().unwrap_or(0);
+    let session = Session {
+        selected: state.selected.map(|k| feed.notes[feed_view.refs[k]].id),
+        read,
+        last_id: feed.last_id.max(newest),
+    };
+    store::save_session(data_file, &session)
+}
+
+/// The notes outside the trash whose titles match `query` for the quick
+/// switcher, best match first and otherwise in feed order.
+fn switcher_matches(feed: &Feed, query: &str) -> Vec<usize> {
+    let mut matches: Vec<(i64, usize)> = (0..feed.notes.len())
+        .filter(|&i| feed.notes[i].deleted_at.is_none())
+        .filter_map(|i| Some((fuzzy::score(query, feed.notes[i].title())?, i)))
+        .collect();
+    matches.sort_by_key(|&(score, _)| std::cmp::Reverse(score));
+    matches.into_iter().map(|(_, i)| i).collect()
+}
+
+/// Adds the note written in the composer to the top of the feed, as a reply
+/// when it is one, and sends it on to the webhooks.
+fn push_note(
+    feed: &mut Feed,
+    text: String,
+    mode: &FeedEditingMode,
+    config: &Config,
+    undo_stack: &mut [Undo],
+    toasts: &mut Toasts,
+) {
+    let strings = config.language.unwrap_or_else(Language::from_env).strings();
+    let mut note = Note::new(text);
+    if let FeedEditingMode::Reply(parent) = *mode {
+        note.reply_to = Some(parent);
+    }
+    if let Some(command) =
+        config.location_command.as_ref().filter(|_| config.locate_new_notes)
+    {
+        match geo::locate(command) {
+            Ok(location) => note.location = Some(location),
+            Err(e) => {
+                toasts.error(format!("{}: {}", strings.status.cannot_locate, e))
+            }
+        }
+    }
+    feed.push(note);
+    shift_undo(undo_stack);
+    if let Err(e) = webhooks::send(&config.webhooks, &feed.notes[0]) {
+        toasts.error(format!("{}: {}", strings.status.cannot_send, e));
+    }
+}
+
+/// Replaces the text of the note at `i` with `text`, keeping what it was for
+/// undo.
+fn edit_note(
+    feed: &mut Feed,
+    i: usize,
+    text: String,
+    undo_stack: &mut Vec<Undo>,
+) {
+    undo_stack.push(Undo::Edit { notes: vec![(i, feed.notes[i].clone())] });
+    feed.notes[i].set_text(text);
+    feed.touch(i);
+}
+
+/// Whether `key` in the feed changes the notes, which is turned off when
+/// they are read only.
+fn changes_notes(key: KeyEvent, keys: &Keys) -> bool {
+    match key.code {
+        _ if key.modifiers.contains(KeyModifiers::CONTROL) => false,
+        KeyCode::Char(c) if c == keys.next || c == keys.previous => false,
+        KeyCode::Char(c) => {
+            "nNriuaAmcp#S*XM@ ".contains(c) || keys.delete.starts_with(c)
+        }
+        _ => false,
+    }
+}
+
+/// Moves the indices the undo entries refer to past a note pushed to the top
+/// of the feed.
+fn shift_undo(undo_stack: &mut [Undo]) {
+    for undo in undo_stack {
+        match undo {
+            Undo::Delete { indices } => {
+                indices.iter_mut().for_each(|i| *i += 1)
+            }
+            Undo::Edit { notes } => notes.iter_mut().for_each(|(i, _)| *i += 1),
+            Undo::Split { note, added } => {
+                note.0 += 1;
+                *added += 1;
+            }
+        }
+    }
+}
+
+/// Moves the selection of a list of `len` cards `by` cards down, or up when
+/// negative, stopping at either end.
+fn move_by(state: &mut ListState, len: usize, by: isize) {
+    if len == 0 {
+        return;
+    }
+    let k = match state.selected {
+        Some(k) => k.saturating_add_signed(by).min(len - 1),
+        None => 0,
+    };
+    state.select(Some(k));
+}
+
+/// How many positions `Ctrl-o` can go back through.
+const JUMPS: usize = 100;
+
+/// Selects card `k` of the feed, remembering the note selected before.
+fn jump(
+    state: &mut ListState,
+    k: usize,
+    feed: &Feed,
+    view: &FeedView,
+    jumps: &mut Vec<NoteId>,
+) {
+    match state.selected {
+        Some(selected) if selected == k => return,
+        Some(selected) => remember(jumps, feed.notes[view.refs[selected]].id),
+        None => {}
+    }
+    state.select(Some(k));
+}
+
+/// Adds `id` as the latest position to go back to with `Ctrl-o`.
+fn remember(jumps: &mut Vec<NoteId>, id: NoteId) {
+    jumps.retain(|&j| j != id);
+    jumps.push(id);
+    if jumps.len() > JUMPS {
+        jumps.remove(0);
+    }
+}
+
+/// The notes a bulk action applies to: the marked ones still outside the
+/// trash, in feed order, or the `selected` one if none are marked.
+fn targets(
+    feed: &Feed,
+    marked: &HashSet<NoteId>,
+    selected: Option<usize>,
+) -> Vec<usize> {
+    if marked.is_empty() {
+        return selected.into_iter().collect();
+    }
+    (0..feed.notes.len())
+        .filter(|&i| {
+            let note = &feed.notes[i];
+            marked.contains(&note.id) && note.deleted_at.is_none()
+        })
+        .collect()
+}
+
+/// Moves the notes at `indices` to the trash as one undoable change and
+/// returns the message saying so.
+fn trash_notes(
+    feed: &mut Feed,
+    indices: Vec<usize>,
+    undo_stack: &mut Vec<Undo>,
+) -> String {
+    let now = Local::now();
+    for &i in &indices {
+        feed.notes[i].deleted_at = Some(now);
+        feed.touch(i);
+    }
+    let message = match indices.len() {
+        1 => "note moved to trash — press u to undo".into(),
+        n => format!("{} notes moved to trash — press u to undo", n),
+    };
+    undo_stack.push(Undo::Delete { indices });
+    message
+}
+
+/// Keeps the selection of a list of `len` items on one of them.
+fn clamp(state: &mut ListState, len: usize) {
+    if let Some(k) = state.selected {
+        state.select(if len == 0 { None } else { Some(k.min(len - 1)) });
+    }
+}
+
+/// `text` with `#tag` added at the end, on the last line if that holds
+/// nothing but tags.
+fn append_tag(text: &str, tag: &str) -> String {
+    let text = text.trim_end();
+    let last = text.lines().last().unwrap_or("");
+    if !last.trim().is_empty()
+        && last.split_whitespace().all(|w| w.starts_with('#'))
+    {
+        format!("{} #{}", text, tag)
+    } else if text.is_empty() {
+        format!("#{}", tag)
+    } else {
+        format!("{}\n\n#{}", text, tag)
+    }
+}
+
+/// A path typed into a prompt, with a leading `~/` standing for the home
+/// directory.
+fn expand_home(input: &str) -> PathBuf {
+    match input.strip_prefix("~/") {
+        Some(rest) => match std::env::var("HOME") {
+            Ok(home) => Path::new(&home).join(rest),
+            Err(_) => PathBuf::from(input),
+        },
+        None => PathBuf::from(input),
+    }
+}
+
+/// State of the composer's vim keys kept from one key press to the next.
+struct Vim {
+    /// The keys of the last change made from normal mode, with the text typed
+    /// if it entered insert mode, replayed by `.`.
+    last_change: Vec<Input>,
+    /// The keys of the command being read.
+    recording: Vec<Input>,
+    /// Set while the text typed after a change entered insert mode is being
+    /// recorded.
+    inserting: bool,
+    /// Keys still to be replayed by `.`, taken before the terminal's.
+    replay: VecDeque<Input>,
+    /// The line visual line mode was entered on.
+    line_anchor: usize,
+    /// The query being typed after `/` in the composer.
+    search: Option<String>,
+    /// The column lines are wrapped at, from the config.
+    text_width: usize,
+    /// Break lines at `text_width` while typing.
+    auto_wrap: bool,
+    /// The width of the text area last drawn, for moving by display lines.
+    width: usize,
+    /// Make `j` and `k` move by display lines like `gj` and `gk`.
+    display_lines: bool,
+    /// The snippets from the config, expanded when typed in insert mode.
+    snippets: BTreeMap<String, String>,
+    /// The save keys and `z=`, taken in normal mode before the vim keys.
+    keys: Chords,
+    /// The keys of a command typed so far, waiting for the rest of it.
+    pending: Vec<Input>,
+    /// The keys typed after the first of the command being read.
+    ahead: VecDeque<Input>,
+    since: Instant,
+    timeout: Duration,
+}
+
+impl Vim {
+    fn new(config: &Config) -> Vim {
+        Vim {
+            last_change: Vec::new(),
+            recording: Vec::new(),
+            inserting: false,
+            replay: VecDeque::new(),
+            line_anchor: 0,
+            search: None,
+            text_width: config
+                .text_width
+                .unwrap_or(config.layout.popup_width.saturating_sub(3) as usize)
+                .max(1),
+            auto_wrap: config.auto_wrap,
+            width: 0,
+            display_lines: config.display_lines,
+            snippets: config.snippets.clone(),
+            keys: Chords::new(
+                vec![config.keys.save.clone(), "z=".into(), "gz".into()],
+                config.keys.timeout(),
+            ),
+            pending: Vec::new(),
+            ahead: VecDeque::new(),
+            since: Instant::now(),
+            timeout: config.keys.timeout(),
+        }
+    }
+
+    /// The next key of the command being read, or an error of the kind
+    /// `WouldBlock` if it was not typed yet.
+    fn read(&mut self) -> io::Result<Input> {
+        let input = match self.replay.pop_front() {
+            Some(input) => input,
+            None => self.ahead.pop_front().ok_or(io::ErrorKind::WouldBlock)?,
+        };
+        self.recording.push(input.clone());
+        Ok(input)
+    }
+
+    /// Whether no keys wait for the rest of a command.
+    fn idle(&self) -> bool {
+        self.search.is_none()
+            && self.pending.is_empty()
+            && self.keys.typed().is_empty()
+    }
+
+    /// Whether keys typed in normal mode go to [`Vim::keys`] first, which
+    /// they do unless a command of the text area is being typed.
+    fn takes_keys(&self) -> bool {
+        self.search.is_none() && self.pending.is_empty()
+    }
+
+    /// The keys typed so far of a command, for the status bar.
+    fn typed(&self) -> String {
+        let pending = self.pending.iter().filter_map(|input| match input.key {
+            Key::Char(c) => Some(c),
+            _ => None,
+        });
+        self.keys.typed() + &pending.collect::<String>()
+    }
+
+    /// How long the keys typed so far wait for the rest.
+    fn deadline(&self) -> Option<Duration> {
+        let pending = (!self.pending.is_empty())
+            .then(|| self.timeout.saturating_sub(self.since.elapsed()));
+        pending.into_iter().chain(self.keys.deadline()).min()
+    }
+
+    /// Drops the keys typed so far once they have waited too long.
+    fn expire(&mut self) {
+        self.keys.expire();
+        if self.deadline() == Some(Duration::ZERO) {
+            self.pending.clear();
+        }
+    }
+
+    /// The first and last lines selected in visual line mode.
+    fn line_range(&self, textarea: &TextArea) -> (usize, usize) {
+        let row = textarea.cursor().0;
+        (row.min(self.line_anchor), row.max(self.line_anchor))
+    }
+
+    /// Reads a count, if `input` starts one, and returns it with the key
+    /// that follows it.
+    fn count(
+        &mut self,
+        mut input: Input,
+    ) -> io::Result<(Option<usize>, Input)> {
+        let mut count = None;
+        while let Key::Char(c) = input.key {
+            match c.to_digit(10) {
+                Some(0) if count.is_none() => break,
+                Some(d) => count = Some(count.unwrap_or(0) * 10 + d as usize),
+                None => break,
+            }
+            input = self.read()?;
+        }
+        Ok((count, input))
+    }
+}
+
+/// Handles a key typed in insert mode, recording it when it is part of a
+/// change `.` repeats.
+fn insert_event(
+    event: impl Into<Input>,
+    textarea: &mut TextArea,
+    inputmode: &mut InputMode,
+    vim: &mut Vim,
+) {
+    let input = event.into();
+    if vim.inserting {
+        vim.recording.push(input.clone());
+    }
+    match input {
+        Input { key: Key::Esc, .. } => {
+            *inputmode = InputMode::Normal;
+            if vim.inserting {
+                vim.inserting = false;
+                vim.last_change = std::mem::take(&mut vim.recording);
+            }
+        }
+        input => {
+            let typed = matches!(input.key, Key::Char(c) if c != ' ');
+            let space = matches!(
+                input,
+                Input { key: Key::Char(' '), ctrl: false, alt: false, .. }
+            );
+            // a snippet ending in a line break takes the space typed after it
+            if space
+                && snippets::expand(textarea, &vim.snippets)
+                    .is_some_and(|text| text.ends_with('\n'))
+            {
+                return;
+            }
+            textarea.input(input);
+            if typed && vim.auto_wrap {
+                break_line(textarea, vim.text_width);
+            }
+        }
+    }
+}
+
+/// Handles a key typed in normal or visual mode. A count typed before a
+/// motion or a change repeats it, and `.` repeats the last change.
+fn textarea_event(
+    event: impl Into<Input>,
+    textarea: &mut TextArea,
+    focus: &mut Focus,
+    inputmode: &mut InputMode,
+    vim: &mut Vim,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let input = event.into();
+    if let Some(query) = &mut vim.search {
+        match input {
+            Input { key: Key::Enter, .. } => {
+                // matched literally and ignoring case, like the feed filter
+                let pattern = format!("(?i){}", regex::escape(query));
+                vim.search = None;
+                textarea.set_search_pattern(pattern)?;
+                textarea.search_forward(false);
+            }
+            Input { key: Key::Esc, .. } => vim.search = None,
+            Input { key: Key::Backspace, .. } if query.is_empty() => {
+                vim.search = None
+            }
+            Input { key: Key::Backspace, .. } => {
+                query.pop();
+            }
+            Input { key: Key::Char(c), .. } => query.push(c),
+            _ => {}
+        }
+        return Ok(());
+    }
+    // a command is read again from its first key whenever one more is typed,
+    // until it has all of them
+    vim.expire();
+    vim.ahead = std::mem::take(&mut vim.pending).into();
+    vim.ahead.push_back(input);
+    let input = vim.ahead.pop_front().unwrap();
+    match vim_command(input, textarea, focus, inputmode, vim) {
+        Err(e)
+            if e.downcast_ref::<io::Error>()
+                .is_some_and(|e| e.kind() == io::ErrorKind::WouldBlock) =>
+        {
+            vim.pending = std::mem::take(&mut vim.recording);
+            vim.since = Instant::now();
+            Ok(())
+        }
+        result => result,
+    }
+}
+
+/// Runs the command starting with `input`, taking the keys after it from
+/// [`Vim::read`].
+fn vim_command(
+    input: Input,
+    textarea: &mut TextArea,
+    focus: &mut Focus,
+    inputmode: &mut InputMode,
+    vim: &mut Vim,
+) -> Result<(), Box<dyn std::error::Error>> {
+    vim.recording = vec![input.clone()];
+    let (count, input) = vim.count(input)?;
+    let n = count.unwrap_or(1);
+    let normal = matches!(inputmode, InputMode::Normal);
+    let change = normal
+        && matches!(
+            input.key,
+            Key::Char('i' | 'A' | 'o' | 'O' | 'p' | 'x' | '>' | '<' | 'd')
+        );
+
+    match input {
+        // normal mode
+        Input { key: Key::Backspace, .. } => {
+            if matches!(inputmode, InputMode::Normal) {
+                *focus = Focus::Feed;
+            }
+        }
+        Input { key: Key::Char('i'), .. } => {
+            if matches!(inputmode, InputMode::Normal) {
+                *inputmode = InputMode::Insert;
+            }
+        }
+        Input { key: Key::Char('A'), .. } => {
+            if matches!(inputmode, InputMode::Normal) {
+                textarea.move_cursor(CursorMove::End);
+                *inputmode = InputMode::Insert;
+            }
+        }
+        Input { key: Key::Char('o'), .. } => {
+            if matches!(inputmode, InputMode::Normal) {
+                textarea.move_cursor(CursorMove::End);
+                textarea.insert_newline();
+                *inputmode = InputMode::Insert;
+            }
+        }
+        Input { key: Key::Char('O'), .. } => {
+            if matches!(inputmode, InputMode::Normal) {
+                textarea.move_cursor(CursorMove::Head);
+                textarea.insert_newline();
+                textarea.move_cursor(CursorMove::Up);
+                *inputmode = InputMode::Insert;
+            }
+        }
+        Input { key: Key::Char('p'), .. } => {
+            for _ in 0..n {
+                textarea.paste();
+            }
+        }
+        Input { key: Key::Char('u'), .. } => {
+            for _ in 0..n {
+                textarea.undo();
+            }
+        }
+        Input { key: Key::Char('r'), ctrl: true, .. } => {
+            for _ in 0..n {
+                textarea.redo();
+            }
+        }
+        Input { key: Key::Char('.'), .. } if normal => {
+            let last_change = vim.last_change.clone();
+            for _ in 0..n {
+                vim.replay.extend(last_change.iter().cloned());
+                while let Some(input) = vim.replay.pop_front() {
+                    if matches!(inputmode, InputMode::Insert) {
+                        insert_event(input, textarea, inputmode, vim);
+                    } else {
+                        textarea_event(input, textarea, focus, inputmode, vim)?;
+                    }
+                }
+            }
+            vim.last_change = last_change;
+        }
+        Input { key: Key::Char('/'), .. } if *focus == Focus::NewNote => {
+            vim.search = Some(String::new());
+        }
+        Input { key: Key::Char('n'), .. } => {
+            for _ in 0..n {
+                textarea.search_forward(false);
+            }
+        }
+        Input { key: Key::Char('N'), .. } => {
+            for _ in 0..n {
+                textarea.search_back(false);
+            }
+        }
+        Input { key: Key::Char('v'), .. } => {
+            if matches!(*inputmode, InputMode::Normal) {
+                textarea.start_selection();
+                *inputmode = InputMode::View;
+            }
+        }
+        Input { key: Key::Char('V'), .. } => match *inputmode {
+            InputMode::Normal => {
+                vim.line_anchor = textarea.cursor().0;
+                *inputmode = InputMode::ViewLine;
+            }
+            InputMode::ViewLine => {
+                textarea.cancel_selection();
+                *inputmode = InputMode::Normal;
+            }
+            _ => {}
+        },
+        Input { key: Key::Char('x'), .. } => {
+            for _ in 0..n {
+                textarea.delete_next_char();
+            }
+        }
+        Input { key: Key::Char(c @ ('>' | '<')), .. } => match *inputmode {
+            InputMode::Normal => {
+                if matches!(vim.read()?, Input { key: Key::Char(k), .. } if k == c)
+                {
+                    let y = textarea.cursor().0;
+                    shift_lines(textarea, y, y + n - 1, c == '>');
+                }
+            }
+            InputMode::ViewLine => {
+                let (from, to) = vim.line_range(textarea);
+                shift_lines(textarea, from, to, c == '>');
+                textarea.move_cursor(CursorMove::Jump(from as u16, 0));
+                *inputmode = InputMode::Normal;
+            }
+            _ => {}
+        },
+
+        // universal movement
+        Input { key: Key::Char('h'), .. } => {
+            for _ in 0..n {
+                textarea.move_cursor(CursorMove::Back)
+            }
+        }
+        Input { key: Key::Char('j'), .. } if vim.display_lines => {
+            move_display_lines(textarea, vim.width, n, false)
+        }
+        Input { key: Key::Char('k'), .. } if vim.display_lines => {
+            move_display_lines(textarea, vim.width, n, true)
+        }
+        Input { key: Key::Char('j'), .. } => {
+            for _ in 0..n {
+                textarea.move_cursor(CursorMove::Down)
+            }
+        }
+        Input { key: Key::Char('k'), .. } => {
+            for _ in 0..n {
+                textarea.move_cursor(CursorMove::Up)
+            }
+        }
+        Input { key: Key::Char('l'), .. } => {
+            for _ in 0..n {
+                textarea.move_cursor(CursorMove::Forward)
+            }
+        }
+        Input { key: Key::Char('w'), .. } => {
+            for _ in 0..n {
+                textarea.move_cursor(CursorMove::WordForward)
+            }
+        }
+        Input { key: Key::Char('b'), .. } => {
+            for _ in 0..n {
+                textarea.move_cursor(CursorMove::WordBack)
+            }
+        }
+        Input { key: Key::Char('e'), .. } => {
+            for _ in 0..n {
+                textarea.move_cursor(CursorMove::WordEnd)
+            }
+        }
+        Input { key: Key::Char('^'), .. } => {
+            textarea.move_cursor(CursorMove::Head)
+        }
+        Input { key: Key::Char('$'), .. } => {
+            textarea.move_cursor(CursorMove::End)
+        }
+        Input { key: Key::Char('g'), .. } => match vim.read()? {
+            Input { key: Key::Char('g'), .. } => match count {
+                Some(line) => go_to_line(textarea, line),
+                None => textarea.move_cursor(CursorMove::Top),
+            },
+            Input { key: Key::Char('j'), .. } => {
+                move_display_lines(textarea, vim.width, n, false)
+            }
+            Input { key: Key::Char('k'), .. } => {
+                move_display_lines(textarea, vim.width, n, true)
+            }
+            // gqq and gqap rewrap the paragraph, gq the lines selected with V
+            Input { key: Key::Char('q'), .. } => match *inputmode {
+                InputMode::Normal => {
+                    let paragraph = match vim.read()? {
+                        Input { key: Key::Char('q'), .. } => true,
+                        Input { key: Key::Char('a'), .. } => matches!(
+                            vim.read()?,
+                            Input { key: Key::Char('p'), .. }
+                        ),
+                        _ => false,
+                    };
+                    if paragraph {
+                        let (from, to) = paragraph_range(textarea);
+                        reflow_lines(textarea, from, to, vim.text_width);
+                    }
+                }
+                InputMode::ViewLine => {
+                    let (from, to) = vim.line_range(textarea);
+                    textarea.cancel_selection();
+                    reflow_lines(textarea, from, to, vim.text_width);
+                    *inputmode = InputMode::Normal;
+                }
+                _ => {}
+            },
+            _ => {}
+        },
+        Input { key: Key::Char('G'), .. } => match count {
+            Some(line) => go_to_line(textarea, line),
+            None => textarea.move_cursor(CursorMove::Bottom),
+        },
+
+        Input { key: Key::Char('d'), .. } => match *inputmode {
+            InputMode::Normal => {
+                // both counts multiply, as in 2d3w
+                let e = vim.read()?;
+                let (motion_count, e) = vim.count(e)?;
+                let n = n * motion_count.unwrap_or(1);
+                match e {
+                    Input { key: Key::Char('d'), .. } => {
+                        for _ in 0..n {
+                            textarea.move_cursor(CursorMove::Head);
+                            textarea.delete_line_by_end();
+                            textarea.delete_newline();
+                            textarea.move_cursor(CursorMove::Down);
+                        }
+                    }
+                    Input { key: Key::Char('w'), .. } => {
+                        textarea.start_selection();
+                        for _ in 0..n {
+                            textarea.move_cursor(CursorMove::WordForward);
+                        }
+                        textarea.cut();
+                        textarea.cancel_selection();
+                    }
+                    Input { key: Key::Char('b'), .. } => {
+                        for _ in 0..n {
+                            textarea.delete_word();
+                        }
+                    }
+                    Input { key: Key::Char('i'), .. } => {
+                        if matches!(
+                            vim.read()?,
+                            Input { key: Key::Char('w'), .. }
+                        ) {
+                            textarea.move_cursor(CursorMove::WordBack);
+                            textarea.delete_next_word();
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            InputMode::View => {
+                textarea.move_cursor(CursorMove::Forward);
+                textarea.cut();
+                *inputmode = InputMode::Normal;
+            }
+            InputMode::ViewLine => {
+                let (from, to) = vim.line_range(textarea);
+                select_whole_lines(textarea, from, to);
+                textarea.cut();
+                textarea.move_cursor(CursorMove::Head);
+                *inputmode = InputMode::Normal;
+            }
+            InputMode::Insert => {}
+        },
+        Input { key: Key::Char('y'), .. } => match *inputmode {
+            InputMode::View => {
+                textarea.move_cursor(CursorMove::Forward);
+                textarea.copy();
+                textarea.cancel_selection();
+                *inputmode = InputMode::Normal;
+            }
+            InputMode::ViewLine => {
+                let (from, to) = vim.line_range(textarea);
+                select_whole_lines(textarea, from, to);
+                textarea.copy();
+                textarea.cancel_selection();
+                textarea.move_cursor(CursorMove::Jump(from as u16, 0));
+                *inputmode = InputMode::Normal;
+            }
+            _ => {}
+        },
+
+        Input { key: Key::Esc, .. } => {
+            if matches!(inputmode, InputMode::View | InputMode::ViewLine) {
+                textarea.cancel_selection();
+                *inputmode = InputMode::Normal;
+            }
+        }
+        _ => {}
+    };
+
+    // the selection is stretched to whole lines after every motion
+    if matches!(inputmode, InputMode::ViewLine) {
+        let row = textarea.cursor().0;
+        let len = |y: usize| textarea.lines()[y].chars().count() as u16;
+        let (anchor, cursor) = if row >= vim.line_anchor {
+            ((vim.line_anchor, 0), (row, len(row)))
+        } else {
+            ((vim.line_anchor, len(vim.line_anchor)), (row, 0))
+        };
+        textarea.cancel_selection();
+        textarea.move_cursor(CursorMove::Jump(anchor.0 as u16, anchor.1));
+        textarea.start_selection();
+        textarea.move_cursor(CursorMove::Jump(cursor.0 as u16, cursor.1));
+    }
+
+    if change {
+        if matches!(inputmode, InputMode::Insert) {
+            // the change goes on until insert mode is left
+            vim.inserting = true;
+        } else {
+            vim.last_change = std::mem::take(&mut vim.recording);
+        }
+    }
+    Ok(())
+}
+
+/// A composer filled in with template `name`, the cursor at the end.
+fn template_textarea(
+    name: &str,
+) -> Result<TextArea<'static>, Box<dyn std::error::Error>> {
+    let text = templates::load(name)?;
+    let mut textarea =
+        TextArea::new(text.lines().map(|l| l.to_string()).collect());
+    textarea.move_cursor(CursorMove::Bottom);
+    textarea.move_cursor(CursorMove::End);
+    Ok(textarea)
+}
+
+/// Indents lines `from` to `to` by four spaces, or takes up to four leading
+/// spaces away from them.
+fn shift_lines(textarea: &mut TextArea, from: usize, to: usize, right: bool) {
+    let (y, x) = textarea.cursor();
+    let mut lines = textarea.clone().into_lines();
+    let to = to.min(lines.len() - 1);
+    for line in &mut lines[from..=to] {
+        if right {
+            let mut new_line = String::from("    ");
+            new_line += line;
+            *line = new_line;
+        } else {
+            let mut count = 0;
+            *line = line
+                .chars()
+                .skip_while(|c| {
+                    count += 1;
+                    *c == ' ' && count <= 4
+                })
+                .collect();
+        }
+    }
+    *textarea = TextArea::new(lines);
+    textarea.move_cursor(CursorMove::Jump(y as u16, x as u16));
+}
+
+/// The lines of the paragraph the cursor is in, up to the blank lines around
+/// it.
+fn paragraph_range(textarea: &TextArea) -> (usize, usize) {
+    let lines = textarea.lines();
+    let blank = |y: usize| lines[y].trim().is_empty();
+    let y = textarea.cursor().0;
+    let mut from = y;
+    while from > 0 && !blank(from - 1) {
+        from -= 1;
+    }
+    let mut to = y;
+    while to + 1 < lines.len() && !blank(to + 1) {
+        to += 1;
+    }
+    (from, to)
+}
+
+/// Rewraps lines `from` to `to` at `width` columns, leaving the cursor at
+/// the start of the first.
+fn reflow_lines(textarea: &mut TextArea, from: usize, to: usize, width: usize) {
+    let mut lines = textarea.clone().into_lines();
+    let to = to.min(lines.len() - 1);
+    let wrapped = reflow(&lines[from..=to], width);
+    lines.splice(from..=to, wrapped);
+    *textarea = TextArea::new(lines);
+    textarea.move_cursor(CursorMove::Jump(from as u16, 0));
+}
+
+/// Joins `lines` into paragraphs and breaks them again so they fit in
+/// `width` columns, except for words longer than that. Blank lines and list
+/// items start new paragraphs. A paragraph keeps the indentation of its first
+/// line, and the rest of a list item is lined up with its text.
+fn reflow(lines: &[String], width: usize) -> Vec<String> {
+    let fill =
+        |out: &mut Vec<String>, words: &[&str], first: &str, rest: &str| {
+            if words.is_empty() {
+                return;
+            }
+            let mut line = first.to_string();
+            let mut empty = true;
+            for word in words {
+                let len = line.chars().count() + word.chars().count();
+                if !empty && len + 1 > width {
+                    out.push(std::mem::replace(&mut line, rest.to_string()));
+                    empty = true;
+                }
+                if !empty {
+                    line.push(' ');
+                }
+                line += word;
+                empty = false;
+            }
+            out.push(line);
+        };
+
+    let mut out = Vec::new();
+    let mut words: Vec<&str> = Vec::new();
+    let (mut first, mut rest) = (String::new(), String::new());
+    for line in lines {
+        let text = line.trim_start();
+        if text.is_empty() {
+            fill(&mut out, &words, &first, &rest);
+            words.clear();
+            out.push(String::new());
+            continue;
+        }
+        let marker = list_marker(text);
+        if words.is_empty() || marker.is_some() {
+            fill(&mut out, &words, &first, &rest);
+            words.clear();
+            let indent = &line[..line.len() - text.len()];
+            let marker = marker.unwrap_or("");
+            first = format!("{}{}", indent, marker);
+            rest = format!("{}{}", indent, " ".repeat(marker.chars().count()));
+            words.extend(text[marker.len()..].split_whitespace());
+        } else {
+            words.extend(text.split_whitespace());
+        }
+    }
+    fill(&mut out, &words, &first, &rest);
+    out
+}
+
+/// The bullet, number or checkbox `line` starts with, with the space after
+/// it, if it is a list item.
+fn list_marker(line: &str) -> Option<&str> {
+    if let Some((_, item)) = checkbox(line) {
+        return Some(&line[..line.len() - item.len()]);
+    }
+    if ["- ", "* ", "+ "].iter().any(|b| line.starts_with(b)) {
+        return Some(&line[..2]);
+    }
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    if digits > 0 && line[digits..].starts_with(". ") {
+        return Some(&line[..digits + 2]);
+    }
+    None
+}
+
+/// Breaks the line under the cursor at its last space before column `width`
+/// once typing has gone past it.
+fn break_line(textarea: &mut TextArea, width: usize) {
+    let (y, x) = textarea.cursor();
+    let line: Vec<char> = textarea.lines()[y].chars().collect();
+    if x <= width || line.len() <= width {
+        return;
+    }
+    let Some(space) = line[..=width].iter().rposition(|&c| c == ' ') else {
+        return;
+    };
+    // nothing but indentation before it
+    if line[..space].iter().all(|&c| c == ' ') {
+        return;
+    }
+    textarea.move_cursor(CursorMove::Jump(y as u16, space as u16));
+    textarea.delete_next_char();
+    textarea.insert_newline();
+    textarea
+        .move_cursor(CursorMove::Jump(y as u16 + 1, (x - space - 1) as u16));
+}
+
+/// Selects lines `from` to `to` along with a line break, so cutting them
+/// leaves no empty line behind.
+fn select_whole_lines(textarea: &mut TextArea, from: usize, to: usize) {
+    let len = |y: usize| textarea.lines()[y].chars().count() as u16;
+    let (start, end) = if to + 1 < textarea.lines().len() {
+        ((from, 0), (to + 1, 0))
+    } else if from > 0 {
+        ((from - 1, len(from - 1)), (to, len(to)))
+    } else {
+        ((from, 0), (to, len(to)))
+    };
+    textarea.cancel_selection();
+    textarea.move_cursor(CursorMove::Jump(start.0 as u16, start.1));
+    textarea.start_selection();
+    textarea.move_cursor(CursorMove::Jump(end.0 as u16, end.1));
+}
+
+/// Moves the cursor to the start of line `line`, counted from 1.
+/// Moves the cursor `n` display lines down, or up when `up`, keeping its
+/// column within them. A display line is as much of a line as fits across
+/// `width` columns, so long lines take several.
+fn move_display_lines(
+    textarea: &mut TextArea,
+    width: usize,
+    n: usize,
+    up: bool,
+) {
+    let width = width.max(1);
+    let lines = textarea.lines();
+    // display lines taken by each line, an empty one still taking one
+    let parts = |row: usize| lines[row].chars().count().max(1).div_ceil(width);
+    let (mut row, col) = textarea.cursor();
+    let mut part = (col / width).min(parts(row) - 1);
+    let column = col - part * width;
+    for _ in 0..n {
+        if up && part > 0 {
+            part -= 1;
+        } else if up && row > 0 {
+            row -= 1;
+            part = parts(row) - 1;
+        } else if !up && part + 1 < parts(row) {
+            part += 1;
+        } else if !up && row + 1 < lines.len() {
+            row += 1;
+            part = 0;
+        }
+    }
+    let col = (part * width + column).min(lines[row].chars().count());
+    textarea.move_cursor(CursorMove::Jump(row as u16, col as u16));
+}
+
+fn go_to_line(textarea: &mut TextArea, line: usize) {
+    let y = line.clamp(1, textarea.lines().len()) - 1;
+    textarea.move_cursor(CursorMove::Jump(y as u16, 0));
+}
+
+/// A popup of `width` by `height` centered horizontally in `area`, `top`
+/// rows down. It is shrunk and moved up as needed to fit small terminals.
+fn popup_area(area: Rect, width: u16, height: u16, top: u16) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + top.min(area.height - height),
+        width,
+        height,
+    }
+}
+
+/// Underlines the words drawn in `area` that `speller` found misspelled,
+/// going by what was drawn so it does not matter how the text is scrolled.
+fn underline_misspelled(buf: &mut Buffer, area: Rect, speller: &Speller) {
+    for y in area.top()..area.bottom() {
+        // the row as text, with the column each character starts at
+        let mut row = String::new();
+        let mut columns = Vec::new();
+        for x in area.left()..area.right() {
+            columns.push((row.len(), x));
+            row.push_str(buf[(x, y)].symbol());
+        }
+        for (range, word) in spell::words(&row) {
+            if !speller.is_misspelled(word) {
+                continue;
+            }
+            for &(_, x) in columns.iter().filter(|(i, _)| range.contains(i)) {
+                buf[(x, y)].set_style(
+                    Style::default()
+                        .fg(Color::Red)
+                        .add_modifier(Modifier::UNDERLINED),
+                );
+            }
+        }
+    }
+}
+
+/// Where the cursor of `textarea` is when it is drawn in `area`, for the
+/// terminal's own cursor to be put there so input methods show what is being
+/// composed in the right place. TextArea keeps how far it scrolled to itself,
+/// so this draws a copy off screen with a cursor nothing else looks like.
+fn cursor_position(textarea: &TextArea, area: Rect) -> Option<Position> {
+    let mut textarea = textarea.clone();
+    textarea.set_cursor_style(Style::default().add_modifier(Modifier::HIDDEN));
+    let mut buf = Buffer::empty(area);
+    (&textarea).render(area, &mut buf);
+    for y in area.top()..area.bottom() {
+        for x in area.left()..area.right() {
+            if buf[(x, y)].modifier.contains(Modifier::HIDDEN) {
+                return Some(Position { x, y });
+            }
+        }
+    }
+    None
+}
+
+/// Where the zen composer goes on `screen`: a column as wide as the lines are
+/// wrapped, in the middle, with a blank line above and below.
+fn zen_area(screen: Rect, text_width: usize) -> Rect {
+    let width = (text_width as u16).saturating_add(1).min(screen.width);
+    Rect {
+        x: screen.x + (screen.width - width) / 2,
+        y: screen.y + 1,
+        width,
+        height: screen.height.saturating_sub(2),
+    }
+}
+
+/// Draws `textarea` into `area` scrolled so the cursor's line is in the
+/// middle of it, with blank lines above the first line when it is near the
+/// top, like a typewriter. Returns where the cursor is.
+fn draw_typewriter(
+    textarea: &TextArea,
+    area: Rect,
+    buf: &mut Buffer,
+) -> Option<Position> {
+    // drawn whole first, then the lines around the cursor copied over
+    let mut whole = textarea.clone();
+    whole.set_cursor_style(
+        textarea.cursor_style().add_modifier(Modifier::HIDDEN),
+    );
+    let height = textarea.lines().len().min(i16::MAX as usize) as u16;
+    let mut lines =
+        Buffer::empty(Rect { x: 0, y: 0, width: area.width, height });
+    // drawn once as tall as the text, so that scrolling back to its top
+    // leaves the cursor where it is
+    (&whole).render(lines.area, &mut lines);
+    whole.scroll((-(height as i16), 0));
+    lines.reset();
+    (&whole).render(lines.area, &mut lines);
+
+    let top = textarea.cursor().0 as i64 - (area.height / 2) as i64;
+    let mut cursor = None;
+    for y in 0..area.height {
+        let Ok(row) = u16::try_from(top + y as i64) else {
+            continue;
+        };
+        if row >= height {
+            break;
+        }
+        for x in 0..area.width {
+            let mut cell = lines[(x, row)].clone();
+            if cell.modifier.contains(Modifier::HIDDEN) {
+                cell.modifier.remove(Modifier::HIDDEN);
+                cursor = Some(Position { x: area.x + x, y: area.y + y });
+            }
+            buf[(area.x + x, area.y + y)] = cell;
+        }
+    }
+    cursor
+}
+
+/// The candidates of `completion` in a list under the cursor at `at`, or
+/// over it if there is no room below.
+fn draw_completion(
+    buf: &mut Buffer,
+    screen: Rect,
+    completion: &Completion,
+    at: Position,
+    theme: &Theme,
+) {
+    let width = completion
+        .candidates
+        .iter()
+        .map(|c| Line::from(c.label.as_str()).width() as u16)
+        .max()
+        .unwrap_or(0)
+        + 2;
+    let height = completion.candidates.len() as u16 + 2;
+    let y = if at.y + 1 + height <= screen.bottom() {
+        at.y + 1
+    } else {
+        at.y.saturating_sub(height)
+    };
+    let x = at.x.min(screen.right().saturating_sub(width));
+    let area = Rect { x, y, width, height }.intersection(screen);
+    let lines: Vec<Line> = completion
+        .candidates
+        .iter()
+        .enumerate()
+        .map(|(i, c)| {
+            let line = Line::from(c.label.as_str());
+            if i == completion.selected {
+                line.style(Style::default().bg(theme.selection_bg))
+            } else {
+                line
+            }
+        })
+        .collect();
+    Clear.render(area, buf);
+    Paragraph::new(lines)
+        .block(Block::bordered().border_type(theme.border_type))
+        .render(area, buf);
+}
+
+/// Where the cards of a list drawn in `area` ended up, as positions in the
+/// list with the area of each, so mouse clicks can be mapped back to notes.
+/// This follows how the list lays cards out: down from the scroll offset, with
+/// the first one cut off at the top when the selected card would not fit
+/// otherwise.
+fn card_areas(
+    area: Rect,
+    state: &ListState,
+    len: usize,
+    height: impl Fn(usize) -> u16,
+) -> Vec<(usize, Rect)> {
+    if len == 0 {
+        return Vec::new();
+    }
+    let offset = state.scroll_offset_index();
+    let bottom = area.bottom() as i32;
+    let mut y = area.y as i32;
+    if let Some(selected) = state.selected {
+        let total: i32 =
+            (offset..=selected.min(len - 1)).map(|i| height(i) as i32).sum();
+        y -= (total - area.height as i32).max(0);
+    }
+
+    let mut cards = Vec::new();
+    for i in offset..len {
+        if y >= bottom {
+            break;
+        }
+        let end = y + height(i) as i32;
+        let top = y.max(area.y as i32);
+        if end.min(bottom) > top {
+            cards.push((
+                i,
+                Rect {
+                    y: top as u16,
+                    height: (end.min(bottom) - top) as u16,
+                    ..area
+                },
+            ));
+        }
+        y = end;
+    }
+    cards
+}
+
+/// Scrolls or selects in a list for a mouse event, given the card areas from
+/// [`card_areas`]. Returns whether the event was the second click of a
+/// double click on the same card.
+fn mouse_event(
+    mouse: MouseEvent,
+    cards: &[(usize, Rect)],
+    state: &mut ListState,
+    last_click: &mut Option<(usize, Instant)>,
+) -> bool {
+    match mouse.kind {
+        MouseEventKind::ScrollDown => state.next(),
+        MouseEventKind::ScrollUp => state.previous(),
+        MouseEventKind::Down(MouseButton::Left) => {
+            let position = Position { x: mouse.column, y: mouse.row };
+            let Some(&(i, _)) =
+                cards.iter().find(|(_, area)| area.contains(position))
+            else {
+                return false;
+            };
+            state.select(Some(i));
+            let double = last_click.is_some_and(|(last, at)| {
+                last == i && at.elapsed() < Duration::from_millis(400)
+            });
+            *last_click = if double { None } else { Some((i, Instant::now())) };
+            return double;
+        }
+        _ => {}
+    }
+    false
+}
+
+/// A feed card, moved right by `indent` columns for replies, which is how
+/// they sit under their parent, and with the day heading above it when it is
+/// the first note of a day in the journal layout.
+struct Card {
+    indent: u16,
+    /// Lines above the card, like the day it starts in the journal layout.
+    header: Vec<Line<'static>>,
+    body: Paragraph<'static>,
+}
+
+impl Widget for Card {
+    fn render(self, mut area: Rect, buf: &mut Buffer) {
+        for line in self.header {
+            line.render(Rect { height: 1.min(area.height), ..area }, buf);
+            area.y += 1.min(area.height);
+            area.height = area.height.saturating_sub(1);
+        }
+        let indent = self.indent.min(area.width);
+        let area =
+            Rect { x: area.x + indent, width: area.width - indent, ..area };
+        self.body.render(area, buf);
+    }
+}
+
+/// The rows `body` takes up inside `block` in `width` columns.
+/// `Paragraph::line_count` wraps the text at the width of the block rather
+/// than of its inside, which undercounts the lines of long notes, most of
+/// all ones in wide characters like CJK and emoji.
+fn card_height(body: &Paragraph, block: &Block, width: u16) -> u16 {
+    let outer = Rect { x: 0, y: 0, width, height: u16::MAX };
+    let inner = block.inner(outer);
+    let lines = body.line_count(inner.width) as u16;
+    lines.saturating_add(outer.height - inner.height)
+}
+
+/// A note on one line, for the compact and split layouts: its date, then
+/// its title as far as it fits.
+fn compact_line(note: &Note, date: String, marked: bool) -> Line<'static> {
+    let mut title = String::new();
+    if marked {
+        title += "● ";
+    }
+    if note.starred {
+        title += "★ ";
+    }
+    title += note.title();
+    let mut line = Line::from(vec![
+        Span::styled(date + " ", Style::default().fg(Color::DarkGray)),
+        Span::raw(title),
+    ]);
+    if let Some(color) = note.color {
+        line = line.style(Style::default().fg(color_of(color)));
+    }
+    line
+}
+
+/// The notebook notes are moved to when archived in a review.
+const ARCHIVE: &str = "archive";
+
+/// What can be reviewed: the notes written in the last so many days, or
+/// with `None` the notes in the feed as it is filtered.
+const REVIEW_PERIODS: [(&str, Option<u64>); 4] = [
+    ("the last week", Some(7)),
+    ("the last month", Some(30)),
+    ("the last year", Some(365)),
+    ("the notes in the feed", None),
+];
+
+/// A review under way, going through notes one at a time.
+#[derive(Default)]
+struct Review {
+    notes: Vec<NoteId>,
+    at: usize,
+    /// The tag being typed for the note.
+    tag: Option<String>,
+    kept: usize,
+    archived: usize,
+    tagged: usize,
+    deleted: usize,
+}
+
+impl Review {
+    /// The index in `feed` of the note being reviewed, passing over notes
+    /// gone since the review started. `None` once the review is over.
+    fn current(&mut self, feed: &Feed) -> Option<usize> {
+        while let Some(&id) = self.notes.get(self.at) {
+            match feed.notes.iter().position(|n| n.id == id) {
+                Some(i) if feed.notes[i].deleted_at.is_none() => {
+                    return Some(i)
+                }
+                _ => self.at += 1,
+            }
+        }
+        None
+    }
+
+    fn summary(&self) -> String {
+        format!(
+            "reviewed {} notes: {} kept, {} archived, {} tagged, {} deleted",
+            self.kept + self.archived + self.tagged + self.deleted,
+            self.kept,
+            self.archived,
+            self.tagged,
+            self.deleted
+        )
+    }
+}
+
+/// The lock screen, entered with `L` or after `lock_after` minutes without
+/// input.
+#[derive(Default)]
+struct Lock {
+    /// The passphrase to unlock notes that are not encrypted with, chosen
+    /// the first time they are locked.
+    passphrase: Option<String>,
+    /// The passphrase being chosen, typed once and waiting to be repeated.
+    first: Option<String>,
+    /// Go back once the passphrase is chosen, rather than locking with it.
+    choose_only: bool,
+    typed: String,
+    /// Why the last passphrase typed was not taken.
+    message: &'static str,
+    /// What was in focus when the screen was locked.
+    from: Option<Focus>,
+}
+
+impl Lock {
+    fn unlocks(&self, typed: &str, cipher: Option<&Cipher>) -> bool {
+        match cipher {
+            Some(cipher) => cipher.is_passphrase(typed),
+            None => self.passphrase.as_deref() == Some(typed),
+        }
+    }
+}
+
+/// The journal layout: the feed grouped under a heading for every day.
+struct Journal {
+    on: bool,
+    /// Days showing only their first note.
+    collapsed: HashSet<NaiveDate>,
+}
+
+/// The feed as shown: filtered, sorted and, in the journal layout, grouped
+/// by day.
+fn build_view(
+    feed: &Feed,
+    filter: &str,
+    sort: Sort,
+    journal: &Journal,
+) -> FeedView {
+    let view = FeedView::filter(feed, filter, sort);
+    if journal.on {
+        view.group_by_day(feed, &journal.collapsed)
+    } else {
+        view
+    }
+}
+
+/// "▾ Monday, 3 June 2024 · 4 notes"
+/// A rule across `width` columns marking where the notes added since the
+/// last session start, which are above it when `above`.
+fn unread_divider(above: bool, width: u16) -> Line<'static> {
+    let label =
+        format!(" {} new since last time ", if above { "↑" } else { "↓" });
+    let fill = (width as usize).saturating_sub(label.chars().count());
+    Line::styled(
+        format!(
+            "{}{}{}",
+            "─".repeat(fill / 2),
+            label,
+            "─".repeat(fill - fill / 2)
+        ),
+        Style::default().fg(Color::Yellow),
+    )
+}
+
+fn day_header(header: &DayHeader) -> Line<'static> {
+    Line::styled(
+        format!(
+            "{} {} · {} {}",
+            if header.collapsed { "▸" } else { "▾" },
+            header.day.format("%A, %-d %B %Y"),
+            header.notes,
+            if header.notes == 1 { "note" } else { "notes" },
+        ),
+        Style::default().add_modifier(Modifier::BOLD),
+    )
+}
+
+/// The month of `day` as weeks starting on `week_start`, each a row of days
+/// over a row of how many notes were written on them, with `day` picked out
+/// and `today` in bold.
+fn calendar(
+    day: NaiveDate,
+    today: NaiveDate,
+    week_start: Weekday,
+    per_day: &BTreeMap<NaiveDate, usize>,
+    selection_bg: Color,
+) -> Text<'static> {
+    let first = day.with_day(1).unwrap();
+    let mut week =
+        first - Days::new(first.weekday().days_since(week_start) as u64);
+    let mut text = Text::from(Line::styled(
+        (0..7)
+            .map(|k| {
+                format!(
+                    "{:>4} ",
+                    &(week + Days::new(k)).weekday().to_string()[..2]
+                )
+            })
+            .collect::<String>(),
+        Style::default().add_modifier(Modifier::BOLD),
+    ));
+    while week < first || week.month() == first.month() {
+        let mut days = Vec::new();
+        let mut counts = Vec::new();
+        for d in (0..7).map(|k| week + Days::new(k)) {
+            if d.month() != first.month() {
+                days.push(Span::raw("     "));
+                counts.push(Span::raw("     "));
+                continue;
+            }
+            let mut style = Style::default();
+            if d == today {
+                style = style.add_modifier(Modifier::BOLD);
+            }
+            if d == day {
+                style = style.bg(selection_bg);
+            }
+            days.push(Span::styled(format!("{:>4} ", d.day()), style));
+            let count = match per_day.get(&d) {
+                Some(n) => format!("{:>4} ", n),
+                None => "     ".into(),
+            };
+            counts.push(Span::styled(count, style.fg(Color::Green)));
+        }
+        text.push_line(Line::from(days));
+        text.push_line(Line::from(counts));
+        week = week + Days::new(7);
+    }
+    text
+}
+
+/// The name of the current mode shown at the start of the status bar.
+fn mode_label(
+    strings: &Strings,
+    focus: &Focus,
+    inputmode: &InputMode,
+) -> String {
+    let labels = &strings.labels;
+    let name = match focus {
+        Focus::Feed => labels.feed,
+        Focus::Trash => labels.trash,
+        Focus::ConfirmDelete => labels.delete,
+        Focus::Tags => labels.tags,
+        Focus::Notebooks => labels.notebooks,
+        Focus::Read => labels.read,
+        Focus::Messages => labels.messages,
+        Focus::Attachments => labels.attachments,
+        Focus::Checkboxes => labels.toggle,
+        Focus::SplitNote => labels.split,
+        Focus::Templates => labels.templates,
+        Focus::Stats => labels.stats,
+        Focus::Topics => labels.topics,
+        Focus::Colors => labels.color,
+        Focus::Spelling => labels.spelling,
+        Focus::ReviewEdit => labels.review,
+        Focus::Duplicate => labels.duplicate,
+        Focus::Revisions => labels.versions,
+        Focus::Switcher => labels.go_to_note,
+        Focus::Urls => labels.links,
+        Focus::ReviewPeriod | Focus::Review => labels.review,
+        Focus::Calendar => labels.calendar,
+        Focus::Lock => labels.locked,
+        // the views with a text area, which have modes
+        Focus::Scratch => labels.scratch,
+        Focus::SelectText => labels.select,
+        Focus::NewNote => labels.compose,
+        Focus::Filter => labels.filter,
+        Focus::JumpDate => labels.go_to_date,
+        Focus::NewNotebook => labels.new_notebook,
+        Focus::Attach => labels.attach,
+        Focus::TagNotes => labels.tag,
+        Focus::ExportNotes => labels.export,
+        Focus::Command => labels.command,
+        Focus::Locate => labels.location,
+    };
+    match focus {
+        Focus::Scratch
+        | Focus::SelectText
+        | Focus::NewNote
+        | Focus::Filter
+        | Focus::JumpDate
+        | Focus::NewNotebook
+        | Focus::Attach
+        | Focus::TagNotes
+        | Focus::ExportNotes
+        | Focus::Command
+        | Focus::Locate => {
+            format!("{} {}", name, mode_name(strings, inputmode).to_uppercase())
+        }
+        _ => name.into(),
+    }
+}
+
+/// What `inputmode` is called in the language of `strings`.
+fn mode_name(strings: &Strings, inputmode: &InputMode) -> &'static str {
+    match inputmode {
+        InputMode::Normal => strings.modes.normal,
+        InputMode::Insert => strings.modes.insert,
+        InputMode::View => strings.modes.view,
+        InputMode::ViewLine => strings.modes.view_line,
+    }
+}
+
+/// `new` set against `old` word by word, removed words struck out in red and
+/// added ones in green. Line breaks added or removed are shown as `↵`.
+fn diff_text(old: &str, new: &str) -> Text<'static> {
+    let mut text = Text::default();
+    let mut line = Line::default();
+    for (change, token) in diff::words(old, new) {
+        let style = match change {
+            Change::Same => Style::default(),
+            Change::Removed => Style::default()
+                .fg(Color::Red)
+                .add_modifier(Modifier::CROSSED_OUT),
+            Change::Added => Style::default().fg(Color::Green),
+        };
+        if change == Change::Removed {
+            line.push_span(Span::styled(token.replace('\n', "↵"), style));
+            continue;
+        }
+        let mut parts = token.split('\n');
+        line.push_span(Span::styled(parts.next().unwrap().to_string(), style));
+        for part in parts {
+            if change == Change::Added {
+                line.push_span(Span::styled("↵", style));
+            }
+            text.push_line(std::mem::take(&mut line));
+            line.push_span(Span::styled(part.to_string(), style));
+        }
+    }
+    text.push_line(line);
+    text
+}
+
+/// Splits `text` into lines with the matches of `highlighter` reversed,
+/// `[[links]]` and web addresses underlined, the `selected` one of the
+/// `[[links]]` reversed, and checkbox lines drawn as boxes, checked items
+/// crossed out.
+fn highlight(
+    text: &str,
+    highlighter: Option<&Regex>,
+    selected: Option<usize>,
+) -> Text<'static> {
+    // links on the lines before
+    let mut seen = 0;
+    let lines: Vec<Line> = text
+        .lines()
+        .map(|line| {
+            let mut spans = Vec::new();
+            let mut style = Style::default();
+            let mut line = line;
+            if let Some((checked, item)) = checkbox(line) {
+                let indent = &line[..line.len() - line.trim_start().len()];
+                spans.push(Span::raw(format!(
+                    "{}{} ",
+                    indent,
+                    if checked { "☑" } else { "☐" }
+                )));
+                if checked {
+                    style = style
+                        .fg(Color::DarkGray)
+                        .add_modifier(Modifier::CROSSED_OUT);
+                }
+                line = item;
+            }
+            let links: Vec<_> =
+                links(line).into_iter().map(|(r, _)| r).collect();
+            let urls = urls(line);
+            let matches: Vec<_> = highlighter
+                .iter()
+                .flat_map(|re| re.find_iter(line))
+                .filter(|m| !m.is_empty())
+                .map(|m| m.range())
+                .collect();
+            // the line is cut wherever a link, an address or a match starts
+            // or ends
+            let mut cuts = vec![0, line.len()];
+            for r in links.iter().chain(&urls).chain(&matches) {
+                cuts.extend([r.start, r.end]);
+            }
+            cuts.sort();
+            cuts.dedup();
+            for cut in cuts.windows(2) {
+                let (from, to) = (cut[0], cut[1]);
+                let within = |r: &Range<usize>| r.start <= from && to <= r.end;
+                let mut style = style;
+                if let Some(k) = links.iter().position(within) {
+                    style = style.patch(link_style());
+                    if selected == Some(seen + k) {
+                        style = style.add_modifier(Modifier::REVERSED);
+                    }
+                }
+                if urls.iter().any(within) {
+                    style = style.patch(link_style());
+                }
+                if matches.iter().any(within) {
+                    style = style.add_modifier(Modifier::REVERSED);
+                }
+                spans.push(Span::styled(line[from..to].to_string(), style));
+            }
+            seen += links.len();
+            Line::from(spans)
+        })
+        .collect();
+    Text::from(lines)
+}
+
+/// `text`, the lines of `note` drawn by [`highlight`], with the first line
+/// that is not blank, the note's title, in bold.
+fn bold_title(mut text: Text<'static>, note: &str) -> Text<'static> {
+    if let Some(k) = note.lines().position(|l| !l.trim().is_empty()) {
+        let line = std::mem::take(&mut text.lines[k]);
+        text.lines[k] = line.patch_style(Modifier::BOLD);
+    }
+    text
+}
+
+/// The terminal color a note marked with `color` is drawn in.
+fn color_of(color: NoteColor) -> Color {
+    match color {
+        NoteColor::Red => Color::Red,
+        NoteColor::Yellow => Color::Yellow,
+        NoteColor::Green => Color::Green,
+        NoteColor::Cyan => Color::Cyan,
+        NoteColor::Blue => Color::Blue,
+        NoteColor::Magenta => Color::Magenta,
+    }
+}
+
+fn link_style() -> Style {
+    Style::default().fg(Color::Blue).add_modifier(Modifier::UNDERLINED)
+}
+
+fn tag_style() -> Style {
+    Style::default().fg(Color::Cyan)
+}
+
+fn tag_line(tags: &[String]) -> Line<'static> {
+    let mut spans = vec![Span::raw(" ")];
+    for tag in tags {
+        spans.push(Span::styled(format!("#{}", tag), tag_style()));
+        spans.push(Span::raw(" "));
+    }
+    Line::from(spans)
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::{backend::TestBackend, Terminal};
+
+    use super::*;
+
+    /// A notebook holding `texts`, oldest first, in a directory of its own,
+    /// and a config that leaves saving to the tests.
+    struct Notebook {
+        feed: Feed,
+        data_file: PathBuf,
+        config: Config,
+    }
+
+    impl Notebook {
+        fn new(name: &str, texts: &[&str]) -> Notebook {
+            let dir = std::env::temp_dir().join(format!(
+                "feednotes-ui-{}-{}",
+                std::process::id(),
+                name
+            ));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            let data_file = dir.join("notes.json");
+            let mut feed = store::load(&data_file, None).unwrap();
+            for text in texts {
+                feed.push(Note::new(text.to_string()));
+            }
+            let config = Config {
+                language: Some(Language::En),
+                autosave: false,
+                ..Config::default()
+            };
+            Notebook { feed, data_file, config }
+        }
+
+        fn app(&mut self) -> App<'_> {
+            App::new(
+                &mut self.feed,
+                &mut self.data_file,
+                &self.config,
+                None,
+                false,
+            )
+            .unwrap()
+        }
+
+        fn clean_up(self) {
+            fs::remove_dir_all(self.data_file.parent().unwrap()).unwrap();
+        }
+    }
+
+    /// Sends `code` as the main loop would, after a draw, which is what
+    /// tells the lists how long they are.
+    fn press(app: &mut App, code: KeyCode) {
+        screen(app);
+        app.handle(Event::Key(KeyEvent::from(code)), false);
+        app.sync(None);
+    }
+
+    fn type_keys(app: &mut App, keys: &str) {
+        for c in keys.chars() {
+            press(app, KeyCode::Char(c));
+        }
+    }
+
+    /// The texts of the notes not deleted, sorted.
+    fn texts(app: &App) -> Vec<String> {
+        let mut texts: Vec<String> = app
+            .feed
+            .notes
+            .iter()
+            .filter(|n| n.deleted_at.is_none())
+            .map(|n| n.text.clone())
+            .collect();
+        texts.sort();
+        texts
+    }
+
+    /// The lines `app` draws on a screen of 60 by 20.
+    fn screen(app: &mut App) -> Vec<String> {
+        let mut terminal = Terminal::new(TestBackend::new(60, 20)).unwrap();
+        terminal.draw(|f| app.draw(f)).unwrap();
+        let buffer = terminal.backend().buffer();
+        buffer
+            .content
+            .chunks(buffer.area.width as usize)
+            .map(|row| row.iter().map(|cell| cell.symbol()).collect())
+            .collect()
+    }
+
+    fn shows(screen: &[String], text: &str) -> bool {
+        screen.iter().any(|line| line.contains(text))
+    }
+
+    #[test]
+    fn a_note_written_in_the_composer_is_added() {
+        let mut notebook = Notebook::new("compose", &["first"]);
+        let mut app = notebook.app();
+        type_keys(&mut app, "nihello");
+        assert!(app.focus == Focus::NewNote);
+        press(&mut app, KeyCode::Esc);
+        type_keys(&mut app, "W");
+
+        assert!(app.focus == Focus::Feed);
+        assert_eq!(texts(&app), ["first", "hello"]);
+        drop(app);
+        notebook.clean_up();
+    }
+
+    #[test]
+    fn deleting_asks_first_and_can_be_undone() {
+        let mut notebook = Notebook::new("delete", &["first", "second"]);
+        let mut app = notebook.app();
+        type_keys(&mut app, "jdd");
+        assert!(app.focus == Focus::ConfirmDelete);
+        type_keys(&mut app, "y");
+        assert!(app.focus == Focus::Feed);
+        assert_eq!(texts(&app).len(), 1);
+        assert_eq!(app.trash_view.refs.len(), 1);
+
+        type_keys(&mut app, "u");
+        assert_eq!(texts(&app), ["first", "second"]);
+        drop(app);
+        notebook.clean_up();
+    }
+
+    #[test]
+    fn the_delete_prompt_counts_the_marked_notes() {
+        let mut notebook = Notebook::new("marked", &["a", "b", "c"]);
+        let mut app = notebook.app();
+        type_keys(&mut app, "jvvdd");
+
+        let screen = screen(&mut app);
+        assert!(shows(&screen, "Delete 2 notes? (y/n)"));
+        assert!(shows(&screen, "2 marked"));
+        assert!(shows(&screen, "3 notes"));
+        press(&mut app, KeyCode::Esc);
+        assert_eq!(texts(&app), ["a", "b", "c"]);
+        drop(app);
+        notebook.clean_up();
+    }
+
+    #[test]
+    fn text_pasted_into_a_prompt_stays_on_one_line() {
+        let mut notebook = Notebook::new("paste", &["first"]);
+        let mut app = notebook.app();
+        type_keys(&mut app, "/");
+        assert!(app.focus == Focus::Filter);
+        app.handle(Event::Paste("one\r\ntwo".into()), false);
+
+        assert_eq!(app.textarea.lines(), ["one two"]);
+        drop(app);
+        notebook.clean_up();
+    }
+
+    #[test]
+    fn the_lock_screen_hides_the_notes_and_the_status_bar() {
+        let mut notebook = Notebook::new("lock", &["secret"]);
+        let mut app = notebook.app();
+        assert!(shows(&screen(&mut app), "secret"));
+        type_keys(&mut app, "L");
+
+        assert!(app.focus == Focus::Lock);
+        let screen = screen(&mut app);
+        assert!(shows(&screen, "Choose a passphrase to lock with"));
+        assert!(!shows(&screen, "secret"));
+        assert!(!shows(&screen, "1 note"));
+        drop(app);
+        notebook.clean_up();
+    }
+
+    #[test]
+    fn a_card_grows_with_the_note_after_an_edit() {
+        let mut notebook = Notebook::new("grow", &["first", "second"]);
+        let mut app = notebook.app();
+        assert!(shows(&screen(&mut app), "second"));
+        type_keys(&mut app, "jiothird line");
+        press(&mut app, KeyCode::Esc);
+        type_keys(&mut app, "W");
+
+        let screen = screen(&mut app);
+        assert!(shows(&screen, "third line"));
+        assert!(shows(&screen, "first"));
+        drop(app);
+        notebook.clean_up();
+    }
+
+    #[test]
+    fn q_quits_without_touching_the_terminal() {
+        let mut notebook = Notebook::new("quit", &[]);
+        let mut app = notebook.app();
+        type_keys(&mut app, "q");
+
+        assert!(app.quit);
+        drop(app);
+        notebook.clean_up();
+    }
+}
@@ -0,0 +1,43 @@
+//! Noticing when another process, like a second feednotes, `feednotes add`
+//! or a sync tool, writes to a notebook while the UI is open.
+
+use std::{
+    path::Path,
+    sync::mpsc::{self, Receiver},
+};
+
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::backend;
+
+pub struct Watch {
+    _watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<Event>>,
+}
+
+impl Watch {
+    /// Watches the notebooks in `dir`.
+    pub fn new(dir: &Path) -> notify::Result<Watch> {
+        let (tx, events) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(move |e| {
+            let _ = tx.send(e);
+        })?;
+        // the directory rather than the files, which saves replace
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+        Ok(Watch { _watcher: watcher, events })
+    }
+
+    /// Whether the files of notebook `path` were written to since the last
+    /// call, by this process or another.
+    pub fn changed(&self, path: &Path) -> bool {
+        let files = backend::open(path).watch();
+        let names: Vec<_> = files.iter().map(|f| f.file_name()).collect();
+        // all of the events are taken, not just up to the first match
+        let mut changed = false;
+        for e in self.events.try_iter().flatten() {
+            changed |= !e.kind.is_access()
+                && e.paths.iter().any(|p| names.contains(&p.file_name()));
+        }
+        changed
+    }
+}
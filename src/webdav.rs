@@ -0,0 +1,260 @@
+//! Sync through a WebDAV server, like Nextcloud, set up under `[webdav]` in
+//! the config. Each notebook is kept on the server as one JSON file in the
+//! folder at `url`, encrypted when the notes are, and the notebook on disk
+//! stays the one read and written between syncs, so everything works
+//! offline.
+//!
+//! A sync downloads the file only if its ETag changed since the last sync,
+//! merges the notes changed on the server into the notebook, and uploads the
+//! result on the condition that the file is still the one downloaded, so two
+//! machines syncing at once never overwrite each other. What the last sync
+//! saw is kept next to the notebook in `<file>.webdav`.
+//!
+//! The requests are made by [`curl`].
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    backend, config,
+    crypt::Cipher,
+    curl,
+    model::{extract_tags, Feed, NoteId},
+    store::{self, CONFLICT_TAG},
+};
+
+/// What the last sync of a notebook saw.
+#[derive(Default, Serialize, Deserialize)]
+struct State {
+    /// Of the file on the server, as uploaded or downloaded.
+    etag: Option<String>,
+    /// The revision of each note, on both sides.
+    revisions: HashMap<NoteId, u64>,
+}
+
+/// The status code and the ETag of a response.
+struct Response {
+    status: u16,
+    etag: Option<String>,
+}
+
+fn state_path(data_file: &Path) -> PathBuf {
+    let mut state = data_file.as_os_str().to_owned();
+    state.push(".webdav");
+    PathBuf::from(state)
+}
+
+/// The file downloaded from and uploaded to the server during a sync.
+fn copy_path(data_file: &Path) -> PathBuf {
+    let mut copy = data_file.as_os_str().to_owned();
+    copy.push(".remote");
+    PathBuf::from(copy)
+}
+
+/// The address of the notebook at `data_file` on the server.
+fn remote_url(
+    data_file: &Path,
+    settings: &config::Webdav,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let url = settings
+        .url
+        .as_deref()
+        .ok_or("set url under [webdav] in the config")?;
+    let name = store::notebook_name(data_file);
+    Ok(format!("{}/{}.json", url.trim_end_matches('/'), escape(&name)))
+}
+
+/// `name` with everything but unreserved characters percent-encoded.
+fn escape(name: &str) -> String {
+    name.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' => {
+                (b as char).to_string()
+            }
+            b => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Syncs the notebook at `data_file` with its file on the server, and
+/// returns how many notes changed on both sides since the last sync were
+/// kept twice, the version here tagged `#conflict`.
+pub fn sync(
+    data_file: &Path,
+    settings: &config::Webdav,
+    cipher: Option<&Cipher>,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    let url = remote_url(data_file, settings)?;
+    let state_path = state_path(data_file);
+    let state: State = match fs::read(&state_path) {
+        Ok(data) => serde_json::from_slice(&data)?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => State::default(),
+        Err(e) => return Err(e.into()),
+    };
+    let copy = copy_path(data_file);
+    let copy_arg = copy.to_string_lossy();
+
+    let mut options = vec![("output", copy_arg.to_string())];
+    if let Some(etag) = &state.etag {
+        options.push(("header", format!("If-None-Match: {}", etag)));
+    }
+    let got = request(settings, &url, &options)?;
+    let mut feed = store::load(data_file, cipher)?;
+    let mut conflicts = 0;
+    let (mut etag, on_server) = match got.status {
+        200 => {
+            let remote = backend::open(&copy).load(cipher)?;
+            let on_server = revisions(&remote);
+            conflicts = merge(&mut feed, remote, &state.revisions);
+            (got.etag, Some(on_server))
+        }
+        304 => (state.etag, Some(state.revisions)),
+        404 => (None, None),
+        status => return Err(failed("GET", &url, status)),
+    };
+    store::save(data_file, &mut feed, cipher)?;
+
+    let revisions = revisions(&feed);
+    if on_server.as_ref() != Some(&revisions) {
+        store::write(&copy, &feed, cipher)?;
+        let mut options = vec![("upload-file", copy_arg.to_string())];
+        match &etag {
+            Some(etag) => {
+                options.push(("header", format!("If-Match: {}", etag)))
+            }
+            None if on_server.is_none() => {
+                options.push(("header", "If-None-Match: *".into()))
+            }
+            None => {}
+        }
+        let put = request(settings, &url, &options)?;
+        match put.status {
+            200..=299 => etag = put.etag,
+            412 => {
+                return Err("the notes on the server changed during the \
+                            sync, sync again"
+                    .into())
+            }
+            status => return Err(failed("PUT", &url, status)),
+        }
+    }
+    fs::write(&state_path, serde_json::to_vec(&State { etag, revisions })?)?;
+    match fs::remove_file(&copy) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(conflicts),
+    }
+}
+
+fn failed(method: &str, url: &str, status: u16) -> Box<dyn std::error::Error> {
+    match status {
+        401 => "the WebDAV server refused the user and password".into(),
+        // as when the folder at url does not exist
+        409 => format!("webdav {} {}: no such folder", method, url).into(),
+        _ => format!("webdav {} {}: HTTP {}", method, url, status).into(),
+    }
+}
+
+fn revisions(feed: &Feed) -> HashMap<NoteId, u64> {
+    feed.notes.iter().map(|n| (n.id, n.revision)).collect()
+}
+
+/// Merges `remote`, the notes on the server, into `feed`, given the
+/// `synced` revision of each note at the last sync. A note changed on one
+/// side only takes that side's version, and a note removed on one side only
+/// is removed. A note changed on both keeps the server's version, and the
+/// one of `feed` is added next to it tagged `#conflict`, unless both have
+/// the same text. Returns how many were.
+fn merge(
+    feed: &mut Feed,
+    remote: Feed,
+    synced: &HashMap<NoteId, u64>,
+) -> usize {
+    feed.last_id = feed.last_id.max(remote.last_id);
+    let on_server: HashSet<NoteId> =
+        remote.notes.iter().map(|n| n.id).collect();
+    for i in (0..feed.notes.len()).rev() {
+        let note = &feed.notes[i];
+        if !on_server.contains(&note.id)
+            && synced.get(&note.id) == Some(&note.revision)
+        {
+            feed.remove(i);
+        }
+    }
+
+    let mut conflicts = 0;
+    for theirs in remote.notes {
+        let base = synced.get(&theirs.id).copied();
+        let Some(ours) = feed.notes.iter().find(|n| n.id == theirs.id) else {
+            // new on the server, or removed here but changed there since
+            if base != Some(theirs.revision) {
+                feed.put(theirs);
+            }
+            continue;
+        };
+        let mut ours = ours.clone();
+        if ours.date != theirs.date {
+            // a different note, given the same id on another machine
+            ours.id = feed.next_id();
+            feed.put(theirs);
+            feed.put(ours);
+        } else if base == Some(theirs.revision) {
+            // changed here only, if at all
+        } else if base == Some(ours.revision)
+            || ours.text == theirs.text && theirs.revision >= ours.revision
+        {
+            feed.put(theirs);
+        } else if ours.text != theirs.text {
+            ours.id = feed.next_id();
+            ours.text =
+                format!("{}\n\n#{}", ours.text.trim_end(), CONFLICT_TAG);
+            ours.tags = extract_tags(&ours.text);
+            feed.put(theirs);
+            feed.put(ours);
+            conflicts += 1;
+        }
+    }
+    conflicts
+}
+
+/// Runs `curl` on `url` with `options`, named as in its config files, and
+/// the credentials of `settings`.
+fn request(
+    settings: &config::Webdav,
+    url: &str,
+    options: &[(&str, String)],
+) -> Result<Response, Box<dyn std::error::Error>> {
+    let mut request = curl::Options::default();
+    request.push("url", url);
+    if let Some(user) = &settings.user {
+        let password = settings.password.as_deref().unwrap_or_default();
+        request.push("user", &format!("{}:{}", user, password));
+    }
+    for (name, value) in options {
+        request.push(name, value);
+    }
+    let args =
+        ["--silent", "--show-error", "--dump-header", "-", "--max-time", "60"];
+    let output =
+        curl::run(&args, &request).map_err(|e| format!("webdav: {}", e))?;
+
+    let headers = String::from_utf8_lossy(&output);
+    let mut response = Response { status: 0, etag: None };
+    for line in headers.lines() {
+        if line.starts_with("HTTP/") {
+            // a new response, after one like 100 Continue
+            let status = line.split_whitespace().nth(1);
+            response.status = status.and_then(|s| s.parse().ok()).unwrap_or(0);
+            response.etag = None;
+        } else if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("etag") {
+                response.etag = Some(value.trim().to_string());
+            }
+        }
+    }
+    Ok(response)
+}
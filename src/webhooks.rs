@@ -0,0 +1,47 @@
+//! Sending new notes to the addresses in `webhooks` in the config, for other
+//! services to act on. Each note is POSTed as JSON, in the form `feednotes
+//! dump` prints notes, by a `curl` left running on its own: nothing waits for
+//! it, and it retries a few times when the service cannot be reached or
+//! answers with an error.
+
+use std::thread;
+
+use crate::{curl, model::Note};
+
+/// How many times a failed delivery is tried again.
+const RETRIES: u32 = 3;
+
+/// POSTs `note` to each of `urls` in the background. A delivery that still
+/// fails after the retries is dropped.
+pub fn send(
+    urls: &[String],
+    note: &Note,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if urls.is_empty() {
+        return Ok(());
+    }
+    let json = serde_json::to_string(note)?;
+    let retries = RETRIES.to_string();
+    for url in urls {
+        let mut options = curl::Options::default();
+        options.push("url", url);
+        options.push("header", "Content-Type: application/json");
+        options.push("data-binary", &json);
+        let args = [
+            "--silent",
+            "--fail",
+            "--output",
+            "/dev/null",
+            "--retry",
+            &retries,
+            "--retry-all-errors",
+            "--max-time",
+            "30",
+        ];
+        let mut curl = curl::start(&args, &options)?;
+        // reaped once done; a command line run that exits first leaves curl
+        // to finish on its own
+        thread::spawn(move || curl.wait());
+    }
+    Ok(())
+}